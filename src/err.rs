@@ -62,6 +62,15 @@ pub enum AppError {
 
     #[error("Error during CSV write operation: {0:?}")]
     CsvError(#[source] csv::Error),
+
+    #[error("Error during Excel write operation: {0:?}")]
+    XlsxError(#[from] rust_xlsxwriter::XlsxError),
+
+    #[error("Error during Parquet write operation: {0:?}")]
+    ParquetError(#[from] parquet::errors::ParquetError),
+
+    #[error("Error when running the HTTP server at {1:?}")]
+    ServerError(#[source] std::io::Error, String),
 }
 
 
@@ -119,6 +128,13 @@ pub fn report_error(e: AppError) -> () {
         AppError::UserInputError(e) => print_simple_error (e.to_string(), "USER INPUT ERROR"),
     
         AppError::CsvError(e) => print_simple_error (e.to_string(), "CSV ERROR"),
+
+        AppError::XlsxError(e) => print_simple_error (e.to_string(), "EXCEL ERROR"),
+
+        AppError::ParquetError(e) => print_simple_error (e.to_string(), "PARQUET ERROR"),
+
+        AppError::ServerError(e, a) => print_error (e.to_string(),
+                        format!("Address was: {}", a), "HTTP SERVER ERROR"),
         
     }
 }
@@ -0,0 +1,245 @@
+use sqlx::{Pool, Postgres};
+use std::path::PathBuf;
+use crate::err::AppError;
+use chrono::Local;
+use super::export_structs::{ComparisonRow, CSVComparisonRow};
+use super::export_helpers::{append_to_file, get_hdr_line};
+use super::export_csv;
+use log::info;
+
+// Compares the stored summary data of two versions, metric by metric, across the
+// version summary, attribute, count distribution and singleton tables. Each metric
+// is listed with both values, the absolute change and the relative change (as a %age
+// of the first value). Changes with a relative size above the threshold, and any
+// categories or counts present in only one of the versions, are marked.
+
+pub async fn generate_comparison(output_folder : &PathBuf, vcode_1: &String, vcode_2: &String, threshold: f64,
+                                 inc_withdrawn: bool, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let rows = fetch_comparison_rows(vcode_1, vcode_2, inc_withdrawn, pool).await?;
+    let comp_rows: Vec<CSVComparisonRow> = rows.into_iter()
+            .map(|r| get_comparison_row(r, vcode_1, vcode_2, threshold)).collect();
+
+    // csv file
+
+    let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
+    let dv_string = if inc_withdrawn {format!("{vcode_1} vs {vcode_2} inc WD")} else {format!("{vcode_1} vs {vcode_2}")};
+    let output_file_name = format!("{} {} {}.csv", dv_string, "comparison", datetime_string);
+    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
+    export_csv::generate_file(&file_path, &comp_rows)?;
+
+    // text file
+
+    let datetime_string = Local::now().format("%Y-%m-%d %H-%M").to_string();
+    let inc_wd_tag = if inc_withdrawn {" (inc. withdrawn orgs)"} else {""};
+    let output_file_name = format!("{} vs {} comparison{} at {}.txt", vcode_1, vcode_2, inc_wd_tag, &datetime_string);
+    let output_file_path: PathBuf = [output_folder, &PathBuf::from(output_file_name)].iter().collect();
+
+    let num_flagged = comp_rows.iter().filter(|r| r.above_threshold).count();
+    let mut txt = format!("\n\n\tCOMPARISON OF ROR VERSIONS {} AND {}{}", vcode_1, vcode_2, inc_wd_tag);
+    txt += &format!("\n\n\tReport generated         {}", Local::now().format("%Y-%m-%d %H:%M:%S"));
+    txt += &format!("\n\tChange threshold         {}%", threshold);
+    txt += &format!("\n\tMetrics compared         {}", comp_rows.len());
+    txt += &format!("\n\tMarked (*) changes       {}", num_flagged);
+    txt += "\n\n\tChanges are shown as value 2 - value 1, and as a %age of value 1. Changes whose size is greater";
+    txt += "\n\tthan the threshold, or that involve an item present in only one version, are marked with a '*'.";
+    append_to_file(&output_file_path, &txt)?;
+
+    let mut current_section = "";
+    let mut section_txt = "".to_string();
+    for r in comp_rows.iter() {
+        if r.section != current_section {
+            append_to_file(&output_file_path, &section_txt)?;
+            current_section = &r.section;
+            section_txt = get_hdr_line(&current_section.to_uppercase());
+            section_txt += &get_comparison_hdr(vcode_1, vcode_2);
+        }
+        section_txt += &get_comparison_line(r);
+    }
+    append_to_file(&output_file_path, &section_txt)?;
+
+    info!("Content appended successfully");
+    Ok(())
+}
+
+
+async fn fetch_comparison_rows(vcode_1: &String, vcode_2: &String, inc_withdrawn: bool,
+                               pool : &Pool<Postgres>) -> Result<Vec<ComparisonRow>, AppError> {
+
+    // Each table is first 'unpivoted' into one row per metric, and the two versions then matched
+    // using a full outer join, so that items in only one version are still listed. The sort keys
+    // (s1 - s4) keep the rows in the same order as they are in the summary tables.
+
+    let sql = format!(r#"with m as (
+            select vs.vcode, 1 as s1, 0 as s2, 0 as s3, v.mid as s4,
+            'version summary'::varchar as section, ''::varchar as item, v.metric::varchar as metric, v.value
+            from smm.version_summaries vs
+            cross join lateral (values (1, 'num_recs', vs.num_recs::float8), (2, 'num_active', vs.num_active::float8),
+                    (3, 'num_inactive', vs.num_inactive::float8), (4, 'num_withdrawn', vs.num_withdrawn::float8),
+                    (5, 'num_denom', vs.num_denom::float8), (6, 'num_names', vs.num_names::float8),
+                    (7, 'num_types', vs.num_types::float8), (8, 'num_links', vs.num_links::float8),
+                    (9, 'num_ext_ids', vs.num_ext_ids::float8), (10, 'num_rels', vs.num_rels::float8),
+                    (11, 'num_locations', vs.num_locations::float8), (12, 'num_domains', vs.num_domains::float8))
+                    as v(mid, metric, value)
+            where vs.inc_wd = {inc_withdrawn} and vs.vcode in ('{vcode_1}', '{vcode_2}')
+            union all
+            select a.vcode, 2, a.att_id, a.cat_id, v.mid,
+            'attributes: ' || a.att_name, a.cat_name, v.metric, v.value
+            from smm.attributes_summary a
+            cross join lateral (values (1, 'number_cat', a.number_cat::float8), (2, 'pc_of_atts', round(a.pc_of_atts::numeric, 2)::float8),
+                    (3, 'number_orgs', a.number_orgs::float8), (4, 'pc_of_orgs', round(a.pc_of_orgs::numeric, 2)::float8))
+                    as v(mid, metric, value)
+            where a.inc_wd = {inc_withdrawn} and a.vcode in ('{vcode_1}', '{vcode_2}')
+            union all
+            select c.vcode, 3, c.count_id, c.count, v.mid,
+            'counts: ' || c.count_name, c.count::varchar, v.metric, v.value
+            from smm.count_distributions c
+            cross join lateral (values (1, 'num_of_orgs', c.num_of_orgs::float8), (2, 'pc_of_orgs', round(c.pc_of_orgs::numeric, 2)::float8))
+                    as v(mid, metric, value)
+            where c.inc_wd = {inc_withdrawn} and c.vcode in ('{vcode_1}', '{vcode_2}')
+            union all
+            select s.vcode, 4, 0, s.id, v.mid,
+            'singletons', s.name, v.metric, v.value
+            from smm.singletons s
+            cross join lateral (values (1, 'number', s.number::float8), (2, 'pc', round(s.pc::numeric, 2)::float8))
+                    as v(mid, metric, value)
+            where s.inc_wd = {inc_withdrawn} and s.vcode in ('{vcode_1}', '{vcode_2}')
+        )
+        select coalesce(a.section, b.section) as section, coalesce(a.item, b.item) as item,
+        coalesce(a.metric, b.metric) as metric, a.value as value_1, b.value as value_2
+        from (select * from m where vcode = '{vcode_1}') a
+        full outer join (select * from m where vcode = '{vcode_2}') b
+        on a.s1 = b.s1 and a.s2 = b.s2 and a.item = b.item and a.metric = b.metric
+        order by coalesce(a.s1, b.s1), coalesce(a.s2, b.s2), coalesce(a.s3, b.s3), coalesce(a.s4, b.s4);"#);
+
+    sqlx::query_as(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
+
+
+fn get_comparison_row(r: ComparisonRow, vcode_1: &str, vcode_2: &str, threshold: f64) -> CSVComparisonRow {
+
+    let (abs_change, rel_change_pc) = match (r.value_1, r.value_2) {
+        (Some(v1), Some(v2)) => {
+            let abs = ((v2 - v1) * 100.0).round() / 100.0;
+            let rel = if v1 != 0.0 { Some(((v2 - v1) * 10000.0 / v1.abs()).round() / 100.0) } else { None };
+            (Some(abs), rel)
+        },
+        _ => (None, None),
+    };
+
+    // A change from zero has no relative size, so is marked if non-zero.
+
+    let above_threshold = match (abs_change, rel_change_pc) {
+        (Some(_), Some(rel)) => rel.abs() > threshold,
+        (Some(abs), None) => abs != 0.0,
+        _ => r.value_1.is_some() != r.value_2.is_some(),
+    };
+
+    CSVComparisonRow {
+        vcode_1: vcode_1.to_string(),
+        vcode_2: vcode_2.to_string(),
+        section: r.section,
+        item: r.item,
+        metric: r.metric,
+        value_1: r.value_1,
+        value_2: r.value_2,
+        abs_change,
+        rel_change_pc,
+        above_threshold,
+    }
+}
+
+
+fn get_comparison_hdr(vcode_1: &str, vcode_2: &str) -> String {
+    format!("\n\n\t{}{:>12}{:>12}{:>12}{:>12}\n", " ".repeat(40), vcode_1, vcode_2, "change", "% change")
+}
+
+
+fn get_comparison_line(r: &CSVComparisonRow) -> String {
+
+    let topic = if r.item.is_empty() {r.metric.clone()} else {format!("{} - {}", r.item, r.metric)};
+    let topic: String = topic.chars().take(39).collect();
+    let spacer = " ".repeat(40 - topic.chars().count());
+    let marker = if r.above_threshold {"  *"} else {""};
+    format!("\n\t{topic}{spacer}{:>12}{:>12}{:>12}{:>12}{marker}",
+            fmt_value(r.value_1), fmt_value(r.value_2), fmt_value(r.abs_change), fmt_value(r.rel_change_pc))
+}
+
+
+fn fmt_value(v: Option<f64>) -> String {
+
+    // Whole numbers are shown without decimals, anything else (mostly percentages) to 2 places.
+
+    match v {
+        Some(n) if n.fract() == 0.0 => format!("{:.0}", n),
+        Some(n) => format!("{:.2}", n),
+        None => "--".to_string(),
+    }
+}
+
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compare(value_1: Option<f64>, value_2: Option<f64>, threshold: f64) -> CSVComparisonRow {
+        let r = ComparisonRow { section: "summary".to_string(), item: "".to_string(), metric: "num_recs".to_string(),
+                                value_1, value_2 };
+        get_comparison_row(r, "v1.50", "v1.51", threshold)
+    }
+
+    #[test]
+    fn check_change_from_zero() {
+        let r = compare(Some(0.0), Some(5.0), 10.0);
+        assert_eq!(r.abs_change, Some(5.0));
+        assert_eq!(r.rel_change_pc, None);
+        assert!(r.above_threshold);
+        let r = compare(Some(0.0), Some(0.0), 10.0);
+        assert_eq!(r.rel_change_pc, None);
+        assert!(!r.above_threshold);
+    }
+
+    #[test]
+    fn check_value_in_one_version_only() {
+        let r = compare(None, Some(12.0), 10.0);
+        assert_eq!((r.abs_change, r.rel_change_pc), (None, None));
+        assert!(r.above_threshold);
+        let r = compare(Some(12.0), None, 10.0);
+        assert!(r.above_threshold);
+        let r = compare(None, None, 10.0);
+        assert!(!r.above_threshold);
+    }
+
+    #[test]
+    fn check_equal_values() {
+        let r = compare(Some(250.0), Some(250.0), 10.0);
+        assert_eq!((r.abs_change, r.rel_change_pc), (Some(0.0), Some(0.0)));
+        assert!(!r.above_threshold);
+    }
+
+    #[test]
+    fn check_changes_around_the_threshold() {
+        let r = compare(Some(200.0), Some(220.0), 10.0);
+        assert_eq!(r.rel_change_pc, Some(10.0));
+        assert!(!r.above_threshold);
+        let r = compare(Some(200.0), Some(180.0), 10.0);
+        assert_eq!(r.rel_change_pc, Some(-10.0));
+        assert!(!r.above_threshold);
+        let r = compare(Some(200.0), Some(220.02), 10.0);
+        assert_eq!(r.rel_change_pc, Some(10.01));
+        assert!(r.above_threshold);
+    }
+
+    #[test]
+    fn check_changes_are_rounded() {
+        let r = compare(Some(3.0), Some(4.0), 10.0);
+        assert_eq!(r.abs_change, Some(1.0));
+        assert_eq!(r.rel_change_pc, Some(33.33));
+        let r = compare(Some(7.0), Some(7.123), 10.0);
+        assert_eq!(r.abs_change, Some(0.12));
+        assert_eq!(r.rel_change_pc, Some(1.76));
+    }
+}
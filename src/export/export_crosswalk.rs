@@ -0,0 +1,39 @@
+use sqlx::{Pool, Postgres};
+use std::fs;
+use std::path::PathBuf;
+use crate::err::AppError;
+use crate::process::crosswalk::{create_crosswalk_tables, ID_SCHEMES};
+use chrono::Local;
+use super::export_tables::copy_to_file;
+use log::info;
+
+// Writes the crosswalk between ROR ids and the GRID, ISNI, FundRef and Wikidata ids held in
+// ppr.external_ids as csv files: one with a row per organisation, giving the preferred and all
+// ids of each scheme, and one per scheme mapping each external id back to the organisation(s)
+// that hold it, with any value held by more than one organisation flagged as a collision.
+// The persistent ppr tables are rebuilt first, so that they reflect the current data, even if
+// that was processed before the tables were introduced.
+
+pub async fn generate_crosswalk(output_folder : &PathBuf, data_version: &String,
+                                pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    create_crosswalk_tables(pool).await?;
+
+    let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
+    let folder_name = format!("{} crosswalk {}", data_version, datetime_string);
+    let folder_path: PathBuf = [output_folder, &PathBuf::from(&folder_name)].iter().collect();
+    fs::create_dir_all(&folder_path)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e, folder_path.to_owned()))?;
+
+    let mut tables = vec!["crosswalk".to_string()];
+    tables.extend(ID_SCHEMES.iter().map(|(scheme, _)| format!("{scheme}_to_ror")));
+
+    for table in tables {
+        let order = if table == "crosswalk" {"id"} else {"id_value, id"};
+        let file_path: PathBuf = [&folder_path, &PathBuf::from(format!("{table}.csv"))].iter().collect();
+        let sql = format!("copy (select * from ppr.{table} order by {order}) to stdout with (format csv, header true);");
+        copy_to_file(&sql, &file_path, false, pool).await?;
+        info!("{} written", file_path.display());
+    }
+    Ok(())
+}
@@ -2,260 +2,52 @@ use sqlx::{Pool, Postgres};
 use std::path::PathBuf;
 use crate::err::AppError;
 use chrono::Local;
-use super::export_structs::{CSVSummaryRow, CSVAttributeRow, CSVDistribRow, CSVRankedRow, 
-                            CSVSingletonRow, CSVOrgAndLangRow, CSVOrgAndRelRow};
+use super::export_structs::CSVCandidateDupRow;
+use super::export_data::{self, SummaryData};
 use serde::Serialize;
 use super::export_helpers;
 
-pub async fn generate_csv(output_folder : &PathBuf, data_version: &String, 
+pub async fn generate_csv(output_folder : &PathBuf, data_version: &String,
                  inc_withdrawn: bool, pool : &Pool<Postgres> ) -> Result<(), AppError>
 {
     let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
-    let where_clause = format!("where vs.vcode = '{data_version}' and vs.inc_wd = {inc_withdrawn}");
     let dv_string = if inc_withdrawn {format!("{data_version} inc WD")} else {data_version.to_string()};
-    
-    // 1) Version Summary 
 
-    let output_file_name = format!("{} {} {}.csv", dv_string, "summary", datetime_string);
-    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
-
-    let sql = format!("SELECT vcode, inc_wd, vdate::text, vdays, num_recs, 
-                               num_active, num_inactive, num_withdrawn, num_denom, num_names, 
-                               num_types, num_links, num_ext_ids, num_rels, num_locations, num_domains 
-                               from smm.version_summaries vs
-                               {where_clause}");
-    let summ: CSVSummaryRow = sqlx::query_as(&sql).fetch_one(pool).await
-           .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
-    let summ_as_vec = vec![summ];
-    generate_file(&file_path, summ_as_vec)?;
-
-    
-    // 2) Attribute Summaries
-    
-    let output_file_name = format!("{} {} {}.csv", dv_string, "attributes", datetime_string);
-    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
-
-    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays, att_id, att_name, 
-                            cat_id, cat_name, number_cat, pc_of_atts, number_orgs, pc_of_orgs
-                            from smm.attributes_summary ss
-                            inner join smm.version_summaries vs 
-                            on vs.vcode = ss.vcode 
-                            {where_clause}
-                            order by att_id, cat_id;"#);
-
-    let att_rows: Vec<CSVAttributeRow> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
-    generate_file(&file_path, att_rows)?;
-
-   
-    // 3) Count distributions
-
-    let output_file_name = format!("{} {} {}.csv", dv_string, "counts", datetime_string);
-    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
-
-    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays, count_id,
-                            count_name, count, num_of_orgs, pc_of_orgs
-                            from smm.count_distributions ss
-                            inner join smm.version_summaries vs 
-                            on vs.vcode = ss.vcode 
-                            {where_clause}
-                            order by count_id, count;"#);
-
-    let cdist_rows: Vec<CSVDistribRow> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
-    generate_file(&file_path, cdist_rows)?;
-
-
-    // 4) Ranked count distributions
-
-    let output_file_name = format!("{} {} {}.csv", dv_string, "ranked_counts", datetime_string);
-    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
-
-    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays, dist_id,
-                            dist_name, rank, entity, number, pc_of_entities, pc_of_base_set
-                            from smm.ranked_distributions ss
-                            inner join smm.version_summaries vs 
-                            on vs.vcode = ss.vcode 
-                            {where_clause}
-                            order by dist_id, rank;"#);
-
-    let rdist_rows: Vec<CSVRankedRow> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
-    generate_file(&file_path, rdist_rows)?;
-
-
-    // 5) Singletons
-
-    let output_file_name = format!("{} {} {}.csv", dv_string, "singletons", datetime_string);
-    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
-    
-    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays, 
-                            id, name, description, number, pc
-                            from smm.singletons ss
-                            inner join smm.version_summaries vs 
-                            on vs.vcode = ss.vcode 
-                            {where_clause}
-                            order by id;"#);
-    
-    let sing_rows: Vec<CSVSingletonRow> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
-    generate_file(&file_path, sing_rows)?;
-
-
-    // 6) Org types and WOLC
-
-    let output_file_name = format!("{} {} {}.csv", dv_string, "orgtypes and names", datetime_string);
-    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
-   
-    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays, 
-                            name_type_id, name_type, org_type_id, org_type, 
-                            names_num, names_wlc, names_wolc, names_wlc_pc, names_wolc_pc
-                            from smm.org_type_and_lang_code ss
-                            inner join smm.version_summaries vs 
-                            on vs.vcode = ss.vcode 
-                            {where_clause}
-                            order by name_type_id, org_type_id;"#);
-
-    let orglang_rows: Vec<CSVOrgAndLangRow> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
-    generate_file(&file_path, orglang_rows)?;
-
-
-    // 7) Orgs types and relationships
-
-    let output_file_name = format!("{} {} {}.csv", dv_string, "orgtypes and relationships", datetime_string);
-    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
-
-    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays, 
-                            rel_type_id, rel_type, org_type_id, org_type, 
-                            ss.num_links, ss.num_orgs, num_orgs_total, num_orgs_pc 
-                            from smm.org_type_and_relationships ss
-                            inner join smm.version_summaries vs 
-                            on vs.vcode = ss.vcode 
-                            {where_clause}
-                            order by rel_type_id, org_type_id;"#);
-   
-    let orgrel_rows: Vec<CSVOrgAndRelRow> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;                               
-    generate_file(&file_path, orgrel_rows)?;
-
-    Ok(())
+    let data = export_data::fetch_version_data(data_version, inc_withdrawn, pool).await?;
+    let file_path = |topic: &str| -> PathBuf {
+        let output_file_name = format!("{} {} {}.csv", dv_string, topic, datetime_string);
+        [output_folder, &PathBuf::from(&output_file_name)].iter().collect()
+    };
+
+    generate_file(&file_path("summary"), &data.summaries)?;
+    generate_file(&file_path("attributes"), &data.attributes)?;
+    generate_file(&file_path("counts"), &data.counts)?;
+    generate_file(&file_path("ranked_counts"), &data.ranked)?;
+    write_remaining_files(&data, file_path)
 }
 
 
 pub async fn generate_all_versions_csv(output_folder : &PathBuf, inc_withdrawn: bool, pool : &Pool<Postgres>) -> Result<(), AppError>
 {
     let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
-    let where_clause = format!("where vs.inc_wd = {inc_withdrawn} and vs.vcode <> 'v1.57' ");
     let dv_string = if inc_withdrawn {"All versions inc WD"} else {"All versions"};
-    
-    // 1) Version Summary 
-
-    let output_file_name = format!("{} {} {}.csv", dv_string, "summary", datetime_string);
-    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
-
-    let sql = format!("SELECT vcode, inc_wd, vdate::text, vdays, num_recs, 
-                               num_active, num_inactive, num_withdrawn, num_denom, num_names, 
-                               num_types, num_links, num_ext_ids, num_rels, num_locations, num_domains 
-                               from smm.version_summaries vs 
-                               {where_clause} 
-                               order by vdays;");
-    let summs: Vec<CSVSummaryRow> = sqlx::query_as(&sql).fetch_all(pool).await
-           .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
-    generate_file(&file_path, summs)?;
-
-
-    // 2) Attribute Summaries
-    
-    let output_file_name = format!("{} {} {}.csv", dv_string, "attributes", datetime_string);
-    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
-    
-    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays, att_id, att_name, 
-                            cat_id, cat_name, number_cat, pc_of_atts, number_orgs, pc_of_orgs
-                            from smm.attributes_summary ss
-                            inner join smm.version_summaries vs 
-                            on vs.vcode = ss.vcode 
-                            {where_clause}
-                            order by att_id, cat_id, vs.vdays;"#);
-
-    let att_rows: Vec<CSVAttributeRow> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
-    generate_file(&file_path, att_rows)?;
-  
-
-    // 3) Count distributions
-
-    let output_file_name = format!("{} {} {}.csv", dv_string, "counts", datetime_string);
-    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
-
-    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays, 
-                            count_id, count_name, count, num_of_orgs, pc_of_orgs
-                            from smm.count_distributions ss
-                            inner join smm.version_summaries vs 
-                            on vs.vcode = ss.vcode 
-                            {where_clause} 
-                            order by count_id, count, vs.vdays;"#);
-
-    let cdist_rows: Vec<CSVDistribRow> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
-    generate_file(&file_path, cdist_rows)?;
-
-
-    // 4) Ranked count distributions
 
-    // a) languages 
+    let data = export_data::fetch_all_versions_data(inc_withdrawn, pool).await?;
+    let file_path = |topic: &str| -> PathBuf {
+        let output_file_name = format!("{} {} {}.csv", dv_string, topic, datetime_string);
+        [output_folder, &PathBuf::from(&output_file_name)].iter().collect()
+    };
 
-    let output_file_name = format!("{} {} {}.csv", dv_string, "ranked_languages", datetime_string);
-    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
-
-    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays, 
-                            dist_id, dist_name, rank, entity, number, pc_of_entities, pc_of_base_set
-                            from smm.ranked_distributions ss
-                            inner join smm.version_summaries vs 
-                            on vs.vcode = ss.vcode 
-                            {where_clause} 
-                            and dist_id = 1
-                            order by dist_id, rank, vs.vdays;"#);
-
-    let rdist_rows: Vec<CSVRankedRow> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
-    generate_file(&file_path, rdist_rows)?;
-
-    // b) scripts
+    generate_file(&file_path("summary"), &data.summaries)?;
+    generate_file(&file_path("attributes"), &data.attributes)?;
+    generate_file(&file_path("counts"), &data.counts)?;
 
-    let output_file_name = format!("{} {} {}.csv", dv_string, "ranked_scripts", datetime_string);
-    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
+    // Ranked count distributions are split into languages (dist_id 1), scripts (2) and countries (3).
 
-    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays, 
-                            dist_id, dist_name, rank, entity, number, pc_of_entities, pc_of_base_set
-                            from smm.ranked_distributions ss
-                            inner join smm.version_summaries vs 
-                            on vs.vcode = ss.vcode 
-                            {where_clause}
-                            and dist_id = 2
-                            order by dist_id, rank, vs.vdays;"#);
-
-    let rdist_rows: Vec<CSVRankedRow> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
-    generate_file(&file_path, rdist_rows)?;
-
-    // c) countries
-
-    let output_file_name = format!("{} {} {}.csv", dv_string, "ranked_countries", datetime_string);
-    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
-
-    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays, 
-                            dist_id, dist_name, rank, entity, number, pc_of_entities, pc_of_base_set
-                            from smm.ranked_distributions ss
-                            inner join smm.version_summaries vs 
-                            on vs.vcode = ss.vcode 
-                            {where_clause}
-                            and dist_id = 3
-                            order by dist_id, rank, vs.vdays;"#);
-
-    let rdist_rows: Vec<CSVRankedRow> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
-    generate_file(&file_path, rdist_rows)?;
+    for (dist_id, topic) in [(1, "ranked_languages"), (2, "ranked_scripts"), (3, "ranked_countries")] {
+        let rows: Vec<_> = data.ranked.iter().filter(|r| r.dist_id == dist_id).collect();
+        generate_file(&file_path(topic), &rows)?;
+    }
 
     export_helpers::set_up_country_grid(pool).await?;
 
@@ -263,67 +55,49 @@ pub async fn generate_all_versions_csv(output_folder : &PathBuf, inc_withdrawn:
     // Partly becaue the columns may vary for each extraction so it is difficult to construct a struct
     // for deserialisation.
 
+    write_remaining_files(&data, file_path)
+}
 
-    // 5) Singletons
-
-    let output_file_name = format!("{} {} {}.csv", dv_string, "singletons", datetime_string);
-    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
-    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays, 
-                            id, name, description, number, pc
-                            from smm.singletons ss
-                            inner join smm.version_summaries vs 
-                            on vs.vcode = ss.vcode 
-                            {where_clause}
-                            order by id, vs.vdays;"#);
-    
-    let sing_rows: Vec<CSVSingletonRow> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
-    generate_file(&file_path, sing_rows)?;
-
-
-    // 6) Org types and WOLC
 
-    let output_file_name = format!("{} {} {}.csv", dv_string, "orgtypes and names", datetime_string);
-    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
+fn write_remaining_files(data: &SummaryData, file_path: impl Fn(&str) -> PathBuf) -> Result<(), AppError> {
 
-    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays, 
-                            name_type_id, name_type, org_type_id, org_type, 
-                            names_num, names_wlc, names_wolc, names_wlc_pc, names_wolc_pc
-                            from smm.org_type_and_lang_code ss
-                            inner join smm.version_summaries vs 
-                            on vs.vcode = ss.vcode 
-                            {where_clause}
-                            order by name_type_id, org_type_id, vs.vdays;"#);
+    // Sections 5 to 10, which are the same for a single and all versions.
 
-    let orglang_rows: Vec<CSVOrgAndLangRow> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
-    generate_file(&file_path, orglang_rows)?;
+    generate_file(&file_path("singletons"), &data.singletons)?;
+    generate_file(&file_path("orgtypes and names"), &data.org_langs)?;
+    generate_file(&file_path("orgtypes and relationships"), &data.org_rels)?;
+    generate_file(&file_path("established years"), &data.established)?;
+    generate_file(&file_path("last modified months"), &data.last_modified)?;
+    generate_file(&file_path("country summaries"), &data.countries)?;
+    Ok(())
+}
 
 
-    // 7) Orgs types and relationships
+pub async fn generate_candidate_dups_csv(output_folder : &PathBuf, data_version: &String,
+                 pool : &Pool<Postgres> ) -> Result<(), AppError>
+{
+    // The candidate duplicate organisations, as identified during processing of the current version.
 
-    let output_file_name = format!("{} {} {}.csv", dv_string, "orgtypes and relationships", datetime_string);
+    let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
+    let output_file_name = format!("{} {} {}.csv", data_version, "candidate duplicates", datetime_string);
     let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
 
-        let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays, 
-                            rel_type_id, rel_type, org_type_id, org_type, 
-                            ss.num_links, ss.num_orgs, num_orgs_total, num_orgs_pc 
-                            from smm.org_type_and_relationships ss
-                            inner join smm.version_summaries vs 
-                            on vs.vcode = ss.vcode 
-                            {where_clause}
-                            order by rel_type_id, org_type_id, vs.vdays;"#);
-   
-    let orgrel_rows: Vec<CSVOrgAndRelRow> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;       
-    generate_file(&file_path, orgrel_rows)?;
+    let sql = r#"SELECT score, id1, ror_name1, country_code1, id2, ror_name2, country_code2,
+                            shared_names, shared_ext_ids, shared_domains, shared_websites,
+                            distance_km, existing_rel
+                            from rec.candidate_duplicates
+                            order by score desc, id1, id2;"#;
+
+    let dup_rows: Vec<CSVCandidateDupRow> = sqlx::query_as(sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    generate_file(&file_path, &dup_rows)?;
 
     Ok(())
 }
 
 
-fn generate_file<T: Serialize>(file_path: &PathBuf, data:Vec<T>) -> Result<(), AppError> {
-    
+pub fn generate_file<T: Serialize>(file_path: &PathBuf, data: &[T]) -> Result<(), AppError> {
+
     let mut wtr = csv::Writer::from_path(file_path)
                 .map_err(|e|AppError::CsvError(e))?;
 
@@ -331,9 +105,7 @@ fn generate_file<T: Serialize>(file_path: &PathBuf, data:Vec<T>) -> Result<(), A
         wtr.serialize(d)
         .map_err(|e|AppError::CsvError(e))?;
     }
-    
+
     wtr.flush()?;
     Ok(())
 }
-
-    
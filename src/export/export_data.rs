@@ -0,0 +1,180 @@
+use sqlx::{Pool, Postgres};
+use crate::err::AppError;
+use super::export_helpers::fetch_rows;
+use super::export_structs::{CSVSummaryRow, CSVAttributeRow, CSVDistribRow, CSVRankedRow,
+                            CSVSingletonRow, CSVOrgAndLangRow, CSVOrgAndRelRow,
+                            CSVEstablishedRow, CSVLastModRow, CSVCountryRow};
+
+// The rows of each of the smm tables, joined to the version summary data, for either
+// a single version or all versions. Used by each of the csv and xlsx (and any later) exports,
+// so that they all present exactly the same data.
+
+pub struct SummaryData {
+    pub summaries: Vec<CSVSummaryRow>,
+    pub attributes: Vec<CSVAttributeRow>,
+    pub counts: Vec<CSVDistribRow>,
+    pub ranked: Vec<CSVRankedRow>,
+    pub singletons: Vec<CSVSingletonRow>,
+    pub org_langs: Vec<CSVOrgAndLangRow>,
+    pub org_rels: Vec<CSVOrgAndRelRow>,
+    pub established: Vec<CSVEstablishedRow>,
+    pub last_modified: Vec<CSVLastModRow>,
+    pub countries: Vec<CSVCountryRow>,
+}
+
+
+pub async fn fetch_version_data(data_version: &String, inc_withdrawn: bool, pool : &Pool<Postgres>) -> Result<SummaryData, AppError> {
+
+    let where_clause = format!("where vs.vcode = '{data_version}' and vs.inc_wd = {inc_withdrawn}");
+    fetch_summary_data(&where_clause, "", pool).await
+}
+
+
+pub async fn fetch_all_versions_data(inc_withdrawn: bool, pool : &Pool<Postgres>) -> Result<SummaryData, AppError> {
+
+    // v1.57 is excluded, as it appears to be the same as v1.58, without the v2.1 geographical details.
+
+    let where_clause = format!("where vs.inc_wd = {inc_withdrawn} and vs.vcode <> 'v1.57' ");
+    fetch_summary_data(&where_clause, ", vs.vdays", pool).await
+}
+
+
+async fn fetch_summary_data(where_clause: &str, order_suffix: &str, pool : &Pool<Postgres>) -> Result<SummaryData, AppError> {
+
+    // 1) Version Summary
+
+    let sql = format!("SELECT vcode, inc_wd, vdate::text, vdays, num_recs,
+                               num_active, num_inactive, num_withdrawn, num_denom, num_names,
+                               num_types, num_links, num_ext_ids, num_rels, num_locations, num_domains
+                               from smm.version_summaries vs
+                               {where_clause}
+                               order by vdays;");
+    let summaries: Vec<CSVSummaryRow> = fetch_rows(&sql, &[], pool).await?;
+
+    // 2) Attribute Summaries
+
+    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays, att_id, att_name,
+                            cat_id, cat_name, number_cat, pc_of_atts, number_orgs, pc_of_orgs
+                            from smm.attributes_summary ss
+                            inner join smm.version_summaries vs
+                            on vs.vcode = ss.vcode
+                            {where_clause}
+                            order by att_id, cat_id{order_suffix};"#);
+    let attributes: Vec<CSVAttributeRow> = fetch_rows(&sql, &[], pool).await?;
+
+    // 3) Count distributions
+
+    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays,
+                            count_id, count_name, count, num_of_orgs, pc_of_orgs
+                            from smm.count_distributions ss
+                            inner join smm.version_summaries vs
+                            on vs.vcode = ss.vcode
+                            {where_clause}
+                            order by count_id, count{order_suffix};"#);
+    let counts: Vec<CSVDistribRow> = fetch_rows(&sql, &[], pool).await?;
+
+    // 4) Ranked count distributions
+
+    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays,
+                            dist_id, dist_name, rank, entity, number, pc_of_entities, pc_of_base_set
+                            from smm.ranked_distributions ss
+                            inner join smm.version_summaries vs
+                            on vs.vcode = ss.vcode
+                            {where_clause}
+                            order by dist_id, rank{order_suffix};"#);
+    let ranked: Vec<CSVRankedRow> = fetch_rows(&sql, &[], pool).await?;
+
+    // 5) Singletons
+
+    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays,
+                            id, name, description, number, pc
+                            from smm.singletons ss
+                            inner join smm.version_summaries vs
+                            on vs.vcode = ss.vcode
+                            {where_clause}
+                            order by id{order_suffix};"#);
+    let singletons: Vec<CSVSingletonRow> = fetch_rows(&sql, &[], pool).await?;
+
+    // 6) Org types and WOLC
+
+    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays,
+                            name_type_id, name_type, org_type_id, org_type,
+                            names_num, names_wlc, names_wolc, names_wlc_pc, names_wolc_pc
+                            from smm.org_type_and_lang_code ss
+                            inner join smm.version_summaries vs
+                            on vs.vcode = ss.vcode
+                            {where_clause}
+                            order by name_type_id, org_type_id{order_suffix};"#);
+    let org_langs: Vec<CSVOrgAndLangRow> = fetch_rows(&sql, &[], pool).await?;
+
+    // 7) Orgs types and relationships
+
+    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays,
+                            rel_type_id, rel_type, org_type_id, org_type,
+                            ss.num_links, ss.num_orgs, num_orgs_total, num_orgs_pc
+                            from smm.org_type_and_relationships ss
+                            inner join smm.version_summaries vs
+                            on vs.vcode = ss.vcode
+                            {where_clause}
+                            order by rel_type_id, org_type_id{order_suffix};"#);
+    let org_rels: Vec<CSVOrgAndRelRow> = fetch_rows(&sql, &[], pool).await?;
+
+    // 8) Established years
+
+    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays,
+                            grouping, group_code, group_name, num_orgs, num_with_year, pc_with_year,
+                            earliest, quartile_1, median, quartile_3, latest, num_pre_1800,
+                            num_1800_1899, num_1900_1949, num_1950_1999, num_2000_on
+                            from smm.established_years ss
+                            inner join smm.version_summaries vs
+                            on vs.vcode = ss.vcode
+                            and vs.inc_wd = ss.inc_wd
+                            {where_clause}
+                            order by grouping, group_code{order_suffix};"#);
+    let established: Vec<CSVEstablishedRow> = fetch_rows(&sql, &[], pool).await?;
+
+    // 9) Last modified months
+
+    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays,
+                            lm_month, lm_schema, num_orgs, pc_of_orgs
+                            from smm.last_modified_months ss
+                            inner join smm.version_summaries vs
+                            on vs.vcode = ss.vcode
+                            and vs.inc_wd = ss.inc_wd
+                            {where_clause}
+                            order by lm_month, lm_schema{order_suffix};"#);
+    let last_modified: Vec<CSVLastModRow> = fetch_rows(&sql, &[], pool).await?;
+
+    // 10) Country summaries
+
+    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays,
+                            ss.country_code, ss.country_name, ss.num_orgs, ss.pc_of_orgs, ss.num_active, ss.num_inactive, ss.num_withdrawn,
+                            ss.num_government, ss.num_education, ss.num_healthcare, ss.num_company, ss.num_nonprofit,
+                            ss.num_funder, ss.num_facility, ss.num_archive, ss.num_other,
+                            ss.num_names, ss.num_labels, ss.num_aliases, ss.num_acronyms, ss.num_names_wolc, ss.pc_names_wlc,
+                            ss.num_nacro, ss.num_nacro_wolc, ss.pc_nacro_wlc,
+                            ss.num_with_ext_ids, ss.pc_with_ext_ids, ss.num_with_isni, ss.num_with_grid, ss.num_with_fundref, ss.num_with_wikidata,
+                            ss.num_with_links, ss.pc_with_links, ss.num_with_website, ss.num_with_wikipedia,
+                            ss.num_parent_rels, ss.num_child_rels, ss.num_related_rels, ss.num_pred_rels, ss.num_succ_rels,
+                            ss.num_with_rels, ss.pc_with_rels, ss.num_with_domains, ss.pc_with_domains
+                            from smm.country_summaries ss
+                            inner join smm.version_summaries vs
+                            on vs.vcode = ss.vcode
+                            and vs.inc_wd = ss.inc_wd
+                            {where_clause}
+                            order by ss.country_code{order_suffix};"#);
+    let countries: Vec<CSVCountryRow> = fetch_rows(&sql, &[], pool).await?;
+
+    Ok(SummaryData {
+        summaries,
+        attributes,
+        counts,
+        ranked,
+        singletons,
+        org_langs,
+        org_rels,
+        established,
+        last_modified,
+        countries,
+    })
+}
@@ -0,0 +1,102 @@
+use sqlx::{Pool, Postgres};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use crate::err::AppError;
+use chrono::Local;
+use super::export_structs::GeoFeatureRow;
+use super::export_helpers::{float_col, get_org_filter_clause, OrgFilters};
+use log::info;
+
+// Writes the locations of the processed (ppr) organisations as a GeoJSON FeatureCollection,
+// for mapping, e.g. in QGIS or with web mapping libraries. Each feature is a point, for one
+// location of one organisation (most have only one), with the organisation's ROR id, name,
+// types and status, and the GeoNames id, name and country of the location, as properties.
+// Locations without coordinates are omitted. The features can be written to a single file, or
+// to one file per country, within a folder. The same filters as for graphs can be applied.
+
+pub async fn generate_geojson(output_folder : &PathBuf, data_version: &String, by_country: bool,
+                              filters: &OrgFilters, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let rows = fetch_feature_rows(filters, pool).await?;
+    info!("{} organisation locations selected", rows.len());
+
+    let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
+    let file_stem = format!("{} ror locations {}", data_version, datetime_string);
+
+    if by_country {
+
+        // Rows are ordered by country code, so each country's rows are consecutive.
+
+        let folder_path: PathBuf = [output_folder, &PathBuf::from(&file_stem)].iter().collect();
+        fs::create_dir_all(&folder_path)
+            .map_err(|e| AppError::IoWriteErrorWithPath(e, folder_path.to_owned()))?;
+
+        let mut num_files = 0;
+        for country_rows in rows.chunk_by(|a, b| a.country_code == b.country_code) {
+            let country = country_rows[0].country_code.as_deref().unwrap_or("unknown");
+            let file_path: PathBuf = [&folder_path, &PathBuf::from(format!("{country}.geojson"))].iter().collect();
+            write_feature_collection(&file_path, country_rows)?;
+            num_files += 1;
+        }
+        info!("{} country files written", num_files);
+    }
+    else {
+        let file_path: PathBuf = [output_folder, &PathBuf::from(format!("{file_stem}.geojson"))].iter().collect();
+        write_feature_collection(&file_path, &rows)?;
+    }
+    Ok(())
+}
+
+
+async fn fetch_feature_rows(filters: &OrgFilters, pool : &Pool<Postgres>) -> Result<Vec<GeoFeatureRow>, AppError> {
+
+    let sql = format!(r#"select c.id, c.ror_full_id, c.ror_name as name, s.name as status,
+                coalesce((select array_agg(t.name::text order by p.org_type) from ppr.type p
+                          inner join lup.ror_org_types t on p.org_type = t.id
+                          where p.id = c.id and p.org_type <> 1000), '{{}}') as types,
+                l.geonames_id, l.location, l.country_code, l.country_name,
+                {} as lat, {} as lng
+                from ppr.locations l
+                inner join ppr.core_data c on l.id = c.id
+                inner join lup.ror_status_types s on c.status = s.id
+                where l.lat is not null and l.lng is not null
+                and {}
+                order by l.country_code, c.id, l.geonames_id;"#,
+                float_col("l.lat"), float_col("l.lng"), get_org_filter_clause(filters));
+    sqlx::query_as(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
+
+
+fn write_feature_collection(file_path: &PathBuf, rows: &[GeoFeatureRow]) -> Result<(), AppError> {
+
+    // One feature per line, to keep the files readable and easy to process line by line.
+
+    let file = File::create(file_path)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.to_owned()))?;
+    let mut w = BufWriter::new(file);
+
+    let write_err = |e| AppError::IoWriteErrorWithPath(e, file_path.to_owned());
+    w.write_all(b"{\"type\": \"FeatureCollection\", \"features\": [\n").map_err(write_err)?;
+    for (i, r) in rows.iter().enumerate() {
+        let feature = serde_json::json!({
+            "type": "Feature",
+            "geometry": {"type": "Point", "coordinates": [r.lng, r.lat]},
+            "properties": {
+                "ror_id": r.ror_full_id,
+                "id": r.id,
+                "name": r.name,
+                "types": r.types,
+                "status": r.status,
+                "geonames_id": r.geonames_id,
+                "location": r.location,
+                "country_code": r.country_code,
+                "country_name": r.country_name,
+            }
+        });
+        let sep = if i > 0 {",\n"} else {""};
+        w.write_all(format!("{}{}", sep, serde_json::to_string(&feature)?).as_bytes()).map_err(write_err)?;
+    }
+    w.write_all(b"\n]}\n").and_then(|_| w.flush()).map_err(write_err)
+}
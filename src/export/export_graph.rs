@@ -0,0 +1,132 @@
+use sqlx::{Pool, Postgres};
+use std::path::PathBuf;
+use crate::err::AppError;
+use chrono::Local;
+use super::export_structs::{CSVGraphNodeRow, CSVGraphEdgeRow};
+use super::export_helpers::{append_to_file, escape_markup, get_org_filter_clause, OrgFilters};
+use super::export_csv;
+use log::info;
+
+// Writes the relationships between the processed (ppr) organisations as a directed graph, for
+// use in network analysis tools. Nodes are organisations, with their name, country, types and
+// status as attributes, and edges are relationships, with the relationship type as an attribute.
+// The nodes can be restricted to a country, an organisation type, and / or an organisation
+// together with all its descendants, and edges are only included if both of their nodes are.
+// Relationships are stored in both directions in ROR (e.g. 'has child' is matched by 'has parent'),
+// and both edges are retained, so that the graph reflects the data as provided.
+
+pub async fn generate_graph(output_folder : &PathBuf, data_version: &String, format: &str,
+                            filters: &OrgFilters, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let node_clause = get_org_filter_clause(filters);
+    let nodes = fetch_nodes(&node_clause, pool).await?;
+    let edges = fetch_edges(&node_clause, pool).await?;
+    info!("{} nodes and {} edges selected for the graph", nodes.len(), edges.len());
+
+    let mut filter_tag = "".to_string();
+    for f in [&filters.country, &filters.org_type, &filters.root] {
+        if !f.is_empty() {
+            filter_tag += &format!(" {f}");
+        }
+    }
+    let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
+    let file_stem = format!("{} ror graph{} {}", data_version, filter_tag, datetime_string);
+
+    match format {
+        "csv" => {
+            let file_path: PathBuf = [output_folder, &PathBuf::from(format!("{file_stem} nodes.csv"))].iter().collect();
+            export_csv::generate_file(&file_path, &nodes)?;
+            let file_path: PathBuf = [output_folder, &PathBuf::from(format!("{file_stem} edges.csv"))].iter().collect();
+            export_csv::generate_file(&file_path, &edges)?;
+        },
+        "dot" => {
+            let file_path: PathBuf = [output_folder, &PathBuf::from(format!("{file_stem}.dot"))].iter().collect();
+            append_to_file(&file_path, &get_dot(&nodes, &edges))?;
+        },
+        _ => {
+            let file_path: PathBuf = [output_folder, &PathBuf::from(format!("{file_stem}.graphml"))].iter().collect();
+            append_to_file(&file_path, &get_graphml(&nodes, &edges))?;
+        },
+    }
+    Ok(())
+}
+
+
+async fn fetch_nodes(node_clause: &str, pool : &Pool<Postgres>) -> Result<Vec<CSVGraphNodeRow>, AppError> {
+
+    let sql = format!(r#"select c.id, c.ror_name as name, c.country_code, s.name as status,
+                coalesce(string_agg(t.name, ', ' order by p.org_type) filter (where p.org_type <> 1000), '') as types
+                from ppr.core_data c
+                inner join lup.ror_status_types s on c.status = s.id
+                left join ppr.type p on c.id = p.id
+                left join lup.ror_org_types t on p.org_type = t.id
+                where {node_clause}
+                group by c.id, c.ror_name, c.country_code, s.name
+                order by c.id;"#);
+    sqlx::query_as(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
+
+
+async fn fetch_edges(node_clause: &str, pool : &Pool<Postgres>) -> Result<Vec<CSVGraphEdgeRow>, AppError> {
+
+    let sql = format!(r#"with nodes as (select c.id from ppr.core_data c where {node_clause})
+                select r.id as source, r.related_id as target, t.name as rel_type
+                from ppr.relationships r
+                inner join lup.ror_rel_types t on r.rel_type = t.id
+                where r.id in (select id from nodes)
+                and r.related_id in (select id from nodes)
+                order by r.id, r.rel_type, r.related_id;"#);
+    sqlx::query_as(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
+
+
+fn get_graphml(nodes: &[CSVGraphNodeRow], edges: &[CSVGraphEdgeRow]) -> String {
+
+    let mut s = r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="name" for="node" attr.name="name" attr.type="string"/>
+  <key id="country" for="node" attr.name="country" attr.type="string"/>
+  <key id="types" for="node" attr.name="types" attr.type="string"/>
+  <key id="status" for="node" attr.name="status" attr.type="string"/>
+  <key id="rel_type" for="edge" attr.name="rel_type" attr.type="string"/>
+  <graph id="ror" edgedefault="directed">
+"#.to_string();
+
+    for n in nodes {
+        s += &format!("    <node id=\"{}\">\n", escape_markup(&n.id));
+        s += &format!("      <data key=\"name\">{}</data>\n", escape_markup(&n.name));
+        s += &format!("      <data key=\"country\">{}</data>\n", escape_markup(n.country_code.as_deref().unwrap_or("")));
+        s += &format!("      <data key=\"types\">{}</data>\n", escape_markup(&n.types));
+        s += &format!("      <data key=\"status\">{}</data>\n", escape_markup(&n.status));
+        s += "    </node>\n";
+    }
+    for e in edges {
+        s += &format!("    <edge source=\"{}\" target=\"{}\">\n", escape_markup(&e.source), escape_markup(&e.target));
+        s += &format!("      <data key=\"rel_type\">{}</data>\n", escape_markup(&e.rel_type));
+        s += "    </edge>\n";
+    }
+    s + "  </graph>\n</graphml>\n"
+}
+
+
+fn get_dot(nodes: &[CSVGraphNodeRow], edges: &[CSVGraphEdgeRow]) -> String {
+
+    let mut s = "digraph ror {\n  node [shape=box];\n".to_string();
+    for n in nodes {
+        s += &format!("  \"{}\" [label=\"{}\", country=\"{}\", types=\"{}\", status=\"{}\"];\n",
+                      esc_dot(&n.id), esc_dot(&n.name), esc_dot(n.country_code.as_deref().unwrap_or("")),
+                      esc_dot(&n.types), esc_dot(&n.status));
+    }
+    for e in edges {
+        s += &format!("  \"{}\" -> \"{}\" [rel_type=\"{}\", label=\"{}\"];\n",
+                      esc_dot(&e.source), esc_dot(&e.target), esc_dot(&e.rel_type), esc_dot(&e.rel_type));
+    }
+    s + "}\n"
+}
+
+
+fn esc_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
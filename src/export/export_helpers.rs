@@ -1,10 +1,46 @@
-use sqlx::{Pool, Postgres};
+use sqlx::{Pool, Postgres, FromRow, postgres::PgRow};
 use crate::AppError;
 use std::fs::OpenOptions;
 use std::io::prelude::*;
-use super::export_structs::{Singleton, TypeRow, DistribRow, RankedRow, OrgAndLangCode, OrgAndRel}; 
+use super::export_structs::{Singleton, SingletonRow, TypeRow, DistribRow, RankedRow, OrgAndLangCode, OrgAndRel}; 
 use std::{collections::HashMap, path::PathBuf};
 
+// Filters that restrict the organisations included in an export (empty strings if not used).
+
+pub struct OrgFilters {
+    pub country: String,
+    pub org_type: String,
+    pub root: String,
+}
+
+// The details of a table's columns, as read from information_schema, used when tables
+// are transferred generically (e.g. to SQLite or Parquet).
+
+#[derive(sqlx::FromRow)]
+struct ColumnDetails {
+    table_name: String,
+    column_name: String,
+    data_type: String,
+    is_nullable: String,
+}
+
+#[derive(Clone, Copy)]
+pub enum ColKind {
+    Int,
+    BigInt,
+    Bool,
+    Float,
+    Date,
+    Timestamp,
+    Text,
+}
+
+pub struct TableDetails {
+    pub schema: String,
+    pub name: String,
+    pub columns: Vec<(String, ColKind, bool)>,   // name, kind, not null
+}
+
 #[derive(sqlx::FromRow)]
 struct CPars {
     cname: String,
@@ -113,16 +149,98 @@ pub fn get_orgrel_line(org_type: &str, rel_type: &str, num_links: i32, num_orgs:
 }
 
 
-pub async fn get_attrib_table(att_name: &str, header_type: &str, 
-                          vcode: &String,  inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<String, AppError> {
+pub async fn collect_singleton_values(vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<HashMap<String, Singleton>, AppError> {
+
+    let mut sstructs = HashMap::new();
+    let sql = format!(r#"SELECT name, description, number, pc from smm.singletons 
+              WHERE vcode = '{vcode}' and inc_wd = {inc_withdrawn};"#);
+    let srows: Vec<SingletonRow> = sqlx::query_as(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    
+    for r in srows { 
+        let s = Singleton {
+            description: r.description,
+            number: r.number,
+            pc: r.pc,
+        };
+        sstructs.insert(r.name, s);
+    }
+    Ok(sstructs)
+}
+
+
+// Row fetchers - used to construct the text tables below, and by the other report formats.
+
+pub async fn fetch_rows<T>(sql: &str, binds: &[&str], pool: &Pool<Postgres>) -> Result<Vec<T>, AppError>
+    where T: for<'r> FromRow<'r, PgRow> + Send + Unpin {
+
+    // Any values are bound, in order, to the query's $1, $2... parameters.
+
+    let mut query = sqlx::query_as(sql);
+    for b in binds {
+        query = query.bind(*b);
+    }
+    query.fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
+
+
+pub async fn fetch_attrib_rows(att_name: &str, vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<Vec<TypeRow>, AppError> {
 
     let sql = format!(r#"select cat_name, number_cat, pc_of_atts, number_orgs, pc_of_orgs 
             from smm.attributes_summary
             where vcode = '{vcode}' and inc_wd = {inc_withdrawn} and att_name = '{att_name}' 
             order by cat_id; "#);
-    let rows: Vec<TypeRow> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql))?;
+    sqlx::query_as(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql))
+}
 
+
+pub async fn fetch_distrib_rows(count_name: &str, vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<Vec<DistribRow>, AppError> {
+
+    let sql = format!(r#"select count, num_of_orgs, pc_of_orgs from smm.count_distributions
+            where vcode = '{vcode}' and inc_wd = {inc_withdrawn} and count_name = '{count_name}' 
+            order by count;"#);
+    sqlx::query_as(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql))
+}
+
+
+pub async fn fetch_ranked_rows(dist_name: &str, vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<Vec<RankedRow>, AppError> {
+
+    let sql = format!(r#"SELECT entity, number, pc_of_entities, pc_of_base_set from smm.ranked_distributions 
+            where vcode = '{vcode}' and inc_wd = {inc_withdrawn} and dist_name = '{dist_name}' 
+            order by rank; "#);
+    sqlx::query_as(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql))
+}
+
+
+pub async fn fetch_org_type_and_lang_code_rows(vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<Vec<OrgAndLangCode>, AppError> {
+
+    let sql = format!(r#"select org_type, name_type, names_num, names_wolc, names_wolc_pc 
+                from smm.org_type_and_lang_code where vcode = '{vcode}' and inc_wd = {inc_withdrawn} 
+                order by name_type_id, org_type_id;"#);
+    sqlx::query_as(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql))
+}
+
+
+pub async fn fetch_org_type_and_relationship_rows(vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<Vec<OrgAndRel>, AppError> {
+
+    let sql = format!(r#"select org_type, rel_type, num_links, num_orgs, num_orgs_pc 
+            from smm.org_type_and_relationships 
+            where vcode = '{vcode}' and inc_wd = {inc_withdrawn} 
+            order by rel_type_id, org_type_id;"#);
+    sqlx::query_as(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql))
+}
+
+
+pub async fn get_attrib_table(att_name: &str, header_type: &str, 
+                          vcode: &String,  inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<String, AppError> {
+
+    let rows = fetch_attrib_rows(att_name, vcode, inc_withdrawn, pool).await?;
     let mut rt_numbercat: i32 = 0;   // rt = running total
     let mut rt_numbercat_pc: f32 = 0.0;
     let mut tbl_text = format!("\n
@@ -147,12 +265,7 @@ pub async fn get_attrib_table(att_name: &str, header_type: &str,
 
 pub async fn get_distrib_table(count_name: &str, header_type: &str, vcode: &String, inc_withdrawn: bool, 
                                  pool: &Pool<Postgres>) -> Result<String, AppError> {
-    let sql = format!(r#"select count, num_of_orgs, pc_of_orgs from smm.count_distributions
-            where vcode = '{vcode}' and inc_wd = {inc_withdrawn} and count_name = '{count_name}' 
-            order by count;"#);
-    let rows: Vec<DistribRow> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql))?;
-
+    let rows = fetch_distrib_rows(count_name, vcode, inc_withdrawn, pool).await?;
     let mut rt_numberorgs: i32 = 0;
     let mut rt_numberorgs_pc: f32 = 0.0;
     let hdr_spacer = " ".repeat(33 - header_type.len());
@@ -173,11 +286,7 @@ pub async fn get_distrib_table(count_name: &str, header_type: &str, vcode: &Stri
 
 pub async fn get_ranked_distrib_table(dist_name: &str, vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<String, AppError> {
 
-    let sql = format!(r#"SELECT entity, number, pc_of_entities, pc_of_base_set from smm.ranked_distributions 
-            where vcode = '{vcode}' and inc_wd = {inc_withdrawn} and dist_name = '{dist_name}' 
-            order by rank; "#);
-    let lang_rows: Vec<RankedRow> = sqlx::query_as(&sql).fetch_all(pool).await
-            .map_err(|e| AppError::SqlxError(e, sql))?;
+    let lang_rows = fetch_ranked_rows(dist_name, vcode, inc_withdrawn, pool).await?;
 
     let mut tbl_text = "".to_string();
     let mut rt_numberents: i32 = 0;  // rt = running totals
@@ -197,12 +306,7 @@ pub async fn get_ranked_distrib_table(dist_name: &str, vcode: &String, inc_withd
 
 pub async fn get_org_type_and_lang_code_table(vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<String, AppError> {
 
-    let sql = format!(r#"select org_type, name_type, names_num, names_wolc, names_wolc_pc 
-                from smm.org_type_and_lang_code where vcode = '{vcode}' and inc_wd = {inc_withdrawn} 
-                order by name_type_id, org_type_id;"#);
-    let rows: Vec<OrgAndLangCode> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
-    
+    let rows = fetch_org_type_and_lang_code_rows(vcode, inc_withdrawn, pool).await?;
     let mut tbl_text = format!("\n\n
     Numbers of name types without language codes for different organisational types
         
@@ -224,13 +328,7 @@ pub async fn get_org_type_and_lang_code_table(vcode: &String, inc_withdrawn: boo
 
 pub async fn get_org_type_and_relationship_table(vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<String, AppError> {
 
-    let sql = format!(r#"select org_type, rel_type, num_links, num_orgs, num_orgs_pc 
-            from smm.org_type_and_relationships 
-            where vcode = '{vcode}' and inc_wd = {inc_withdrawn} 
-            order by rel_type_id, org_type_id;"#);
-    let rows: Vec<OrgAndRel> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql))?;                               
-    
+    let rows = fetch_org_type_and_relationship_rows(vcode, inc_withdrawn, pool).await?;
     let mut tbl_text = format!("\n
     Numbers of relationship links for different organisational types:
     
@@ -250,6 +348,99 @@ pub async fn get_org_type_and_relationship_table(vcode: &String, inc_withdrawn:
 }
 
 
+// The condition identifying the rec.names rows carried over to ppr.names. Names removed as
+// duplicates during processing have 'XX' in their change type id (see transfer_to_ppr).
+
+pub const NAME_IN_PPR: &str = "(change_type_id is null or change_type_id not like '%XX%')";
+
+
+pub fn get_ppr_names_sql() -> String {
+
+    // The names in ppr.names, with their type names. The language code in ppr.names is the
+    // combined one, i.e. the derived code if there is one, so the original ROR and derived
+    // codes are taken from the matching row in rec.names. No terminating semicolon, so that
+    // the query can also be used within a COPY statement.
+
+    format!(r#"select n.id, n.value, t.name as name_type, n.is_ror_name, n.lang_code,
+                r.ror_lang as ror_lang_code, r.der_lang as derived_lang_code, n.script_code
+                from ppr.names n
+                inner join lup.ror_name_types t on n.name_type = t.id
+                left join lateral
+                    (select ror_lang, der_lang from rec.names r
+                     where r.id = n.id and r.display_name = n.value and r.name_type = n.name_type
+                     and {NAME_IN_PPR}
+                     limit 1) r on true
+                order by n.id, n.is_ror_name desc, n.name_type, n.value"#)
+}
+
+
+pub fn get_org_filter_clause(filters: &OrgFilters) -> String {
+
+    // Returns a condition on c.id and c.country_code, i.e. on an organisation in ppr.core_data.
+    // The filter values have been checked when the parameters were read. A root organisation's
+    // descendants are found by following 'has child' links down, and 'has parent' links back up.
+
+    let mut conditions: Vec<String> = Vec::new();
+    if !filters.country.is_empty() {
+        conditions.push(format!("c.country_code = '{}'", filters.country));
+    }
+    if !filters.org_type.is_empty() {
+        conditions.push(format!(r#"c.id in (select p.id from ppr.type p
+                    inner join lup.ror_org_types t on p.org_type = t.id
+                    where t.name = '{}')"#, filters.org_type));
+    }
+    if !filters.root.is_empty() {
+        conditions.push(format!(r#"c.id in (with recursive subtree(id) as (
+                        select '{}'::varchar
+                        union
+                        select case when r.rel_type = 2 then r.related_id else r.id end
+                        from ppr.relationships r
+                        inner join subtree s
+                        on (r.rel_type = 2 and r.id = s.id) or (r.rel_type = 1 and r.related_id = s.id)
+                    )
+                    select id from subtree)"#, filters.root));
+    }
+    if conditions.is_empty() {
+        "true".to_string()
+    } else {
+        conditions.join("\n                and ")
+    }
+}
+
+
+pub async fn get_table_details(schema: &str, pool : &Pool<Postgres>) -> Result<Vec<TableDetails>, AppError> {
+
+    let sql = format!(r#"select c.table_name::text, c.column_name::text, c.data_type::text, c.is_nullable::text
+                from information_schema.columns c
+                inner join information_schema.tables t
+                on c.table_schema = t.table_schema and c.table_name = t.table_name
+                where c.table_schema = '{schema}' and t.table_type = 'BASE TABLE'
+                order by c.table_name, c.ordinal_position;"#);
+    let cols: Vec<ColumnDetails> = sqlx::query_as(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let mut tables: Vec<TableDetails> = Vec::new();
+    for c in cols {
+        let kind = match c.data_type.as_str() {
+            "integer" | "smallint" => ColKind::Int,
+            "bigint" => ColKind::BigInt,
+            "boolean" => ColKind::Bool,
+            "real" | "double precision" | "numeric" => ColKind::Float,
+            "date" => ColKind::Date,
+            "timestamp with time zone" | "timestamp without time zone" => ColKind::Timestamp,
+            _ => ColKind::Text,     // varchar, text
+        };
+        if tables.last().map(|t| t.name != c.table_name).unwrap_or(true) {
+            tables.push(TableDetails { schema: schema.to_string(), name: c.table_name.clone(), columns: Vec::new() });
+        }
+        if let Some(t) = tables.last_mut() {
+            t.columns.push((c.column_name, kind, c.is_nullable == "NO"));
+        }
+    }
+    Ok(tables)
+}
+
+
 pub fn append_to_file(output_file_path: &PathBuf, contents: &str) -> Result<(), AppError> {
 
     let mut file = OpenOptions::new()
@@ -345,4 +536,18 @@ pub async fn set_up_country_grid(pool: &Pool<Postgres>) -> Result<(), AppError>
 }
 
 
+pub fn float_col(col: &str) -> String {
+
+    // Coordinates and percentages are stored as reals (f32). Converting them to float8 via
+    // their shortest text form keeps values like 144.96257 from becoming 144.96257019042969.
 
+    format!("{col}::text::float8")
+}
+
+
+pub fn escape_markup(s: &str) -> String {
+
+    // Escapes the characters with special meanings in html and xml text and attribute values.
+
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,194 @@
+use sqlx::{Pool, Postgres};
+use std::{fs, path::PathBuf};
+use crate::AppError;
+use chrono::Local;
+use super::export_report::{build_report, ReportItem, ReportTable};
+use super::export_helpers::escape_markup;
+use log::info;
+
+// Produces the same sections as the text report, as a single html file. Styles and
+// charts (as inline SVG) are embedded, so the file has no external dependencies and
+// can be opened offline or sent as an attachment.
+
+const MAX_BARS: usize = 25;
+
+const STYLE: &str = r#"
+    body { font-family: Arial, Helvetica, sans-serif; font-size: 14px; color: #222; margin: 24px auto; max-width: 980px; }
+    h1 { font-size: 22px; border-bottom: 3px solid #2c5d8a; padding-bottom: 6px; }
+    h2 { font-size: 18px; color: #2c5d8a; border-bottom: 1px solid #2c5d8a; margin-top: 40px; }
+    h3 { font-size: 15px; margin-top: 24px; }
+    table { border-collapse: collapse; margin: 8px 0 16px 0; }
+    th, td { padding: 3px 10px; border-bottom: 1px solid #ddd; }
+    th { background: #eef3f8; text-align: left; }
+    td.num, th.num { text-align: right; }
+    tr.total td { font-weight: bold; border-top: 2px solid #888; }
+    p.note { font-size: 13px; color: #555; max-width: 760px; }
+    table.header td { border: none; padding: 2px 16px 2px 0; }
+    svg { display: block; margin: 4px 0 20px 0; }
+    svg text { font-family: Arial, Helvetica, sans-serif; font-size: 11px; fill: #333; }
+"#;
+
+
+pub async fn generate_html(output_folder : &PathBuf, vcode: &String,
+                           inc_withdrawn: bool, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let datetime_string = Local::now().format("%Y-%m-%d %H-%M").to_string();
+    let inc_wd_tag = if inc_withdrawn {" (inc. withdrawn orgs)"} else {""};
+    let output_file_name = format!("{} summary{} at {}.html", &vcode, inc_wd_tag, &datetime_string);
+    let output_file_path: PathBuf = [output_folder, &PathBuf::from(output_file_name)].iter().collect();
+
+    let items = build_report(vcode, inc_withdrawn, pool).await?;
+
+    let mut html = format!("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+                    <title>ROR {} summary{}</title>\n<style>{}</style>\n</head>\n<body>\n",
+                    escape_markup(vcode), escape_markup(inc_wd_tag), STYLE);
+    for item in &items {
+        html += &match item {
+            ReportItem::Title(t) => format!("<h1>{}</h1>\n", escape_markup(t)),
+            ReportItem::Section(t) => format!("<h2>{}</h2>\n", escape_markup(t)),
+            ReportItem::SubSection(t) => format!("<h3>{}</h3>\n", escape_markup(t)),
+            ReportItem::Note(t) => format!("<p class=\"note\">{}</p>\n", escape_markup(t)),
+            ReportItem::KeyValues(kvs) => get_header_table(kvs),
+            ReportItem::Table(t) => get_table(t),
+            ReportItem::BarChart(bars) => get_bar_chart(bars),
+            ReportItem::TrendChart(points) => get_trend_chart(points),
+        };
+    }
+    html += "</body>\n</html>\n";
+
+    fs::write(&output_file_path, html)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e, output_file_path.to_owned()))?;
+    info!("Content written successfully");
+    Ok(())
+}
+
+
+fn get_header_table(kvs: &[(String, String)]) -> String {
+
+    let mut tbl = "<table class=\"header\">\n".to_string();
+    for (label, value) in kvs {
+        tbl += &format!("<tr><td><b>{}</b></td><td>{}</td></tr>\n", escape_markup(label), escape_markup(value));
+    }
+    tbl += "</table>\n";
+    tbl
+}
+
+
+fn get_table(t: &ReportTable) -> String {
+
+    // The first text_cols columns are left aligned, the remainder (numbers) right aligned.
+
+    let class = |i: usize| if i < t.text_cols {""} else {" class=\"num\""};
+    let mut tbl = "<table>\n<tr>".to_string();
+    for (i, h) in t.headers.iter().enumerate() {
+        tbl += &format!("<th{}>{}</th>", class(i), escape_markup(h));
+    }
+    tbl += "</tr>\n";
+    for r in &t.rows {
+        tbl += "<tr>";
+        for (i, c) in r.iter().enumerate() {
+            tbl += &format!("<td{}>{}</td>", class(i), escape_markup(c));
+        }
+        tbl += "</tr>\n";
+    }
+    if let Some(total) = &t.total {
+        tbl += "<tr class=\"total\">";
+        for (i, c) in total.iter().enumerate() {
+            tbl += &format!("<td{}>{}</td>", class(i), escape_markup(c));
+        }
+        tbl += "</tr>\n";
+    }
+    tbl += "</table>\n";
+    tbl
+}
+
+
+fn get_bar_chart(bars: &[(String, f64)]) -> String {
+
+    // A horizontal bar chart, with at most MAX_BARS bars (the data is already in rank or count order).
+
+    if bars.is_empty() {
+        return "".to_string();
+    }
+    let bars = &bars[..bars.len().min(MAX_BARS)];
+    let max = bars.iter().map(|b| b.1).fold(0.0, f64::max).max(1.0);
+    let (label_width, bar_width, bar_height) = (200.0, 560.0, 18.0);
+    let height = bars.len() as f64 * bar_height + 8.0;
+
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" role=\"img\">\n",
+                          label_width + bar_width + 60.0, height);
+    for (i, (label, value)) in bars.iter().enumerate() {
+        let y = i as f64 * bar_height + 4.0;
+        let w = (value / max * bar_width).max(1.0);
+        svg += &format!("<text x=\"{:.0}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\
+                         <rect x=\"{:.0}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#4a7fb0\"><title>{}: {}</title></rect>\
+                         <text x=\"{:.1}\" y=\"{:.1}\">{}</text>\n",
+                         label_width - 6.0, y + 12.5, escape_markup(label),
+                         label_width, y + 2.0, w, bar_height - 4.0, escape_markup(label), value,
+                         label_width + w + 4.0, y + 12.5, value);
+    }
+    svg += "</svg>\n";
+    svg
+}
+
+
+fn get_trend_chart(points: &[(String, i32, f64)]) -> String {
+
+    // A line chart of (version code, days since 29/04/24, value) points, with the x axis
+    // scaled by days, so that the spacing between versions reflects the time between them.
+
+    let (width, height, left, right, top, bottom) = (760.0, 200.0, 70.0, 30.0, 12.0, 36.0);
+    let min_days = points.iter().map(|p| p.1).min().unwrap_or(0) as f64;
+    let max_days = points.iter().map(|p| p.1).max().unwrap_or(0) as f64;
+    let max_value = points.iter().map(|p| p.2).fold(0.0, f64::max).max(1.0) * 1.05;
+    let x = |d: i32| left + (d as f64 - min_days) / (max_days - min_days).max(1.0) * (width - left - right);
+    let y = |v: f64| top + (1.0 - v / max_value) * (height - top - bottom);
+
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" role=\"img\">\n");
+    svg += &format!("<line x1=\"{left}\" y1=\"{0}\" x2=\"{1}\" y2=\"{0}\" stroke=\"#888\"/>\
+                     <line x1=\"{left}\" y1=\"{top}\" x2=\"{left}\" y2=\"{0}\" stroke=\"#888\"/>\n",
+                     height - bottom, width - right);
+    svg += &format!("<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{:.0}</text><text x=\"{}\" y=\"{}\" text-anchor=\"end\">0</text>\n",
+                     left - 6.0, top + 4.0, max_value, left - 6.0, height - bottom + 4.0);
+
+    let line: Vec<String> = points.iter().map(|p| format!("{:.1},{:.1}", x(p.1), y(p.2))).collect();
+    svg += &format!("<polyline points=\"{}\" fill=\"none\" stroke=\"#4a7fb0\" stroke-width=\"2\"/>\n", line.join(" "));
+    for (vcode, days, value) in points {
+        svg += &format!("<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"#2c5d8a\"><title>{}: {}</title></circle>\n",
+                         x(*days), y(*value), escape_markup(vcode), value);
+    }
+    if let (Some(first), Some(last)) = (points.first(), points.last()) {
+        svg += &format!("<text x=\"{:.1}\" y=\"{}\" text-anchor=\"start\">{}</text><text x=\"{:.1}\" y=\"{}\" text-anchor=\"end\">{}</text>\n",
+                         x(first.1), height - bottom + 16.0, escape_markup(&first.0), x(last.1), height - bottom + 16.0, escape_markup(&last.0));
+    }
+    svg += "</svg>\n";
+    svg
+}
+
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_table_cells_are_escaped() {
+        let t = ReportTable {
+            headers: vec!["Name <x>".to_string(), "Number".to_string()],
+            text_cols: 1,
+            rows: vec![vec!["A | B & <C>".to_string(), "12".to_string()]],
+            total: Some(vec!["Total".to_string(), "12".to_string()]),
+        };
+        assert_eq!(get_table(&t), "<table>\n<tr><th>Name &lt;x&gt;</th><th class=\"num\">Number</th></tr>\n\
+                                   <tr><td>A | B &amp; &lt;C&gt;</td><td class=\"num\">12</td></tr>\n\
+                                   <tr class=\"total\"><td>Total</td><td class=\"num\">12</td></tr>\n</table>\n");
+    }
+
+    #[test]
+    fn check_header_values_are_escaped() {
+        let kvs = vec![("Version".to_string(), "v1.50 \"final\" & <b>".to_string())];
+        assert_eq!(get_header_table(&kvs), "<table class=\"header\">\n\
+                   <tr><td><b>Version</b></td><td>v1.50 &quot;final&quot; &amp; &lt;b&gt;</td></tr>\n</table>\n");
+    }
+}
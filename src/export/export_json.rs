@@ -0,0 +1,101 @@
+use sqlx::{Pool, Postgres};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use crate::err::AppError;
+use chrono::Local;
+use serde::Serialize;
+use super::export_structs::{CSVSummaryRow, CSVAttributeRow, CSVDistribRow, CSVRankedRow,
+                            CSVSingletonRow, CSVOrgAndLangRow, CSVOrgAndRelRow,
+                            CSVEstablishedRow, CSVLastModRow, CSVCountryRow};
+use super::export_data::{self, SummaryData};
+
+// The version of the structure of the json documents, as described in
+// '6_JSON Export Schema.md'. To be changed whenever that structure changes.
+
+pub const JSON_SCHEMA_VERSION: &str = "1.0";
+
+#[derive(Serialize)]
+struct VersionDocument<'a> {
+    schema_version: &'static str,
+    generated: String,
+    version_summary: &'a CSVSummaryRow,
+    attributes_summary: Vec<&'a CSVAttributeRow>,
+    count_distributions: Vec<&'a CSVDistribRow>,
+    ranked_distributions: Vec<&'a CSVRankedRow>,
+    singletons: Vec<&'a CSVSingletonRow>,
+    org_type_and_lang_code: Vec<&'a CSVOrgAndLangRow>,
+    org_type_and_relationships: Vec<&'a CSVOrgAndRelRow>,
+    established_years: Vec<&'a CSVEstablishedRow>,
+    last_modified_months: Vec<&'a CSVLastModRow>,
+    country_summaries: Vec<&'a CSVCountryRow>,
+}
+
+
+pub async fn generate_json(output_folder : &PathBuf, data_version: &String,
+                 inc_withdrawn: bool, pool : &Pool<Postgres> ) -> Result<(), AppError>
+{
+    // A single json object for the version.
+
+    let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
+    let dv_string = if inc_withdrawn {format!("{data_version} inc WD")} else {data_version.to_string()};
+    let output_file_name = format!("{} {} {}.json", dv_string, "summary", datetime_string);
+    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
+
+    let data = export_data::fetch_version_data(data_version, inc_withdrawn, pool).await?;
+    let docs = get_version_documents(&data);
+    match docs.first() {
+        Some(doc) => write_file(&file_path, doc),
+        None => Err(AppError::MissingVersion(data_version.to_string())),
+    }
+}
+
+
+pub async fn generate_all_versions_json(output_folder : &PathBuf, inc_withdrawn: bool, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // A json array, with an object for each version, in date order.
+
+    let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
+    let dv_string = if inc_withdrawn {"All versions inc WD"} else {"All versions"};
+    let output_file_name = format!("{} {} {}.json", dv_string, "summary", datetime_string);
+    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
+
+    let data = export_data::fetch_all_versions_data(inc_withdrawn, pool).await?;
+    let docs = get_version_documents(&data);
+    write_file(&file_path, &docs)
+}
+
+
+fn get_version_documents(data: &SummaryData) -> Vec<VersionDocument<'_>> {
+
+    // Each version summary row is combined with the rows from the other tables that share its
+    // version code (all rows in any one export have the same inc_wd value).
+
+    let generated = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    data.summaries.iter().map(|s| {
+        let v = s.vcode.as_str();
+        VersionDocument {
+            schema_version: JSON_SCHEMA_VERSION,
+            generated: generated.clone(),
+            version_summary: s,
+            attributes_summary: data.attributes.iter().filter(|r| r.vcode == v).collect(),
+            count_distributions: data.counts.iter().filter(|r| r.vcode == v).collect(),
+            ranked_distributions: data.ranked.iter().filter(|r| r.vcode == v).collect(),
+            singletons: data.singletons.iter().filter(|r| r.vcode == v).collect(),
+            org_type_and_lang_code: data.org_langs.iter().filter(|r| r.vcode == v).collect(),
+            org_type_and_relationships: data.org_rels.iter().filter(|r| r.vcode == v).collect(),
+            established_years: data.established.iter().filter(|r| r.vcode == v).collect(),
+            last_modified_months: data.last_modified.iter().filter(|r| r.vcode == v).collect(),
+            country_summaries: data.countries.iter().filter(|r| r.vcode == v).collect(),
+        }
+    }).collect()
+}
+
+
+fn write_file<T: Serialize>(file_path: &PathBuf, data: &T) -> Result<(), AppError> {
+
+    let file = File::create(file_path)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.to_owned()))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), data)?;
+    Ok(())
+}
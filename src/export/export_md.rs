@@ -0,0 +1,105 @@
+use sqlx::{Pool, Postgres};
+use std::{fs, path::PathBuf};
+use crate::AppError;
+use chrono::Local;
+use super::export_report::{build_report, ReportItem, ReportTable};
+use super::export_helpers::escape_markup;
+use log::info;
+
+// Produces the same sections as the text report, as a GitHub flavoured Markdown file,
+// so that it can be pasted directly into wiki pages and similar documents. Bar charts
+// are omitted (the tables above them hold the same data) and trends are shown as tables.
+
+pub async fn generate_md(output_folder : &PathBuf, vcode: &String,
+                         inc_withdrawn: bool, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let datetime_string = Local::now().format("%Y-%m-%d %H-%M").to_string();
+    let inc_wd_tag = if inc_withdrawn {" (inc. withdrawn orgs)"} else {""};
+    let output_file_name = format!("{} summary{} at {}.md", &vcode, inc_wd_tag, &datetime_string);
+    let output_file_path: PathBuf = [output_folder, &PathBuf::from(output_file_name)].iter().collect();
+
+    let items = build_report(vcode, inc_withdrawn, pool).await?;
+
+    let mut md = String::new();
+    for item in &items {
+        md += &match item {
+            ReportItem::Title(t) => format!("# {}\n\n", t),
+            ReportItem::Section(t) => format!("## {}\n\n", t),
+            ReportItem::SubSection(t) => format!("### {}\n\n", t),
+            ReportItem::Note(t) => format!("> {}\n\n", t),
+            ReportItem::KeyValues(kvs) => {
+                let rows = kvs.iter().map(|(k, v)| vec![k.clone(), v.clone()]).collect();
+                get_table(&ReportTable { headers: vec!["".to_string(), "".to_string()], text_cols: 2, rows, total: None })
+            },
+            ReportItem::Table(t) => get_table(t),
+            ReportItem::BarChart(_) => "".to_string(),
+            ReportItem::TrendChart(points) => {
+                let rows = points.iter().map(|(vcode, days, value)| vec![vcode.clone(), days.to_string(), value.to_string()]).collect();
+                get_table(&ReportTable { headers: vec!["Version".to_string(), "Days since 29/04/24".to_string(), "Number".to_string()],
+                                         text_cols: 1, rows, total: None })
+            },
+        };
+    }
+
+    fs::write(&output_file_path, md)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e, output_file_path.to_owned()))?;
+    info!("Content written successfully");
+    Ok(())
+}
+
+
+fn get_table(t: &ReportTable) -> String {
+
+    // The first text_cols columns are left aligned, the remainder (numbers) right aligned.
+    // Totals are shown in bold, as the final row of the table.
+
+    let mut tbl = format!("| {} |\n|", t.headers.iter().map(|h| esc(h)).collect::<Vec<_>>().join(" | "));
+    for i in 0..t.headers.len() {
+        tbl += if i < t.text_cols {" --- |"} else {" ---: |"};
+    }
+    tbl += "\n";
+    for r in &t.rows {
+        tbl += &format!("| {} |\n", r.iter().map(|c| esc(c)).collect::<Vec<_>>().join(" | "));
+    }
+    if let Some(total) = &t.total {
+        let cells: Vec<String> = total.iter().map(|c| if c.is_empty() {"".to_string()} else {format!("**{}**", esc(c))}).collect();
+        tbl += &format!("| {} |\n", cells.join(" | "));
+    }
+    tbl += "\n";
+    tbl
+}
+
+
+fn esc(s: &str) -> String {
+
+    // GitHub flavoured Markdown passes html through, so markup characters are escaped as
+    // well as the pipes that would otherwise split a table cell.
+
+    escape_markup(s).replace('|', "\\|")
+}
+
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_table_cells_are_escaped() {
+        let t = ReportTable {
+            headers: vec!["Name".to_string(), "Number".to_string()],
+            text_cols: 1,
+            rows: vec![vec!["A | B & <C>".to_string(), "12".to_string()]],
+            total: Some(vec!["Total".to_string(), "".to_string()]),
+        };
+        assert_eq!(get_table(&t), "| Name | Number |\n| --- | ---: |\n| A \\| B &amp; &lt;C&gt; | 12 |\n| **Total** |  |\n\n");
+    }
+
+    #[test]
+    fn check_cell_text_is_escaped() {
+        assert_eq!(esc("a|b||c"), "a\\|b\\|\\|c");
+        assert_eq!(esc("no pipes"), "no pipes");
+        assert_eq!(esc("R&D <lab>"), "R&amp;D &lt;lab&gt;");
+    }
+}
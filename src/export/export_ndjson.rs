@@ -0,0 +1,128 @@
+use sqlx::{Pool, Postgres};
+use futures_util::TryStreamExt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use crate::err::AppError;
+use chrono::Local;
+use super::export_helpers::float_col;
+use log::info;
+
+// Writes the processed (ppr) organisations as a search engine bulk file, i.e. newline delimited
+// json (NDJSON) in the format used by the Elasticsearch / OpenSearch _bulk API, with an 'index'
+// action line followed by a document line for each organisation. Each document is denormalised,
+// combining the organisation's core data, names (with language and script codes), types,
+// locations (with coordinates as a geo_point style object), external ids and links (grouped by
+// type), related organisation ids (by relationship type), domains, and the counts and dates in
+// ppr.admin_data. The documents are built by Postgres and streamed to the file.
+
+#[derive(sqlx::FromRow)]
+struct DocRow {
+    id: String,
+    ror_full_id: String,
+    doc: String,
+}
+
+
+pub async fn generate_ndjson(output_folder : &PathBuf, data_version: &String, index_name: &str,
+                             id_field: &str, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
+    let output_file_name = format!("{} ror bulk {} {}.ndjson", data_version, index_name, datetime_string);
+    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
+
+    let file = File::create(&file_path)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.to_owned()))?;
+    let mut w = BufWriter::new(file);
+
+    let sql = get_document_sql();
+    let mut stream = sqlx::query_as::<_, DocRow>(&sql).fetch(pool);
+    let mut num_docs = 0;
+    while let Some(r) = stream.try_next().await
+                            .map_err(|e| AppError::SqlxError(e, sql.to_string()))? {
+
+        // The document id can be the short or full form of the ROR id, or be
+        // omitted, in which case the search engine generates one.
+
+        let action = match id_field {
+            "none" => serde_json::json!({"index": {"_index": index_name}}),
+            "ror_id" => serde_json::json!({"index": {"_index": index_name, "_id": r.ror_full_id}}),
+            _ => serde_json::json!({"index": {"_index": index_name, "_id": r.id}}),
+        };
+        writeln!(w, "{}\n{}", action, r.doc)
+            .map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.to_owned()))?;
+        num_docs += 1;
+    }
+    w.flush()
+        .map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.to_owned()))?;
+
+    info!("{} documents written to bulk file", num_docs);
+    Ok(())
+}
+
+
+fn get_document_sql() -> String {
+
+    // json (rather than jsonb) is used, so that keys keep the order given. The admin data
+    // is the whole ppr.admin_data row, apart from the id and name already in the document.
+
+    format!(r#"select c.id, c.ror_full_id, json_build_object(
+            'id', c.id,
+            'ror_id', c.ror_full_id,
+            'name', c.ror_name,
+            'status', s.name,
+            'established', c.established,
+            'types', coalesce((select json_agg(t.name order by p.org_type)
+                        from ppr.type p
+                        inner join lup.ror_org_types t on p.org_type = t.id
+                        where p.id = c.id and p.org_type <> 1000), '[]'),
+            'names', coalesce((select json_agg(json_build_object('value', n.value, 'type', t.name,
+                        'is_ror_name', n.is_ror_name, 'lang', n.lang_code, 'script', n.script_code)
+                        order by n.is_ror_name desc, n.name_type, n.value)
+                        from ppr.names n
+                        inner join lup.ror_name_types t on n.name_type = t.id
+                        where n.id = c.id), '[]'),
+            'locations', coalesce((select json_agg(json_build_object('geonames_id', l.geonames_id,
+                        'name', l.location, 'subdivision_code', l.csubdiv_code, 'subdivision_name', l.csubdiv_name,
+                        'country_code', l.country_code, 'country_name', l.country_name,
+                        'continent_code', l.cont_code, 'continent_name', l.cont_name,
+                        'coordinates', case when l.lat is not null and l.lng is not null
+                            then json_build_object('lat', {}, 'lon', {}) end)
+                        order by l.geonames_id)
+                        from ppr.locations l
+                        where l.id = c.id), '[]'),
+            'external_ids', coalesce((select json_object_agg(x.id_type, x.ids order by x.id_type)
+                        from (select t.name as id_type, json_build_object(
+                                'preferred', min(e.id_value) filter (where e.is_preferred),
+                                'all', json_agg(e.id_value order by e.is_preferred desc, e.id_value)) as ids
+                              from ppr.external_ids e
+                              inner join lup.ror_id_types t on e.id_type = t.id
+                              where e.id = c.id
+                              group by t.name) x), '{{}}'),
+            'links', coalesce((select json_object_agg(x.link_type, x.links order by x.link_type)
+                        from (select t.name as link_type, json_agg(k.link order by k.link) as links
+                              from ppr.links k
+                              inner join lup.ror_link_types t on k.link_type = t.id
+                              where k.id = c.id
+                              group by t.name) x), '{{}}'),
+            'parent_ids', coalesce((select json_agg(r.related_id order by r.related_id)
+                        from ppr.relationships r where r.id = c.id and r.rel_type = 1), '[]'),
+            'child_ids', coalesce((select json_agg(r.related_id order by r.related_id)
+                        from ppr.relationships r where r.id = c.id and r.rel_type = 2), '[]'),
+            'related_ids', coalesce((select json_agg(r.related_id order by r.related_id)
+                        from ppr.relationships r where r.id = c.id and r.rel_type = 3), '[]'),
+            'predecessor_ids', coalesce((select json_agg(r.related_id order by r.related_id)
+                        from ppr.relationships r where r.id = c.id and r.rel_type = 4), '[]'),
+            'successor_ids', coalesce((select json_agg(r.related_id order by r.related_id)
+                        from ppr.relationships r where r.id = c.id and r.rel_type = 5), '[]'),
+            'domains', coalesce((select json_agg(d.domain order by d.domain)
+                        from ppr.domains d where d.id = c.id), '[]'),
+            'admin', (select json_object_agg(j.key, j.value)
+                        from json_each(row_to_json(a)) j
+                        where j.key not in ('id', 'ror_name'))
+        )::text as doc
+        from ppr.core_data c
+        inner join lup.ror_status_types s on c.status = s.id
+        left join ppr.admin_data a on c.id = a.id
+        order by c.id;"#, float_col("l.lat"), float_col("l.lng"))
+}
@@ -0,0 +1,159 @@
+use sqlx::{Pool, Postgres, Row, postgres::PgRow};
+use futures_util::TryStreamExt;
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_array::builder::{BooleanBuilder, Date32Builder, Float64Builder, Int32Builder, Int64Builder,
+                           StringBuilder, TimestampMicrosecondBuilder};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::sync::Arc;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use crate::err::AppError;
+use super::export_helpers::{float_col, get_table_details, ColKind, TableDetails};
+use log::info;
+
+// Writes each ppr table, and each smm table (with the data for all versions), to a Parquet
+// file, for use in analytics tools. Column types are retained: integers, booleans, dates and
+// timestamps keep their Postgres types, reals and doubles (e.g. lat / lng and percentages) become
+// 64 bit floats, and other columns strings. Rows are read from Postgres as a stream, and written
+// out in batches of a fixed size, so that memory use does not grow with the size of the table.
+
+const BATCH_SIZE: usize = 50000;
+
+
+pub async fn generate_parquet(output_folder : &PathBuf, data_version: &String,
+                              pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
+    let folder_name = format!("{} parquet {}", data_version, datetime_string);
+    let folder_path: PathBuf = [output_folder, &PathBuf::from(&folder_name)].iter().collect();
+    fs::create_dir_all(&folder_path)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e, folder_path.to_owned()))?;
+
+    for schema in ["ppr", "smm"] {
+        for table in get_table_details(schema, pool).await? {
+            let file_path: PathBuf = [&folder_path, &PathBuf::from(format!("{}_{}.parquet", table.schema, table.name))].iter().collect();
+            let n = write_table(&table, &file_path, pool).await?;
+            info!("{} records written to {}_{}.parquet", n, table.schema, table.name);
+        }
+    }
+    Ok(())
+}
+
+
+async fn write_table(table: &TableDetails, file_path: &PathBuf, pool : &Pool<Postgres>) -> Result<usize, AppError> {
+
+    let fields: Vec<Field> = table.columns.iter().map(|(n, k, nn)| {
+        let dt = match k {
+            ColKind::Int => DataType::Int32,
+            ColKind::BigInt => DataType::Int64,
+            ColKind::Bool => DataType::Boolean,
+            ColKind::Float => DataType::Float64,
+            ColKind::Date => DataType::Date32,
+            ColKind::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            ColKind::Text => DataType::Utf8,
+        };
+        Field::new(n, dt, !nn)
+    }).collect();
+    let schema = Arc::new(Schema::new(fields));
+
+    let file = File::create(file_path)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.to_owned()))?;
+    let props = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+    let mut writer = ArrowWriter::try_new(file, schema.clone(), Some(props))?;
+
+    // Values are cast in Postgres where necessary, so each can be read with a single Rust type
+    // (reals become float8, see float_col, and other text-like types become text).
+
+    let select_cols: Vec<String> = table.columns.iter().map(|(n, k, _)| match k {
+        ColKind::Float => float_col(&format!("\"{n}\"")),
+        ColKind::Timestamp => format!("\"{n}\"::timestamptz"),
+        ColKind::Text => format!("\"{n}\"::text"),
+        _ => format!("\"{n}\""),
+    }).collect();
+    let sql = format!("select {} from {}.{};", select_cols.join(", "), table.schema, table.name);
+
+    let mut stream = sqlx::query(&sql).fetch(pool);
+    let mut rows: Vec<PgRow> = Vec::with_capacity(BATCH_SIZE);
+    let mut num_rows = 0;
+    loop {
+        let row = stream.try_next().await
+            .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+        let at_end = row.is_none();
+        if let Some(r) = row {
+            rows.push(r);
+        }
+        if rows.len() == BATCH_SIZE || (at_end && !rows.is_empty()) {
+            let batch = get_record_batch(table, schema.clone(), &rows, &sql)?;
+            writer.write(&batch)?;
+            num_rows += rows.len();
+            rows.clear();
+        }
+        if at_end {
+            break;
+        }
+    }
+    writer.close()?;
+    Ok(num_rows)
+}
+
+
+fn get_record_batch(table: &TableDetails, schema: Arc<Schema>, rows: &[PgRow], sql: &str) -> Result<RecordBatch, AppError> {
+
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap_or_default();
+    let err = |e| AppError::SqlxError(e, sql.to_string());
+    let mut columns: Vec<ArrayRef> = Vec::new();
+
+    for (i, (_, k, _)) in table.columns.iter().enumerate() {
+        let col: ArrayRef = match k {
+            ColKind::Int => {
+                let mut b = Int32Builder::with_capacity(rows.len());
+                for r in rows { b.append_option(r.try_get::<Option<i32>, _>(i).map_err(err)?); }
+                Arc::new(b.finish())
+            },
+            ColKind::BigInt => {
+                let mut b = Int64Builder::with_capacity(rows.len());
+                for r in rows { b.append_option(r.try_get::<Option<i64>, _>(i).map_err(err)?); }
+                Arc::new(b.finish())
+            },
+            ColKind::Bool => {
+                let mut b = BooleanBuilder::with_capacity(rows.len());
+                for r in rows { b.append_option(r.try_get::<Option<bool>, _>(i).map_err(err)?); }
+                Arc::new(b.finish())
+            },
+            ColKind::Float => {
+                let mut b = Float64Builder::with_capacity(rows.len());
+                for r in rows { b.append_option(r.try_get::<Option<f64>, _>(i).map_err(err)?); }
+                Arc::new(b.finish())
+            },
+            ColKind::Date => {
+                let mut b = Date32Builder::with_capacity(rows.len());
+                for r in rows {
+                    let d = r.try_get::<Option<NaiveDate>, _>(i).map_err(err)?;
+                    b.append_option(d.map(|d| (d - epoch).num_days() as i32));
+                }
+                Arc::new(b.finish())
+            },
+            ColKind::Timestamp => {
+                let mut b = TimestampMicrosecondBuilder::with_capacity(rows.len()).with_timezone("UTC");
+                for r in rows {
+                    let t = r.try_get::<Option<DateTime<Utc>>, _>(i).map_err(err)?;
+                    b.append_option(t.map(|t| t.timestamp_micros()));
+                }
+                Arc::new(b.finish())
+            },
+            ColKind::Text => {
+                let mut b = StringBuilder::with_capacity(rows.len(), rows.len() * 16);
+                for r in rows { b.append_option(r.try_get::<Option<String>, _>(i).map_err(err)?); }
+                Arc::new(b.finish())
+            },
+        };
+        columns.push(col);
+    }
+
+    RecordBatch::try_new(schema, columns).map_err(|e| AppError::ParquetError(ParquetError::from(e)))
+}
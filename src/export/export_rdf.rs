@@ -0,0 +1,335 @@
+use sqlx::{Pool, Postgres};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use crate::err::AppError;
+use crate::import::json_models::{RorRecord, Location};
+use chrono::Local;
+use super::export_ror;
+use log::info;
+
+// Writes the processed (ppr) organisations as linked data, using the schema.org vocabulary,
+// with the ROR URI of each organisation as its subject. Names carry language tags (from the
+// ppr language codes, i.e. including those derived during processing), external ids become
+// sameAs links, parent and child relationships become parentOrganization and subOrganization
+// links, and locations are GeoNames URIs, each described once as a schema:Place.
+// The output can be Turtle (ttl), N-Triples (nt) or JSON-LD (jsonld). The triples are the same
+// in each case, and each organisation is written out as it is processed.
+
+const SCHEMA: &str = "http://schema.org/";
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+enum Term {
+    Iri(String),
+    Literal(String, Option<String>, Option<&'static str>),    // value, lang tag, xsd datatype
+}
+
+struct Node {
+    subject: String,
+    node_type: &'static str,
+    props: Vec<(&'static str, Term)>,     // schema.org property, value - properties kept together
+}
+
+
+pub async fn generate_rdf(output_folder : &PathBuf, data_version: &String, format: &str,
+                          pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
+    let output_file_name = format!("{} ror data {}.{}", data_version, datetime_string, format);
+    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
+
+    // The 'enriched' records are used, so that derived language codes are available.
+
+    let records = export_ror::get_ror_records(true, pool).await?;
+    info!("{} ror records assembled from ppr data", records.len());
+
+    let file = File::create(&file_path)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.to_owned()))?;
+    let mut w = BufWriter::new(file);
+
+    let mut out = get_header(format);
+    let mut places_done: HashSet<i64> = HashSet::new();
+    let mut num_nodes = 0;
+    for r in records.iter() {
+        let mut nodes = vec![get_org_node(r)];
+        for loc in r.locations.iter() {
+            if places_done.insert(loc.geonames_id) {
+                nodes.push(get_place_node(loc));
+            }
+        }
+        for n in nodes.iter() {
+            out += &match format {
+                "nt" => get_ntriples(n),
+                "jsonld" => get_jsonld(n, num_nodes == 0)?,
+                _ => get_turtle(n),
+            };
+            num_nodes += 1;
+        }
+        w.write_all(out.as_bytes())
+            .map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.to_owned()))?;
+        out.clear();
+    }
+
+    out += get_footer(format);
+    w.write_all(out.as_bytes())
+        .and_then(|_| w.flush())
+        .map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.to_owned()))?;
+
+    info!("{} organisation and place descriptions written", num_nodes);
+    Ok(())
+}
+
+
+fn get_org_node(r: &RorRecord) -> Node {
+
+    let mut props: Vec<(&'static str, Term)> = Vec::new();
+    let ror_id = r.id.trim_start_matches("https://ror.org/");
+    props.push(("identifier", Term::Literal(ror_id.to_string(), None, None)));
+
+    // The display name and other labels are names, aliases and acronyms alternate names.
+
+    for n in r.names.iter().filter(|n| n.types.iter().any(|t| t == "ror_display" || t == "label")) {
+        props.push(("name", get_name_literal(&n.value, n.derived_lang.as_ref().or(n.lang.as_ref()))));
+    }
+    for n in r.names.iter().filter(|n| !n.types.iter().any(|t| t == "ror_display" || t == "label")) {
+        props.push(("alternateName", get_name_literal(&n.value, n.derived_lang.as_ref().or(n.lang.as_ref()))));
+    }
+
+    if let Some(y) = r.established {
+        props.push(("foundingDate", Term::Literal(format!("{:04}", y), None, Some("gYear"))));
+    }
+
+    for e in r.external_ids.iter().flatten() {
+        for v in e.all.iter() {
+            if let Some(iri) = get_external_id_iri(&e.id_type, v) {
+                props.push(("sameAs", Term::Iri(iri)));
+            }
+        }
+    }
+    for k in r.links.iter().flatten().filter(|k| k.link_type == "wikipedia") {
+        props.push(("sameAs", Term::Iri(k.value.clone())));
+    }
+    for k in r.links.iter().flatten().filter(|k| k.link_type == "website") {
+        props.push(("url", Term::Iri(k.value.clone())));
+    }
+
+    // Only hierarchical relationships have a schema.org equivalent.
+
+    for rel in r.relationships.iter().flatten() {
+        match rel.rel_type.as_str() {
+            "parent" => props.push(("parentOrganization", Term::Iri(rel.id.clone()))),
+            "child" => props.push(("subOrganization", Term::Iri(rel.id.clone()))),
+            _ => {},
+        }
+    }
+
+    for loc in r.locations.iter() {
+        props.push(("location", Term::Iri(get_geonames_iri(loc.geonames_id))));
+    }
+
+    if let Some(d) = r.admin.last_modified.date.get(0..10) {
+        props.push(("dateModified", Term::Literal(d.to_string(), None, Some("date"))));
+    }
+
+    // Group each property's values together, retaining the order above.
+
+    let order = ["identifier", "name", "alternateName", "foundingDate", "sameAs", "url",
+                 "parentOrganization", "subOrganization", "location", "dateModified"];
+    props.sort_by_key(|(p, _)| order.iter().position(|o| o == p));
+
+    Node { subject: r.id.clone(), node_type: "Organization", props }
+}
+
+
+fn get_place_node(loc: &Location) -> Node {
+
+    let g = &loc.geonames_details;
+    let mut props: Vec<(&'static str, Term)> = vec![("name", Term::Literal(g.name.clone(), None, None))];
+    props.push(("latitude", Term::Literal(g.lat.to_string(), None, Some("double"))));
+    props.push(("longitude", Term::Literal(g.lng.to_string(), None, Some("double"))));
+    if !g.country_code.is_empty() {
+        props.push(("addressCountry", Term::Literal(g.country_code.clone(), None, None)));
+    }
+    Node { subject: get_geonames_iri(loc.geonames_id), node_type: "Place", props }
+}
+
+
+fn get_name_literal(value: &str, lang: Option<&String>) -> Term {
+
+    // Language tags must be alphanumeric subtags separated by hyphens, so any
+    // code that does not have that form is left off.
+
+    let is_valid_tag = |t: &String| !t.is_empty() && t.split('-').all(|s| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric()));
+    Term::Literal(value.to_string(), lang.filter(|t| is_valid_tag(t)).cloned(), None)
+}
+
+
+fn get_external_id_iri(id_type: &str, value: &str) -> Option<String> {
+    match id_type {
+        "wikidata" => Some(format!("http://www.wikidata.org/entity/{}", value)),
+        "isni" => Some(format!("https://isni.org/isni/{}", value.replace(' ', ""))),
+        "grid" => Some(format!("https://www.grid.ac/institutes/{}", value)),
+        "fundref" => Some(format!("https://doi.org/10.13039/{}", value)),
+        _ => None,
+    }
+}
+
+
+fn get_geonames_iri(geonames_id: i64) -> String {
+    format!("https://sws.geonames.org/{}/", geonames_id)
+}
+
+
+fn get_header(format: &str) -> String {
+    match format {
+        "nt" => "".to_string(),
+        "jsonld" => format!(r#"{{"@context": {{"@vocab": "{SCHEMA}", "xsd": "{XSD}"}},{}"@graph": ["#, "\n"),
+        _ => format!("@prefix schema: <{SCHEMA}> .\n@prefix xsd: <{XSD}> .\n\n"),
+    }
+}
+
+
+fn get_footer(format: &str) -> &'static str {
+    match format {
+        "jsonld" => "\n]}\n",
+        _ => "",
+    }
+}
+
+
+fn get_ntriples(n: &Node) -> String {
+
+    let subject = format_iri(&n.subject);
+    let mut s = format!("{} <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <{SCHEMA}{}> .\n", subject, n.node_type);
+    for (p, t) in n.props.iter() {
+        let o = match t {
+            Term::Iri(iri) => format_iri(iri),
+            Term::Literal(..) => format_literal(t, &format!("<{XSD}"), ">"),
+        };
+        s += &format!("{} <{SCHEMA}{}> {} .\n", subject, p, o);
+    }
+    s
+}
+
+
+fn get_turtle(n: &Node) -> String {
+
+    // Successive values of the same property are separated by commas.
+
+    let mut s = format!("{}\n    a schema:{}", format_iri(&n.subject), n.node_type);
+    let mut last_prop = "";
+    for (p, t) in n.props.iter() {
+        let o = match t {
+            Term::Iri(iri) => format_iri(iri),
+            Term::Literal(..) => format_literal(t, "xsd:", ""),
+        };
+        if *p == last_prop {
+            s += &format!(" ,\n        {}", o);
+        } else {
+            s += &format!(" ;\n    schema:{} {}", p, o);
+            last_prop = p;
+        }
+    }
+    s + " .\n\n"
+}
+
+
+fn get_jsonld(n: &Node, is_first: bool) -> Result<String, AppError> {
+
+    // Values are given as objects (@id, or @value with @language or @type), and
+    // as arrays where a property has more than one.
+
+    let mut obj = serde_json::Map::new();
+    obj.insert("@id".to_string(), n.subject.clone().into());
+    obj.insert("@type".to_string(), n.node_type.into());
+    for (p, t) in n.props.iter() {
+        let v = match t {
+            Term::Iri(iri) => serde_json::json!({"@id": iri}),
+            Term::Literal(v, Some(lang), _) => serde_json::json!({"@value": v, "@language": lang}),
+            Term::Literal(v, None, Some(dt)) => serde_json::json!({"@value": v, "@type": format!("xsd:{dt}")}),
+            Term::Literal(v, None, None) => serde_json::json!(v),
+        };
+        match obj.get_mut(*p) {
+            Some(serde_json::Value::Array(a)) => a.push(v),
+            Some(existing) => *existing = serde_json::json!([existing.take(), v]),
+            None => { obj.insert(p.to_string(), v); },
+        }
+    }
+    let sep = if is_first {""} else {",\n"};
+    Ok(format!("{}{}", sep, serde_json::to_string(&obj)?))
+}
+
+
+fn format_iri(iri: &str) -> String {
+
+    // Characters not allowed in an IRI (e.g. spaces in some links) are percent encoded.
+
+    let mut s = String::with_capacity(iri.len() + 2);
+    s.push('<');
+    for c in iri.chars() {
+        match c {
+            '\u{0}'..='\u{20}' | '<' | '>' | '"' | '{' | '}' | '|' | '^' | '`' | '\\' => {
+                let mut buf = [0u8; 4];
+                for b in c.encode_utf8(&mut buf).bytes() {
+                    s += &format!("%{:02X}", b);
+                }
+            },
+            _ => s.push(c),
+        }
+    }
+    s.push('>');
+    s
+}
+
+
+fn format_literal(t: &Term, dt_prefix: &str, dt_suffix: &str) -> String {
+
+    let Term::Literal(v, lang, dt) = t else { return "".to_string() };
+    let escaped = v.replace('\\', "\\\\").replace('"', "\\\"")
+                   .replace('\n', "\\n").replace('\r', "\\r").replace('\t', "\\t");
+    match (lang, dt) {
+        (Some(l), _) => format!("\"{}\"@{}", escaped, l),
+        (None, Some(d)) => format!("\"{}\"^^{}{}{}", escaped, dt_prefix, d, dt_suffix),
+        (None, None) => format!("\"{}\"", escaped),
+    }
+}
+
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_iris_are_percent_encoded() {
+        assert_eq!(format_iri("https://ror.org/006jxzx88"), "<https://ror.org/006jxzx88>");
+        assert_eq!(format_iri("http://example.org/a page"), "<http://example.org/a%20page>");
+        assert_eq!(format_iri("http://example.org/<x>\"y\""), "<http://example.org/%3Cx%3E%22y%22>");
+        assert_eq!(format_iri("http://example.org/é"), "<http://example.org/é>");
+    }
+
+    #[test]
+    fn check_literals_are_escaped() {
+        let lit = |v: &str| Term::Literal(v.to_string(), None, None);
+        assert_eq!(format_literal(&lit("Bond University"), "xsd:", ""), "\"Bond University\"");
+        assert_eq!(format_literal(&lit("The \"Old\" College"), "xsd:", ""), "\"The \\\"Old\\\" College\"");
+        assert_eq!(format_literal(&lit("a\\b"), "xsd:", ""), "\"a\\\\b\"");
+        assert_eq!(format_literal(&lit("line 1\nline 2\r\tend"), "xsd:", ""), "\"line 1\\nline 2\\r\\tend\"");
+        let year = Term::Literal("1987".to_string(), None, Some("gYear"));
+        assert_eq!(format_literal(&year, "xsd:", ""), "\"1987\"^^xsd:gYear");
+        assert_eq!(format_literal(&year, &format!("<{XSD}"), ">"), format!("\"1987\"^^<{XSD}gYear>"));
+    }
+
+    #[test]
+    fn check_invalid_language_tags_are_dropped() {
+        let name = |lang: &str| format_literal(&get_name_literal("Nom", Some(&lang.to_string())), "xsd:", "");
+        assert_eq!(name("fr"), "\"Nom\"@fr");
+        assert_eq!(name("zh-Hant"), "\"Nom\"@zh-Hant");
+        assert_eq!(name(""), "\"Nom\"");
+        assert_eq!(name("fr-"), "\"Nom\"");
+        assert_eq!(name("fr fr"), "\"Nom\"");
+        assert_eq!(format_literal(&get_name_literal("Nom", None), "xsd:", ""), "\"Nom\"");
+    }
+}
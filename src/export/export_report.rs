@@ -0,0 +1,333 @@
+use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+use crate::AppError;
+use chrono::{DateTime, Local};
+use super::export_structs::{VSummary, Singleton, CSVSummaryRow, DistribRow, RankedRow};
+use super::export_helpers::{collect_singleton_values, fetch_attrib_rows, fetch_distrib_rows, fetch_ranked_rows,
+                            fetch_org_type_and_lang_code_rows, fetch_org_type_and_relationship_rows};
+
+// The sections of the summary report, as used by the html and Markdown formats, built once as a
+// sequence of headings, notes, tables and chart data. Each format then renders the items in its
+// own markup, so that the wording and content of the report are only defined here.
+
+pub enum ReportItem {
+    Title(String),
+    Section(String),
+    SubSection(String),
+    Note(String),
+    KeyValues(Vec<(String, String)>),
+    Table(ReportTable),
+    BarChart(Vec<(String, f64)>),                   // (label, value), in rank or count order
+    TrendChart(Vec<(String, i32, f64)>),            // (version code, days since 29/04/24, value)
+}
+
+pub struct ReportTable {
+    pub headers: Vec<String>,
+    pub text_cols: usize,                           // the first text_cols columns are text, the remainder numbers
+    pub rows: Vec<Vec<String>>,
+    pub total: Option<Vec<String>>,
+}
+
+type SummaryValue = fn(&CSVSummaryRow) -> i32;
+
+
+pub async fn build_report(vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<Vec<ReportItem>, AppError> {
+
+    let singvals: HashMap<String, Singleton> = collect_singleton_values(vcode, inc_withdrawn, pool).await?;
+
+    let mut items = Vec::new();
+    let summ = get_header(&mut items, vcode, inc_withdrawn, pool).await?;
+    get_summary(&mut items, &summ, inc_withdrawn);
+    get_trends(&mut items, inc_withdrawn, pool).await?;
+    get_name_info(&mut items, vcode, inc_withdrawn, pool, &singvals).await?;
+    get_ranked_name_info(&mut items, vcode, inc_withdrawn, pool, &singvals).await?;
+    get_type_details(&mut items, vcode, inc_withdrawn, pool).await?;
+    get_location_details(&mut items, vcode, inc_withdrawn, pool, &singvals).await?;
+    get_links_and_extid_details(&mut items, vcode, inc_withdrawn, pool).await?;
+    get_relationship_details(&mut items, vcode, inc_withdrawn, pool, &singvals).await?;
+    items.push(ReportItem::Section("Domains".to_string()));
+    get_distrib_section(&mut items, "domains", "domains", vcode, inc_withdrawn, pool).await?;
+    Ok(items)
+}
+
+
+async fn get_header(items: &mut Vec<ReportItem>, vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<VSummary, AppError> {
+
+    let sql = "SELECT import_datetime from src.version_details;";
+    let import_dt: DateTime<Local> = sqlx::query_scalar(sql).fetch_one(pool).await
+           .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let sql = format!(r#"SELECT * from smm.version_summaries
+                      WHERE vcode = '{vcode}' and inc_wd = {inc_withdrawn};"#);
+    let summ: VSummary = sqlx::query_as(&sql).fetch_one(pool).await
+           .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    items.push(ReportItem::Title("Summary of ROR Dataset".to_string()));
+    items.push(ReportItem::KeyValues(vec![
+        ("Version".to_string(), vcode.to_string()),
+        ("Date".to_string(), summ.vdate.to_string()),
+        ("Days since 29/04/24".to_string(), summ.vdays.to_string()),
+        ("Time data imported".to_string(), import_dt.format("%Y-%m-%d %H:%M:%S").to_string()),
+        ("Report generated".to_string(), Local::now().format("%Y-%m-%d %H:%M:%S").to_string()),
+    ]));
+    Ok(summ)
+}
+
+
+fn get_summary(items: &mut Vec<ReportItem>, summ: &VSummary, inc_withdrawn: bool) {
+
+    items.push(ReportItem::Section("Organisation Numbers".to_string()));
+    let pc = |n: i32| if summ.num_recs > 0 { n as f32 * 100.0 / summ.num_recs as f32 } else { 0.0 };
+    let rows = vec![
+        vec!["Active".to_string(), summ.num_active.to_string(), fmt_pc(pc(summ.num_active))],
+        vec!["Inactive".to_string(), summ.num_inactive.to_string(), fmt_pc(pc(summ.num_inactive))],
+        vec!["Withdrawn".to_string(), summ.num_withdrawn.to_string(), fmt_pc(pc(summ.num_withdrawn))],
+    ];
+    let total = vec!["Total".to_string(), summ.num_recs.to_string(), fmt_pc(pc(summ.num_recs))];
+    items.push(get_table(&["Status", "Number", "%age"], 1, rows, Some(total)));
+
+    items.push(ReportItem::Note(if inc_withdrawn {
+        format!("N.B. Withdrawn organisations have been RETAINED in the dataset and the figures \
+            below reflect this. They apply to all ROR organisations, whatever their status. \
+            The denominator for % organisations is therefore {}.", summ.num_denom)
+    }
+    else {
+        format!("N.B. Withdrawn organisations have been REMOVED from the main dataset. \
+            The figures below reflect both active and inactive ROR organisations, but not those classed as \
+            'withdrawn', i.e. those added to ROR in error or duplicated. The denominator for % organisations \
+            is therefore {}. Data on withdrawn organisations, including successor organisations where relevant, \
+            can be found in the 'rec.withdrawn' table.", summ.num_denom)
+    }));
+
+    items.push(ReportItem::Section("Entity Numbers".to_string()));
+    let rows: Vec<Vec<String>> = [("Organisations", summ.num_denom), ("Names", summ.num_names),
+            ("Types", summ.num_types), ("Links", summ.num_links), ("External Ids", summ.num_ext_ids),
+            ("Relationships", summ.num_rels), ("Locations", summ.num_locations), ("Domains", summ.num_domains)]
+            .iter().map(|(e, n)| vec![e.to_string(), n.to_string()]).collect();
+    items.push(get_table(&["Entity", "Number"], 1, rows, None));
+
+    items.push(ReportItem::Note("N.B. The totals under some columns in the tables are generated from the table data, \
+        and were included to provide a visual check on that data. The occasional very small deviations from 100% \
+        for percentage totals are because the source data are stored in the database to only 2 decimal places \
+        of accuracy.".to_string()));
+}
+
+
+async fn get_trends(items: &mut Vec<ReportItem>, inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    // Changes in the main entity numbers across all the summarised versions (with the same inc_wd value).
+
+    let sql = format!(r#"SELECT vcode, inc_wd, vdate::text, vdays, num_recs,
+                        num_active, num_inactive, num_withdrawn, num_denom, num_names,
+                        num_types, num_links, num_ext_ids, num_rels, num_locations, num_domains
+                        from smm.version_summaries
+                        where inc_wd = {inc_withdrawn} and vcode <> 'v1.57'
+                        order by vdays;"#);
+    let versions: Vec<CSVSummaryRow> = sqlx::query_as(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    items.push(ReportItem::Section("Trends Across Versions".to_string()));
+    if versions.len() < 2 {
+        items.push(ReportItem::Note("Only one version has been summarised so far, so no trends can be shown.".to_string()));
+        return Ok(());
+    }
+
+    let series: [(&str, SummaryValue); 6] = [
+        ("Organisations", |v| v.num_denom), ("Names", |v| v.num_names),
+        ("External Ids", |v| v.num_ext_ids), ("Links", |v| v.num_links),
+        ("Relationships", |v| v.num_rels), ("Domains", |v| v.num_domains)];
+    for (title, value) in series {
+        let points: Vec<(String, i32, f64)> = versions.iter()
+                .map(|v| (v.vcode.clone(), v.vdays, value(v) as f64)).collect();
+        items.push(ReportItem::SubSection(title.to_string()));
+        items.push(ReportItem::TrendChart(points));
+    }
+    Ok(())
+}
+
+
+async fn get_name_info(items: &mut Vec<ReportItem>, vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>,
+                       singvals: &HashMap<String, Singleton>) -> Result<(), AppError> {
+
+    items.push(ReportItem::Section("Names".to_string()));
+    items.push(get_singleton_table(singvals, &["added_labels", "dup_names"]));
+    get_attrib_section(items, "name types", "Names", vcode, inc_withdrawn, pool).await?;
+    for (count_name, header_type) in [("names", "names"), ("labels", "labels"),
+                                      ("aliases", "aliases"), ("acronyms", "acronyms")] {
+        get_distrib_section(items, count_name, header_type, vcode, inc_withdrawn, pool).await?;
+    }
+
+    items.push(ReportItem::Section("Names Without Language Code (w/o LCs)".to_string()));
+    items.push(get_singleton_table(singvals, &["total_wolc"]));
+    get_attrib_section(items, "name types wolc", "Names without language codes (w/o LCs)", vcode, inc_withdrawn, pool).await?;
+    items.push(get_singleton_table(singvals, &["nacro_wolc", "nacncmp_wolc"]));
+
+    let rows: Vec<Vec<String>> = fetch_org_type_and_lang_code_rows(vcode, inc_withdrawn, pool).await?.iter()
+            .map(|r| vec![r.name_type.clone(), r.org_type.clone(), r.names_num.to_string(),
+                          r.names_wolc.to_string(), fmt_pc(r.names_wolc_pc)]).collect();
+    items.push(ReportItem::SubSection("Numbers of name types without language codes for different organisational types".to_string()));
+    items.push(get_table(&["Name type", "Org type", "Number names", "Names w/o LC", "%age w/o LC"], 2, rows, None));
+
+    items.push(ReportItem::Section("ROR Names".to_string()));
+    items.push(get_singleton_table(singvals, &["label_ror", "nlabel_ror", "ror_en", "ror_nen", "ror_wolc", "ror_wolc_ncmp"]));
+    Ok(())
+}
+
+
+async fn get_ranked_name_info(items: &mut Vec<ReportItem>, vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>,
+                              singvals: &HashMap<String, Singleton>) -> Result<(), AppError> {
+
+    items.push(ReportItem::Section("Language and Script Usage".to_string()));
+    items.push(get_singleton_table(singvals, &["names_ne", "acro_ne", "nacro_ne"]));
+    let rows = fetch_ranked_rows("languages", vcode, inc_withdrawn, pool).await?;
+    items.push(ReportItem::SubSection("By language (non acronym names only)".to_string()));
+    get_ranked_section(items, &rows, &["Language", "Number of names", "%age non-en non-acr", "%age non-acr names"]);
+
+    items.push(get_singleton_table(singvals, &["names_nl", "acro_nl", "nacro_nl"]));
+    let rows = fetch_ranked_rows("scripts", vcode, inc_withdrawn, pool).await?;
+    items.push(ReportItem::SubSection("By script".to_string()));
+    get_ranked_section(items, &rows, &["Script", "Number of names", "%age non-ltn names", "%age total names"]);
+    Ok(())
+}
+
+
+async fn get_type_details(items: &mut Vec<ReportItem>, vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    items.push(ReportItem::Section("Organisation Types".to_string()));
+    get_attrib_section(items, "org types", "Organisation Types", vcode, inc_withdrawn, pool).await?;
+    get_distrib_section(items, "org_types", "organisation types", vcode, inc_withdrawn, pool).await?;
+    items.push(ReportItem::Note("In most cases a 'funder' organisation has another type designation, reflecting \
+        its primary role. The various primary roles for funder organisations are tabulated below.".to_string()));
+    get_attrib_section(items, "funder co-types", "Funder Co-types", vcode, inc_withdrawn, pool).await?;
+    items.push(ReportItem::Note("N.B. A small number of funders have two additional type designations. Hence the \
+        total of co-types is slightly greater than the total funder number, and the percentage is slightly \
+        greater than 100.".to_string()));
+    Ok(())
+}
+
+
+async fn get_location_details(items: &mut Vec<ReportItem>, vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>,
+                              singvals: &HashMap<String, Singleton>) -> Result<(), AppError> {
+
+    items.push(ReportItem::Section("Locations".to_string()));
+    items.push(get_singleton_table(singvals, &["poly_locs", "poly_subdivs", "poly_countries"]));
+    get_distrib_section(items, "locs", "locations", vcode, inc_withdrawn, pool).await?;
+    let rows = fetch_ranked_rows("countries", vcode, inc_withdrawn, pool).await?;
+    items.push(ReportItem::SubSection("Number of locations by country".to_string()));
+    get_ranked_section(items, &rows, &["Country", "Number of locs", "%age non-US locs", "%age total locs"]);
+    Ok(())
+}
+
+
+async fn get_links_and_extid_details(items: &mut Vec<ReportItem>, vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    items.push(ReportItem::Section("External Ids and Links".to_string()));
+    get_attrib_section(items, "link types", "Links", vcode, inc_withdrawn, pool).await?;
+    get_distrib_section(items, "links", "links", vcode, inc_withdrawn, pool).await?;
+    get_attrib_section(items, "external id types", "External Ids", vcode, inc_withdrawn, pool).await?;
+    get_distrib_section(items, "ext_ids", "external ids", vcode, inc_withdrawn, pool).await?;
+    Ok(())
+}
+
+
+async fn get_relationship_details(items: &mut Vec<ReportItem>, vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>,
+                                  singvals: &HashMap<String, Singleton>) -> Result<(), AppError> {
+
+    items.push(ReportItem::Section("Relationships".to_string()));
+    get_attrib_section(items, "rel types", "Relationships", vcode, inc_withdrawn, pool).await?;
+    for (count_name, header_type) in [("parent orgs", "'has parent' relationships"), ("child orgs", "'has child' relationships"),
+                                      ("related orgs", "'is related to' relationships"), ("predecessor orgs", "'has predecessor' relationships"),
+                                      ("successor orgs", "'has successor' relationships")] {
+        get_distrib_section(items, count_name, header_type, vcode, inc_withdrawn, pool).await?;
+    }
+    items.push(get_singleton_table(singvals, &["nrecip_pc", "nrecip_rr", "nrecip_ps"]));
+
+    let rows: Vec<Vec<String>> = fetch_org_type_and_relationship_rows(vcode, inc_withdrawn, pool).await?.iter()
+            .map(|r| vec![r.rel_type.clone(), r.org_type.clone(), r.num_links.to_string(),
+                          r.num_orgs.to_string(), fmt_pc(r.num_orgs_pc)]).collect();
+    items.push(ReportItem::SubSection("Numbers of relationship links for different organisational types".to_string()));
+    items.push(get_table(&["Relationship", "Org type", "Number links", "Number orgs", "%age org type"], 2, rows, None));
+    Ok(())
+}
+
+
+async fn get_attrib_section(items: &mut Vec<ReportItem>, att_name: &str, header_type: &str, vcode: &String,
+                            inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    // As in the text version the 'nacro' (non acronym) categories are not included in the totals.
+
+    let rows = fetch_attrib_rows(att_name, vcode, inc_withdrawn, pool).await?;
+    let total_num: i32 = rows.iter().filter(|r| !r.cat_name.starts_with("nacro")).map(|r| r.number_cat).sum();
+    let total_pc: f32 = rows.iter().filter(|r| !r.cat_name.starts_with("nacro")).map(|r| r.pc_of_atts).sum();
+
+    let table_rows: Vec<Vec<String>> = rows.iter()
+            .map(|r| vec![r.cat_name.clone(), r.number_cat.to_string(), fmt_pc(r.pc_of_atts),
+                          r.number_orgs.to_string(), fmt_pc(r.pc_of_orgs)]).collect();
+    let total = vec!["TOTAL".to_string(), total_num.to_string(), fmt_pc(total_pc), "".to_string(), "".to_string()];
+
+    items.push(ReportItem::SubSection(format!("{}, categories and numbers", header_type)));
+    items.push(get_table(&["Category", "Number in cat", "%age all cats", "Number orgs", "%age total orgs"], 1, table_rows, Some(total)));
+    Ok(())
+}
+
+
+async fn get_distrib_section(items: &mut Vec<ReportItem>, count_name: &str, header_type: &str, vcode: &String,
+                             inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    let rows: Vec<DistribRow> = fetch_distrib_rows(count_name, vcode, inc_withdrawn, pool).await?;
+    let total_num: i32 = rows.iter().map(|r| r.num_of_orgs).sum();
+    let total_pc: f32 = rows.iter().map(|r| r.pc_of_orgs).sum();
+
+    let table_rows: Vec<Vec<String>> = rows.iter()
+            .map(|r| vec![r.count.to_string(), r.num_of_orgs.to_string(), fmt_pc(r.pc_of_orgs)]).collect();
+    let total = vec!["TOTAL".to_string(), total_num.to_string(), fmt_pc(total_pc)];
+    let bars: Vec<(String, f64)> = rows.iter().map(|r| (r.count.to_string(), r.num_of_orgs as f64)).collect();
+
+    items.push(ReportItem::SubSection(format!("Numbers of organisations with specified count of {}", header_type)));
+    items.push(get_table(&["Count", "Number orgs", "%age total orgs"], 0, table_rows, Some(total)));
+    items.push(ReportItem::BarChart(bars));
+    Ok(())
+}
+
+
+fn get_ranked_section(items: &mut Vec<ReportItem>, rows: &[RankedRow], headers: &[&str]) {
+
+    // As in the text version the United States is excluded from the non-US percentages.
+
+    let total_num: i32 = rows.iter().map(|r| r.number).sum();
+    let total_pc: f32 = rows.iter().filter(|r| r.entity != "United States").map(|r| r.pc_of_entities).sum();
+
+    let table_rows: Vec<Vec<String>> = rows.iter()
+            .map(|r| vec![r.entity.clone(), r.number.to_string(),
+                          if r.entity == "United States" {"----".to_string()} else {fmt_pc(r.pc_of_entities)},
+                          fmt_pc(r.pc_of_base_set)]).collect();
+    let total = vec!["TOTAL".to_string(), total_num.to_string(), fmt_pc(total_pc), "".to_string()];
+    let bars: Vec<(String, f64)> = rows.iter().map(|r| (r.entity.clone(), r.number as f64)).collect();
+
+    items.push(get_table(headers, 1, table_rows, Some(total)));
+    items.push(ReportItem::BarChart(bars));
+}
+
+
+fn get_singleton_table(singvals: &HashMap<String, Singleton>, topics: &[&str]) -> ReportItem {
+
+    let rows: Vec<Vec<String>> = topics.iter().filter_map(|t| singvals.get(*t))
+            .map(|s| vec![s.description.clone(), s.number.to_string(),
+                          s.pc.map(fmt_pc).unwrap_or_default()]).collect();
+    get_table(&["", "Number", "%age"], 1, rows, None)
+}
+
+
+fn get_table(headers: &[&str], text_cols: usize, rows: Vec<Vec<String>>, total: Option<Vec<String>>) -> ReportItem {
+    ReportItem::Table(ReportTable {
+        headers: headers.iter().map(|h| h.to_string()).collect(),
+        text_cols,
+        rows,
+        total,
+    })
+}
+
+
+fn fmt_pc(pc: f32) -> String {
+    format!("{:.2}", pc)
+}
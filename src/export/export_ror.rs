@@ -0,0 +1,201 @@
+use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use crate::err::AppError;
+use crate::import::json_models::{RorRecord, Name, Location, GeoDetails, ExternalId, Link, Relationship, Admin, DateSchema};
+use chrono::Local;
+use super::export_helpers::{fetch_rows, float_col, get_ppr_names_sql};
+use super::export_structs::{RorCoreRow, RorNameRow, RorLocationRow, RorValueRow, RorTypedValueRow, RorRelRow};
+use log::info;
+
+// Writes the processed (ppr) data back out as a ROR v2 schema json file, i.e. as an array of
+// records with the same structure as the ROR data dump, so that tools which read ROR json can
+// use the cleaned data. Names are the cleaned and de-duplicated versions.
+// In 'clean' mode only the fields in the ROR schema are written, and name language codes are
+// those provided by ROR. In 'enriched' mode each name also has the extension fields 'derived_lang'
+// (a language code derived during processing, where ROR provided none or a different one)
+// and 'script' (the script code of the name).
+
+pub async fn generate_ror_json(output_folder : &PathBuf, data_version: &String, enriched: bool,
+                               pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
+    let mode = if enriched {"enriched"} else {"clean"};
+    let output_file_name = format!("{} ror data {} {}.json", data_version, mode, datetime_string);
+    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
+
+    let records = get_ror_records(enriched, pool).await?;
+    info!("{} ror records assembled from ppr data", records.len());
+
+    let file = File::create(&file_path)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.to_owned()))?;
+    serde_json::to_writer(BufWriter::new(file), &records)?;
+    Ok(())
+}
+
+
+pub(super) async fn get_ror_records(enriched: bool, pool : &Pool<Postgres>) -> Result<Vec<RorRecord>, AppError> {
+
+    // 1) Core and admin data, one row per organisation.
+
+    let sql = r#"select c.id, c.ror_full_id, s.name as status, c.established,
+                a.created::text, a.cr_schema, a.last_modified::text, a.lm_schema
+                from ppr.core_data c
+                inner join lup.ror_status_types s on c.status = s.id
+                inner join ppr.admin_data a on c.id = a.id
+                order by c.id;"#;
+    let cores: Vec<RorCoreRow> = fetch_rows(sql, &[], pool).await?;
+
+    // 2) Names, with the original ROR language code as well as the combined one.
+
+    let sql = get_ppr_names_sql();
+    let mut names = group_by_id(fetch_rows::<RorNameRow>(&sql, &[], pool).await?, |r| &r.id);
+
+    // 3) Types, locations, external ids, links, relationships and domains.
+
+    let sql = r#"select t.id, lt.name as value
+                from ppr.type t
+                inner join lup.ror_org_types lt on t.org_type = lt.id
+                where t.org_type <> 1000
+                order by t.id, t.org_type;"#;
+    let mut types = group_by_id(fetch_rows::<RorValueRow>(sql, &[], pool).await?, |r| &r.id);
+
+    let sql = format!(r#"select id, geonames_id, location, {} as lat, {} as lng, cont_code, cont_name,
+                country_code, country_name, csubdiv_code, csubdiv_name
+                from ppr.locations
+                order by id, geonames_id;"#, float_col("lat"), float_col("lng"));
+    let mut locs = group_by_id(fetch_rows::<RorLocationRow>(&sql, &[], pool).await?, |r| &r.id);
+
+    let sql = r#"select e.id, lt.name as value_type, e.id_value as value, e.is_preferred
+                from ppr.external_ids e
+                inner join lup.ror_id_types lt on e.id_type = lt.id
+                order by e.id, lt.name, e.is_preferred desc;"#;
+    let mut ext_ids = group_by_id(fetch_rows::<RorTypedValueRow>(sql, &[], pool).await?, |r| &r.id);
+
+    let sql = r#"select k.id, lt.name as value_type, k.link as value, false as is_preferred
+                from ppr.links k
+                inner join lup.ror_link_types lt on k.link_type = lt.id
+                order by k.id, lt.name;"#;
+    let mut links = group_by_id(fetch_rows::<RorTypedValueRow>(sql, &[], pool).await?, |r| &r.id);
+
+    let sql = r#"select id,
+                case rel_type
+                    when 1 then 'parent'
+                    when 2 then 'child'
+                    when 3 then 'related'
+                    when 4 then 'predecessor'
+                    when 5 then 'successor'
+                end as rel_type,
+                related_id, related_name
+                from ppr.relationships
+                order by id, rel_type, related_id;"#;
+    let mut rels = group_by_id(fetch_rows::<RorRelRow>(sql, &[], pool).await?, |r| &r.id);
+
+    let sql = r#"select id, domain as value from ppr.domains order by id, domain;"#;
+    let mut domains = group_by_id(fetch_rows::<RorValueRow>(sql, &[], pool).await?, |r| &r.id);
+
+    // Assemble the records, in id order.
+
+    let records = cores.into_iter().map(|c| {
+        let id = c.id.clone();
+        RorRecord {
+            id: c.ror_full_id,
+            status: c.status,
+            established: c.established.map(|y| y as i16),
+            names: names.remove(&id).unwrap_or_default().into_iter()
+                        .map(|n| get_name(n, enriched)).collect(),
+            types: types.remove(&id).unwrap_or_default().into_iter().map(|t| t.value).collect(),
+            locations: locs.remove(&id).unwrap_or_default().into_iter().map(get_location).collect(),
+            external_ids: Some(get_external_ids(ext_ids.remove(&id).unwrap_or_default())),
+            links: Some(links.remove(&id).unwrap_or_default().into_iter()
+                        .map(|k| Link { link_type: k.value_type, value: k.value }).collect()),
+            relationships: Some(rels.remove(&id).unwrap_or_default().into_iter()
+                        .map(|r| Relationship { rel_type: r.rel_type, label: r.related_name,
+                                                id: format!("https://ror.org/{}", r.related_id) }).collect()),
+            domains: Some(domains.remove(&id).unwrap_or_default().into_iter().map(|d| d.value).collect()),
+            admin: Admin {
+                created: DateSchema { date: c.created, schema_version: c.cr_schema },
+                last_modified: DateSchema { date: c.last_modified, schema_version: c.lm_schema },
+            },
+        }
+    }).collect();
+
+    Ok(records)
+}
+
+
+fn get_name(n: RorNameRow, enriched: bool) -> Name {
+
+    // If a language code was derived the ROR code is the original one (if any),
+    // otherwise it is the code in ppr.names (which may have been corrected, e.g. 'sh' to 'sr').
+
+    let ror_lang = if n.der_lang.is_some() {n.ror_lang} else {n.lang_code.clone()};
+    let mut types = vec![n.name_type];
+    if n.is_ror_name {
+        types.insert(0, "ror_display".to_string());
+    }
+
+    let (derived_lang, script) = if enriched {
+        (n.lang_code.filter(|lc| Some(lc) != ror_lang.as_ref()), n.script_code.filter(|sc| !sc.is_empty()))
+    } else {
+        (None, None)
+    };
+
+    Name {
+        value: n.value,
+        lang: ror_lang,
+        types,
+        derived_lang,
+        script,
+    }
+}
+
+
+fn get_location(r: RorLocationRow) -> Location {
+    Location {
+        geonames_id: r.geonames_id.unwrap_or_default() as i64,
+        geonames_details: GeoDetails {
+            continent_code: r.cont_code,
+            continent_name: r.cont_name,
+            country_code: r.country_code.unwrap_or_default(),
+            country_name: r.country_name.unwrap_or_default(),
+            country_subdivision_code: r.csubdiv_code,
+            country_subdivision_name: r.csubdiv_name,
+            lat: r.lat.unwrap_or_default(),
+            lng: r.lng.unwrap_or_default(),
+            name: r.location.unwrap_or_default(),
+        }
+    }
+}
+
+
+fn get_external_ids(rows: Vec<RorTypedValueRow>) -> Vec<ExternalId> {
+
+    // The rows are ordered by id type, so are collected into one ExternalId per type.
+
+    let mut ext_ids: Vec<ExternalId> = Vec::new();
+    for r in rows {
+        if ext_ids.last().map(|e| e.id_type != r.value_type).unwrap_or(true) {
+            ext_ids.push(ExternalId { id_type: r.value_type.clone(), all: Vec::new(), preferred: None });
+        }
+        if let Some(e) = ext_ids.last_mut() {
+            if r.is_preferred {
+                e.preferred = Some(r.value.clone());
+            }
+            e.all.push(r.value);
+        }
+    }
+    ext_ids
+}
+
+
+fn group_by_id<T>(rows: Vec<T>, id: impl Fn(&T) -> &String) -> HashMap<String, Vec<T>> {
+
+    let mut map: HashMap<String, Vec<T>> = HashMap::new();
+    for r in rows {
+        map.entry(id(&r).clone()).or_default().push(r);
+    }
+    map
+}
@@ -0,0 +1,177 @@
+use sqlx::{Pool, Postgres, Row, Connection, Executor, postgres::PgRow};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection};
+use std::path::PathBuf;
+use crate::err::AppError;
+use super::export_helpers::{float_col, get_table_details, ColKind, TableDetails};
+use chrono::Local;
+use log::info;
+
+// Writes the ppr and lup tables, and the smm data for the designated version, into a single
+// SQLite file, so that the processed data can be used without Postgres. As SQLite has no
+// schemas each table is named {schema}_{table}, e.g. ppr_core_data, and the Postgres indexes
+// are recreated with the same columns. Views are added that replace the integer codes in the
+// main ppr tables with the corresponding lookup names.
+
+#[derive(sqlx::FromRow)]
+struct IndexDetails {
+    index_name: String,
+    table_name: String,
+    is_unique: bool,
+    columns: Vec<String>,
+}
+
+
+pub async fn generate_sqlite(output_folder : &PathBuf, data_version: &String,
+                             inc_withdrawn: bool, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
+    let dv_string = if inc_withdrawn {format!("{data_version} inc WD")} else {data_version.to_string()};
+    let output_file_name = format!("{} ror data {}.sqlite", dv_string, datetime_string);
+    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
+
+    let options = SqliteConnectOptions::new().filename(&file_path).create_if_missing(true);
+    let mut conn = SqliteConnection::connect_with(&options).await
+        .map_err(|e| AppError::SqlxError(e, format!("opening {}", file_path.display())))?;
+
+    for schema in ["ppr", "lup", "smm"] {
+        for table in get_table_details(schema, pool).await? {
+
+            // smm tables hold data for all versions, and only the designated version is transferred.
+            // Tables without a version code (e.g. scratch tables) are not included.
+
+            let has_col = |c: &str| table.columns.iter().any(|(n, _, _)| n == c);
+            let where_clause = if schema != "smm" {
+                "".to_string()
+            } else if has_col("vcode") && has_col("inc_wd") {
+                format!(" where vcode = '{data_version}' and inc_wd = {inc_withdrawn}")
+            } else if has_col("vcode") {
+                format!(" where vcode = '{data_version}'")
+            } else {
+                continue;
+            };
+
+            let n = transfer_table(&table, &where_clause, &mut conn, pool).await?;
+            info!("{} records transferred to {}_{}", n, table.schema, table.name);
+        }
+        create_indexes(schema, &mut conn, pool).await?;
+    }
+
+    create_views(&mut conn).await?;
+    conn.close().await
+        .map_err(|e| AppError::SqlxError(e, "closing sqlite connection".to_string()))?;
+    Ok(())
+}
+
+
+async fn transfer_table(table: &TableDetails, where_clause: &str, conn: &mut SqliteConnection,
+                        pool : &Pool<Postgres>) -> Result<usize, AppError> {
+
+    let sqlite_name = format!("{}_{}", table.schema, table.name);
+    let col_defs: Vec<String> = table.columns.iter().map(|(n, k, nn)| {
+        let t = match k {
+            ColKind::Int | ColKind::BigInt | ColKind::Bool => "INTEGER",
+            ColKind::Float => "REAL",
+            ColKind::Date | ColKind::Timestamp | ColKind::Text => "TEXT",
+        };
+        format!("\"{}\" {}{}", n, t, if *nn {" NOT NULL"} else {""})
+    }).collect();
+    let sql = format!("drop table if exists {sqlite_name}; create table {sqlite_name} ({});", col_defs.join(", "));
+    conn.execute(sql.as_str()).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    // Values are cast in Postgres, so that each is read as an i64, f64 or String
+    // (booleans become 0 or 1, as SQLite has no boolean type).
+
+    let select_cols: Vec<String> = table.columns.iter().map(|(n, k, _)| match k {
+        ColKind::Int | ColKind::BigInt => format!("\"{n}\"::int8"),
+        ColKind::Bool => format!("\"{n}\"::int::int8"),
+        ColKind::Float => float_col(&format!("\"{n}\"")),
+        ColKind::Date | ColKind::Timestamp | ColKind::Text => format!("\"{n}\"::text"),
+    }).collect();
+    let sql = format!("select {} from {}.{}{};", select_cols.join(", "), table.schema, table.name, where_clause);
+    let rows: Vec<PgRow> = sqlx::query(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let params: Vec<String> = (1..=table.columns.len()).map(|i| format!("?{i}")).collect();
+    let insert_sql = format!("insert into {sqlite_name} values ({});", params.join(", "));
+
+    let mut tx = conn.begin().await
+        .map_err(|e| AppError::SqlxError(e, "begin".to_string()))?;
+    for r in rows.iter() {
+        let mut q = sqlx::query(&insert_sql);
+        for (i, (_, k, _)) in table.columns.iter().enumerate() {
+            q = match k {
+                ColKind::Int | ColKind::BigInt | ColKind::Bool => q.bind(r.try_get::<Option<i64>, _>(i).map_err(|e| AppError::SqlxError(e, sql.to_string()))?),
+                ColKind::Float => q.bind(r.try_get::<Option<f64>, _>(i).map_err(|e| AppError::SqlxError(e, sql.to_string()))?),
+                _ => q.bind(r.try_get::<Option<String>, _>(i).map_err(|e| AppError::SqlxError(e, sql.to_string()))?),
+            };
+        }
+        q.execute(&mut *tx).await
+            .map_err(|e| AppError::SqlxError(e, insert_sql.to_string()))?;
+    }
+    tx.commit().await
+        .map_err(|e| AppError::SqlxError(e, "commit".to_string()))?;
+    Ok(rows.len())
+}
+
+
+async fn create_indexes(schema: &str, conn: &mut SqliteConnection, pool : &Pool<Postgres>) -> Result<(), AppError> {
+
+    // Primary keys become unique indexes. Index names are prefixed with the schema,
+    // as in SQLite they must be unique across the whole database.
+
+    let sql = format!(r#"select i.relname::text as index_name, t.relname::text as table_name, ix.indisunique as is_unique,
+                array_agg(a.attname::text order by array_position(ix.indkey::int2[], a.attnum)) as columns
+                from pg_index ix
+                inner join pg_class i on i.oid = ix.indexrelid
+                inner join pg_class t on t.oid = ix.indrelid
+                inner join pg_namespace n on n.oid = t.relnamespace
+                inner join pg_attribute a on a.attrelid = t.oid and a.attnum = any(ix.indkey)
+                where n.nspname = '{schema}'
+                group by i.relname, t.relname, ix.indisunique
+                order by t.relname, i.relname;"#);
+    let indexes: Vec<IndexDetails> = sqlx::query_as(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    for ix in indexes {
+        let cols: Vec<String> = ix.columns.iter().map(|c| format!("\"{c}\"")).collect();
+        let sql = format!("create {}index if not exists {schema}_{} on {schema}_{} ({});",
+                          if ix.is_unique {"unique "} else {""}, ix.index_name, ix.table_name, cols.join(", "));
+        conn.execute(sql.as_str()).await
+            .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    }
+    Ok(())
+}
+
+
+async fn create_views(conn: &mut SqliteConnection) -> Result<(), AppError> {
+
+    let sql = r#"create view ppr_core_data_v as
+            select c.id, c.ror_full_id, c.ror_name, s.name as status, c.established,
+            c.location, c.csubdiv_code, c.country_code
+            from ppr_core_data c left join lup_ror_status_types s on c.status = s.id;
+
+        create view ppr_names_v as
+            select n.id, n.value, t.name as name_type, n.is_ror_name, n.lang_code, n.script_code
+            from ppr_names n left join lup_ror_name_types t on n.name_type = t.id;
+
+        create view ppr_type_v as
+            select p.id, p.ror_name, t.name as org_type
+            from ppr_type p left join lup_ror_org_types t on p.org_type = t.id;
+
+        create view ppr_external_ids_v as
+            select e.id, e.ror_name, t.name as id_type, e.id_value, e.is_preferred
+            from ppr_external_ids e left join lup_ror_id_types t on e.id_type = t.id;
+
+        create view ppr_links_v as
+            select k.id, k.ror_name, t.name as link_type, k.link
+            from ppr_links k left join lup_ror_link_types t on k.link_type = t.id;
+
+        create view ppr_relationships_v as
+            select r.id, r.ror_name, t.name as rel_type, r.related_id, r.related_name
+            from ppr_relationships r left join lup_ror_rel_types t on r.rel_type = t.id;"#;
+
+    conn.execute(sql).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    Ok(())
+}
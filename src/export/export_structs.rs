@@ -19,7 +19,7 @@ pub struct VSummary {
     pub num_domains: i32,
 }
 
-#[derive(sqlx::FromRow, Serialize)]
+#[derive(sqlx::FromRow, Serialize, Default)]
 pub struct CSVSummaryRow {
     pub vcode: String,
     pub inc_wd: bool,
@@ -49,7 +49,7 @@ pub struct TypeRow {
 }
 
 
-#[derive(sqlx::FromRow, Serialize)]
+#[derive(sqlx::FromRow, Serialize, Default)]
 pub struct CSVAttributeRow {
     pub vcode: String,
     pub inc_wd: bool,
@@ -73,7 +73,7 @@ pub struct DistribRow {
 }
 
 
-#[derive(sqlx::FromRow, Serialize)]
+#[derive(sqlx::FromRow, Serialize, Default)]
 pub struct CSVDistribRow {
   pub vcode: String,
   pub inc_wd: bool, 
@@ -95,7 +95,7 @@ pub struct RankedRow {
   pub pc_of_base_set: f32,
 }
 
-#[derive(sqlx::FromRow, Serialize)]
+#[derive(sqlx::FromRow, Serialize, Default, Clone)]
 pub struct CSVRankedRow {
   pub vcode: String,
   pub inc_wd: bool,
@@ -118,7 +118,7 @@ pub struct SingletonRow {
     pub pc: Option<f32>,
 }
 
-#[derive(sqlx::FromRow, Serialize)]
+#[derive(sqlx::FromRow, Serialize, Default)]
 pub struct CSVSingletonRow {
     pub vcode: String,
     pub inc_wd: bool,
@@ -147,7 +147,7 @@ pub struct OrgAndRel{
     pub num_orgs_pc: f32,
 }
 
-#[derive(sqlx::FromRow, Serialize)]
+#[derive(sqlx::FromRow, Serialize, Default)]
 pub struct CSVOrgAndRelRow{
     pub vcode: String,
     pub inc_wd: bool,
@@ -172,7 +172,7 @@ pub struct OrgAndLangCode{
     pub names_wolc_pc: f32,
 }
 
-#[derive(sqlx::FromRow, Serialize)]
+#[derive(sqlx::FromRow, Serialize, Default)]
 pub struct CSVOrgAndLangRow{
     pub vcode: String,
     pub inc_wd: bool,
@@ -189,7 +189,226 @@ pub struct CSVOrgAndLangRow{
     pub names_wolc_pc: f32,
 }
 
+#[derive(sqlx::FromRow, Serialize, Default)]
+pub struct CSVEstablishedRow{
+    pub vcode: String,
+    pub inc_wd: bool,
+    pub vdate: String,
+    pub vdays: i32,
+    pub grouping: String,
+    pub group_code: Option<String>,
+    pub group_name: Option<String>,
+    pub num_orgs: i32,
+    pub num_with_year: i32,
+    pub pc_with_year: f32,
+    pub earliest: Option<i32>,
+    pub quartile_1: Option<i32>,
+    pub median: Option<i32>,
+    pub quartile_3: Option<i32>,
+    pub latest: Option<i32>,
+    pub num_pre_1800: i32,
+    pub num_1800_1899: i32,
+    pub num_1900_1949: i32,
+    pub num_1950_1999: i32,
+    pub num_2000_on: i32,
+}
+
+#[derive(sqlx::FromRow, Serialize, Default)]
+pub struct CSVLastModRow{
+    pub vcode: String,
+    pub inc_wd: bool,
+    pub vdate: String,
+    pub vdays: i32,
+    pub lm_month: String,
+    pub lm_schema: String,
+    pub num_orgs: i32,
+    pub pc_of_orgs: f32,
+}
+
+#[derive(sqlx::FromRow, Serialize, Default)]
+pub struct CSVCountryRow{
+    pub vcode: String,
+    pub inc_wd: bool,
+    pub vdate: String,
+    pub vdays: i32,
+    pub country_code: String,
+    pub country_name: Option<String>,
+    pub num_orgs: i32,
+    pub pc_of_orgs: f32,
+    pub num_active: i32,
+    pub num_inactive: i32,
+    pub num_withdrawn: i32,
+    pub num_government: i32,
+    pub num_education: i32,
+    pub num_healthcare: i32,
+    pub num_company: i32,
+    pub num_nonprofit: i32,
+    pub num_funder: i32,
+    pub num_facility: i32,
+    pub num_archive: i32,
+    pub num_other: i32,
+    pub num_names: i32,
+    pub num_labels: i32,
+    pub num_aliases: i32,
+    pub num_acronyms: i32,
+    pub num_names_wolc: i32,
+    pub pc_names_wlc: f32,
+    pub num_nacro: i32,
+    pub num_nacro_wolc: i32,
+    pub pc_nacro_wlc: f32,
+    pub num_with_ext_ids: i32,
+    pub pc_with_ext_ids: f32,
+    pub num_with_isni: i32,
+    pub num_with_grid: i32,
+    pub num_with_fundref: i32,
+    pub num_with_wikidata: i32,
+    pub num_with_links: i32,
+    pub pc_with_links: f32,
+    pub num_with_website: i32,
+    pub num_with_wikipedia: i32,
+    pub num_parent_rels: i32,
+    pub num_child_rels: i32,
+    pub num_related_rels: i32,
+    pub num_pred_rels: i32,
+    pub num_succ_rels: i32,
+    pub num_with_rels: i32,
+    pub pc_with_rels: f32,
+    pub num_with_domains: i32,
+    pub pc_with_domains: f32,
+}
 
+#[derive(sqlx::FromRow, Serialize)]
+pub struct CSVCandidateDupRow{
+    pub score: i32,
+    pub id1: String,
+    pub ror_name1: String,
+    pub country_code1: Option<String>,
+    pub id2: String,
+    pub ror_name2: String,
+    pub country_code2: Option<String>,
+    pub shared_names: Option<String>,
+    pub shared_ext_ids: Option<String>,
+    pub shared_domains: Option<String>,
+    pub shared_websites: Option<String>,
+    pub distance_km: Option<f32>,
+    pub existing_rel: Option<String>,
+}
 
+#[derive(sqlx::FromRow)]
+pub struct ComparisonRow{
+    pub section: String,
+    pub item: String,
+    pub metric: String,
+    pub value_1: Option<f64>,
+    pub value_2: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct CSVComparisonRow{
+    pub vcode_1: String,
+    pub vcode_2: String,
+    pub section: String,
+    pub item: String,
+    pub metric: String,
+    pub value_1: Option<f64>,
+    pub value_2: Option<f64>,
+    pub abs_change: Option<f64>,
+    pub rel_change_pc: Option<f64>,
+    pub above_threshold: bool,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct RorCoreRow{
+    pub id: String,
+    pub ror_full_id: String,
+    pub status: String,
+    pub established: Option<i32>,
+    pub created: String,
+    pub cr_schema: String,
+    pub last_modified: String,
+    pub lm_schema: String,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct RorNameRow{
+    pub id: String,
+    pub value: String,
+    pub name_type: String,
+    pub is_ror_name: bool,
+    pub lang_code: Option<String>,
+    #[sqlx(rename = "ror_lang_code")]
+    pub ror_lang: Option<String>,
+    #[sqlx(rename = "derived_lang_code")]
+    pub der_lang: Option<String>,
+    pub script_code: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct RorLocationRow{
+    pub id: String,
+    pub geonames_id: Option<i32>,
+    pub location: Option<String>,
+    pub lat: Option<f64>,
+    pub lng: Option<f64>,
+    pub cont_code: Option<String>,
+    pub cont_name: Option<String>,
+    pub country_code: Option<String>,
+    pub country_name: Option<String>,
+    pub csubdiv_code: Option<String>,
+    pub csubdiv_name: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct RorValueRow{
+    pub id: String,
+    pub value: String,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct RorTypedValueRow{
+    pub id: String,
+    pub value_type: String,
+    pub value: String,
+    pub is_preferred: bool,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct RorRelRow{
+    pub id: String,
+    pub rel_type: String,
+    pub related_id: String,
+    pub related_name: String,
+}
+
+#[derive(sqlx::FromRow, Serialize)]
+pub struct CSVGraphNodeRow{
+    pub id: String,
+    pub name: String,
+    pub country_code: Option<String>,
+    pub types: String,
+    pub status: String,
+}
+
+#[derive(sqlx::FromRow, Serialize)]
+pub struct CSVGraphEdgeRow{
+    pub source: String,
+    pub target: String,
+    pub rel_type: String,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct GeoFeatureRow{
+    pub id: String,
+    pub ror_full_id: String,
+    pub name: String,
+    pub status: String,
+    pub types: Vec<String>,
+    pub geonames_id: Option<i32>,
+    pub location: Option<String>,
+    pub country_code: Option<String>,
+    pub country_name: Option<String>,
+    pub lat: f64,
+    pub lng: f64,
+}
 
 
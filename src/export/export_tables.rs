@@ -0,0 +1,113 @@
+use sqlx::{Pool, Postgres, postgres::PgPoolCopyExt};
+use futures_util::StreamExt;
+use flate2::{Compression, write::GzEncoder};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use crate::err::AppError;
+use chrono::Local;
+use log::info;
+use super::export_helpers::get_ppr_names_sql;
+
+// Writes the row level processed (ppr) data as delimited files, one per table, e.g. for use in
+// R or pandas. Integer codes are replaced by the corresponding lookup names, and the names file
+// includes both the ROR and derived language codes, as well as the script code, of each name.
+// The files are produced by Postgres (using COPY ... TO STDOUT), as csv with a header row and
+// either commas or tabs as delimiters, and are streamed to disk, optionally gzipped, so that the
+// full dataset never needs to be held in memory.
+
+pub async fn generate_table_files(output_folder : &PathBuf, data_version: &String, tables: &[String],
+                                  tab_delimited: bool, gzip: bool, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
+    let folder_name = format!("{} ppr tables {}", data_version, datetime_string);
+    let folder_path: PathBuf = [output_folder, &PathBuf::from(&folder_name)].iter().collect();
+    fs::create_dir_all(&folder_path)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e, folder_path.to_owned()))?;
+
+    let extension = match (tab_delimited, gzip) {
+        (true, true) => "tsv.gz",
+        (true, false) => "tsv",
+        (false, true) => "csv.gz",
+        (false, false) => "csv",
+    };
+    let delimiter = if tab_delimited {"E'\\t'"} else {"','"};
+
+    for table in tables {
+        let file_path: PathBuf = [&folder_path, &PathBuf::from(format!("{table}.{extension}"))].iter().collect();
+        let sql = format!("copy ({}) to stdout with (format csv, header true, delimiter {});",
+                          get_table_sql(table), delimiter);
+        let num_bytes = copy_to_file(&sql, &file_path, gzip, pool).await?;
+        info!("{} written ({} bytes before any compression)", file_path.display(), num_bytes);
+    }
+    Ok(())
+}
+
+
+fn get_table_sql(table: &str) -> String {
+
+    match table {
+        "core_data" => r#"select c.id, c.ror_full_id, c.ror_name, s.name as status, c.established,
+                c.location, c.csubdiv_code, c.country_code
+                from ppr.core_data c
+                inner join lup.ror_status_types s on c.status = s.id
+                order by c.id"#.to_string(),
+        "names" => get_ppr_names_sql(),
+        "external_ids" => r#"select e.id, e.ror_name, t.name as id_type, e.id_value, e.is_preferred
+                from ppr.external_ids e
+                inner join lup.ror_id_types t on e.id_type = t.id
+                order by e.id, t.name, e.is_preferred desc"#.to_string(),
+        "links" => r#"select k.id, k.ror_name, t.name as link_type, k.link
+                from ppr.links k
+                inner join lup.ror_link_types t on k.link_type = t.id
+                order by k.id, t.name"#.to_string(),
+        "type" => r#"select p.id, p.ror_name, t.name as org_type
+                from ppr.type p
+                inner join lup.ror_org_types t on p.org_type = t.id
+                order by p.id, p.org_type"#.to_string(),
+        "relationships" => r#"select r.id, r.ror_name, t.name as rel_type, r.related_id, r.related_name
+                from ppr.relationships r
+                inner join lup.ror_rel_types t on r.rel_type = t.id
+                order by r.id, r.rel_type, r.related_id"#.to_string(),
+        _ => format!("select * from ppr.{table} order by id"),    // locations, domains, admin_data
+    }
+}
+
+
+pub(super) async fn copy_to_file(sql: &str, file_path: &PathBuf, gzip: bool, pool : &Pool<Postgres>) -> Result<usize, AppError> {
+
+    let file = File::create(file_path)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.to_owned()))?;
+
+    // A gzip encoder must be explicitly finished, to write the end of the compressed stream.
+
+    if gzip {
+        let mut gz = GzEncoder::new(BufWriter::new(file), Compression::default());
+        let num_bytes = copy_to_writer(sql, &mut gz, file_path, pool).await?;
+        gz.finish().and_then(|mut w| w.flush())
+            .map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.to_owned()))?;
+        Ok(num_bytes)
+    }
+    else {
+        let mut w = BufWriter::new(file);
+        let num_bytes = copy_to_writer(sql, &mut w, file_path, pool).await?;
+        w.flush()
+            .map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.to_owned()))?;
+        Ok(num_bytes)
+    }
+}
+
+
+async fn copy_to_writer<W: Write>(sql: &str, w: &mut W, file_path: &PathBuf, pool : &Pool<Postgres>) -> Result<usize, AppError> {
+
+    let mut stream = pool.copy_out_raw(sql).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    let mut num_bytes = 0;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+        num_bytes += chunk.len();
+        w.write_all(&chunk)
+            .map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.to_owned()))?;
+    }
+    Ok(num_bytes)
+}
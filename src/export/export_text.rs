@@ -2,7 +2,7 @@ use sqlx::{Pool, Postgres};
 use std::{collections::HashMap, path::PathBuf};
 use crate::AppError;
 use chrono::{DateTime, Local};
-use super::export_structs::{VSummary, SingletonRow, Singleton};
+use super::export_structs::{VSummary, Singleton, CSVCountryRow};
 use super::export_helpers::*;
 use log::info;
 
@@ -34,25 +34,6 @@ pub async fn generate_text(output_folder : &PathBuf, vcode: &String,
     Ok(())
 }
 
-async fn collect_singleton_values(vcode: &String, inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<HashMap<String, Singleton>, AppError> {
-
-    let mut sstructs = HashMap::new();
-    let sql = format!(r#"SELECT name, description, number, pc from smm.singletons 
-              WHERE vcode = '{vcode}' and inc_wd = {inc_withdrawn};"#);
-    let srows: Vec<SingletonRow> = sqlx::query_as(&sql).fetch_all(pool).await
-        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
-    
-    for r in srows { 
-        let s = Singleton {
-            description: r.description,
-            number: r.number,
-            pc: r.pc,
-        };
-        sstructs.insert(r.name, s);
-    }
-    Ok(sstructs)
-}
-
 async fn write_header(output_file_path: &PathBuf, vcode: &String, 
                       inc_withdrawn: bool, pool: &Pool<Postgres>) -> Result<VSummary, AppError> {
     
@@ -368,3 +349,91 @@ async fn write_domain_details(output_file_path: &PathBuf, vcode: &String, inc_wi
 
     Ok(())
 }
+
+
+pub async fn generate_country_text(output_folder : &PathBuf, vcode: &String, 
+                           inc_withdrawn: bool, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // One section per country, in country code order, taken from smm.country_summaries.
+    // Percentages are of the organisations in that country, except the first, which
+    // is the country's share of all organisations in the version.
+
+    let datetime_string = Local::now().format("%Y-%m-%d %H-%M").to_string();
+    let inc_wd_tag = if inc_withdrawn {" (inc. withdrawn orgs)"} else {""};
+    let output_file_name = format!("{} country report{} at {}.txt", &vcode, inc_wd_tag, &datetime_string);
+    let output_file_path: PathBuf = [output_folder, &PathBuf::from(output_file_name)].iter().collect();
+
+    let sql = format!(r#"SELECT vs.vcode, vs.inc_wd, vs.vdate::text, vs.vdays, 
+                            ss.country_code, ss.country_name, ss.num_orgs, ss.pc_of_orgs, ss.num_active, ss.num_inactive, ss.num_withdrawn,
+                            ss.num_government, ss.num_education, ss.num_healthcare, ss.num_company, ss.num_nonprofit, 
+                            ss.num_funder, ss.num_facility, ss.num_archive, ss.num_other,
+                            ss.num_names, ss.num_labels, ss.num_aliases, ss.num_acronyms, ss.num_names_wolc, ss.pc_names_wlc, 
+                            ss.num_nacro, ss.num_nacro_wolc, ss.pc_nacro_wlc,
+                            ss.num_with_ext_ids, ss.pc_with_ext_ids, ss.num_with_isni, ss.num_with_grid, ss.num_with_fundref, ss.num_with_wikidata,
+                            ss.num_with_links, ss.pc_with_links, ss.num_with_website, ss.num_with_wikipedia,
+                            ss.num_parent_rels, ss.num_child_rels, ss.num_related_rels, ss.num_pred_rels, ss.num_succ_rels, 
+                            ss.num_with_rels, ss.pc_with_rels, ss.num_with_domains, ss.pc_with_domains
+                            from smm.country_summaries ss
+                            inner join smm.version_summaries vs 
+                            on vs.vcode = ss.vcode 
+                            and vs.inc_wd = ss.inc_wd
+                            where ss.vcode = '{vcode}' and ss.inc_wd = {inc_withdrawn}
+                            order by ss.country_code;"#);
+    let countries: Vec<CSVCountryRow> = sqlx::query_as(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let header_text = format!("\n\n\tCOUNTRY REPORT FOR {}{}\n\t{} countries listed", vcode, inc_wd_tag, countries.len());
+    append_to_file(&output_file_path, &header_text)?;
+
+    for c in countries.iter() {
+        append_to_file(&output_file_path, &get_country_text(c))?;
+    }
+
+    info!("Content appended successfully");
+    Ok(())
+}
+
+
+fn get_country_text(c: &CSVCountryRow) -> String {
+
+    let pc = |num: i32| if c.num_orgs > 0 {Some(num as f32 * 100.0 / c.num_orgs as f32)} else {None};
+    let country_name = c.country_name.as_deref().unwrap_or("(no name)");
+
+    format!("{}{}{}\n{}{}{}\n{}{}{}{}{}{}{}{}{}\n{}{}{}{}{}{}{}\n{}{}{}{}{}\n{}{}{}\n{}{}{}{}{}{}\n{}\n",
+        get_hdr_line(&format!("{} - {}", c.country_code, country_name)), get_sing_hdr(),
+        get_singleton_line("Organisations (%age of all organisations)", c.num_orgs, Some(c.pc_of_orgs)),
+        get_singleton_line("Active", c.num_active, pc(c.num_active)),
+        get_singleton_line("Inactive", c.num_inactive, pc(c.num_inactive)),
+        get_singleton_line("Withdrawn", c.num_withdrawn, pc(c.num_withdrawn)),
+        get_singleton_line("Government", c.num_government, pc(c.num_government)),
+        get_singleton_line("Education", c.num_education, pc(c.num_education)),
+        get_singleton_line("Healthcare", c.num_healthcare, pc(c.num_healthcare)),
+        get_singleton_line("Company", c.num_company, pc(c.num_company)),
+        get_singleton_line("Nonprofit", c.num_nonprofit, pc(c.num_nonprofit)),
+        get_singleton_line("Funder", c.num_funder, pc(c.num_funder)),
+        get_singleton_line("Facility", c.num_facility, pc(c.num_facility)),
+        get_singleton_line("Archive", c.num_archive, pc(c.num_archive)),
+        get_singleton_line("Other", c.num_other, pc(c.num_other)),
+        get_singleton_line("Names", c.num_names, None),
+        get_singleton_line("Labels", c.num_labels, None),
+        get_singleton_line("Aliases", c.num_aliases, None),
+        get_singleton_line("Acronyms", c.num_acronyms, None),
+        get_singleton_line("Names w/o LCs (%age of names with LCs)", c.num_names_wolc, Some(c.pc_names_wlc)),
+        get_singleton_line("Non acronym names", c.num_nacro, None),
+        get_singleton_line("Non acronym names w/o LCs (%age with LCs)", c.num_nacro_wolc, Some(c.pc_nacro_wlc)),
+        get_singleton_line("Orgs with external ids", c.num_with_ext_ids, Some(c.pc_with_ext_ids)),
+        get_singleton_line("Orgs with ISNI ids", c.num_with_isni, pc(c.num_with_isni)),
+        get_singleton_line("Orgs with GRID ids", c.num_with_grid, pc(c.num_with_grid)),
+        get_singleton_line("Orgs with FundRef ids", c.num_with_fundref, pc(c.num_with_fundref)),
+        get_singleton_line("Orgs with Wikidata ids", c.num_with_wikidata, pc(c.num_with_wikidata)),
+        get_singleton_line("Orgs with links", c.num_with_links, Some(c.pc_with_links)),
+        get_singleton_line("Orgs with websites", c.num_with_website, pc(c.num_with_website)),
+        get_singleton_line("Orgs with Wikipedia links", c.num_with_wikipedia, pc(c.num_with_wikipedia)),
+        get_singleton_line("'Has parent' relationships", c.num_parent_rels, None),
+        get_singleton_line("'Has child' relationships", c.num_child_rels, None),
+        get_singleton_line("'Is related to' relationships", c.num_related_rels, None),
+        get_singleton_line("'Has predecessor' relationships", c.num_pred_rels, None),
+        get_singleton_line("'Has successor' relationships", c.num_succ_rels, None),
+        get_singleton_line("Orgs with relationships", c.num_with_rels, Some(c.pc_with_rels)),
+        get_singleton_line("Orgs with domains", c.num_with_domains, Some(c.pc_with_domains)))
+}
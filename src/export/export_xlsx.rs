@@ -0,0 +1,174 @@
+use sqlx::{Pool, Postgres};
+use std::path::PathBuf;
+use crate::err::AppError;
+use chrono::{DateTime, Local};
+use rust_xlsxwriter::{Workbook, Format, FormatBorder};
+use serde::Serialize;
+use serde_json::Value;
+use super::export_data::{self, SummaryData};
+
+// Writes the same data as the csv export, but as a single workbook, with a front sheet
+// and then a sheet for each smm table. Cells are written as numbers, booleans or text
+// according to the value in each field, with percentage columns given two decimal places.
+
+pub async fn generate_xlsx(output_folder : &PathBuf, data_version: &String,
+                 inc_withdrawn: bool, pool : &Pool<Postgres> ) -> Result<(), AppError>
+{
+    let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
+    let dv_string = if inc_withdrawn {format!("{data_version} inc WD")} else {data_version.to_string()};
+    let output_file_name = format!("{} {} {}.xlsx", dv_string, "summary", datetime_string);
+    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
+
+    let data = export_data::fetch_version_data(data_version, inc_withdrawn, pool).await?;
+
+    // The import time is only available if this is the version currently in the src tables.
+
+    let sql = format!("SELECT import_datetime from src.version_details where version = '{data_version}';");
+    let import_dt: Option<DateTime<Local>> = sqlx::query_scalar(&sql).fetch_optional(pool).await
+           .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    let import_dt_string = match import_dt {
+        Some(dt) => dt.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => "(not the current version)".to_string(),
+    };
+
+    let mut front_lines = vec![("Version", data_version.to_string())];
+    if let Some(s) = data.summaries.first() {
+        front_lines.push(("Date", s.vdate.clone()));
+        front_lines.push(("Days since 29/04/24", s.vdays.to_string()));
+    }
+    front_lines.push(("Withdrawn orgs included", if inc_withdrawn {"Yes"} else {"No"}.to_string()));
+    front_lines.push(("Time data imported", import_dt_string));
+    front_lines.push(("Report generated", Local::now().format("%Y-%m-%d %H:%M:%S").to_string()));
+    if let Some(s) = data.summaries.first() {
+        front_lines.push(("", "".to_string()));
+        front_lines.push(("Number of organisations", s.num_recs.to_string()));
+        front_lines.push(("Active", s.num_active.to_string()));
+        front_lines.push(("Inactive", s.num_inactive.to_string()));
+        front_lines.push(("Withdrawn", s.num_withdrawn.to_string()));
+    }
+
+    let mut workbook = Workbook::new();
+    add_front_sheet(&mut workbook, "SUMMARY OF ROR DATASET", &front_lines)?;
+    add_data_sheet(&mut workbook, "Summary", &data.summaries)?;
+    add_data_sheet(&mut workbook, "Attributes", &data.attributes)?;
+    add_data_sheet(&mut workbook, "Counts", &data.counts)?;
+    add_data_sheet(&mut workbook, "Ranked counts", &data.ranked)?;
+    add_remaining_sheets(&mut workbook, &data)?;
+
+    workbook.save(&file_path)?;
+    Ok(())
+}
+
+
+pub async fn generate_all_versions_xlsx(output_folder : &PathBuf, inc_withdrawn: bool, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
+    let dv_string = if inc_withdrawn {"All versions inc WD"} else {"All versions"};
+    let output_file_name = format!("{} {} {}.xlsx", dv_string, "summary", datetime_string);
+    let file_path: PathBuf = [output_folder, &PathBuf::from(&output_file_name)].iter().collect();
+
+    let data = export_data::fetch_all_versions_data(inc_withdrawn, pool).await?;
+
+    let mut front_lines = vec![("Number of versions", data.summaries.len().to_string())];
+    if let (Some(first), Some(last)) = (data.summaries.first(), data.summaries.last()) {
+        front_lines.push(("Earliest version", format!("{} ({})", first.vcode, first.vdate)));
+        front_lines.push(("Latest version", format!("{} ({})", last.vcode, last.vdate)));
+    }
+    front_lines.push(("Withdrawn orgs included", if inc_withdrawn {"Yes"} else {"No"}.to_string()));
+    front_lines.push(("Report generated", Local::now().format("%Y-%m-%d %H:%M:%S").to_string()));
+
+    let mut workbook = Workbook::new();
+    add_front_sheet(&mut workbook, "SUMMARY OF ROR DATASETS - ALL VERSIONS", &front_lines)?;
+    add_data_sheet(&mut workbook, "Summary", &data.summaries)?;
+    add_data_sheet(&mut workbook, "Attributes", &data.attributes)?;
+    add_data_sheet(&mut workbook, "Counts", &data.counts)?;
+
+    // Ranked count distributions are split into languages (dist_id 1), scripts (2) and countries (3).
+
+    for (dist_id, sheet_name) in [(1, "Ranked languages"), (2, "Ranked scripts"), (3, "Ranked countries")] {
+        let rows: Vec<_> = data.ranked.iter().filter(|r| r.dist_id == dist_id).cloned().collect();
+        add_data_sheet(&mut workbook, sheet_name, &rows)?;
+    }
+    add_remaining_sheets(&mut workbook, &data)?;
+
+    workbook.save(&file_path)?;
+    Ok(())
+}
+
+
+fn add_remaining_sheets(workbook: &mut Workbook, data: &SummaryData) -> Result<(), AppError> {
+
+    add_data_sheet(workbook, "Singletons", &data.singletons)?;
+    add_data_sheet(workbook, "Org types and names", &data.org_langs)?;
+    add_data_sheet(workbook, "Org types and rels", &data.org_rels)?;
+    add_data_sheet(workbook, "Established years", &data.established)?;
+    add_data_sheet(workbook, "Last modified months", &data.last_modified)?;
+    add_data_sheet(workbook, "Country summaries", &data.countries)?;
+    Ok(())
+}
+
+
+fn add_front_sheet(workbook: &mut Workbook, title: &str, lines: &[(&str, String)]) -> Result<(), AppError> {
+
+    let title_format = Format::new().set_bold().set_font_size(14);
+    let label_format = Format::new().set_bold();
+
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Front")?;
+    sheet.write_string_with_format(0, 0, title, &title_format)?;
+    for (i, (label, value)) in lines.iter().enumerate() {
+        let row_num = i as u32 + 2;
+        sheet.write_string_with_format(row_num, 0, *label, &label_format)?;
+        sheet.write_string(row_num, 1, value)?;
+    }
+    sheet.set_column_width(0, 28)?;
+    sheet.set_column_width(1, 32)?;
+    Ok(())
+}
+
+
+fn add_data_sheet<T: Serialize + Default>(workbook: &mut Workbook, sheet_name: &str, rows: &[T]) -> Result<(), AppError> {
+
+    // The field names of the row struct are used as column headers, taken from a default
+    // instance so that a sheet with no data still shows them. Percentage columns (named
+    // pc, pc_... or ..._pc) are rounded to 2 places; other numbers are written as they are.
+
+    let header_format = Format::new().set_bold().set_border_bottom(FormatBorder::Thin);
+    let pc_format = Format::new().set_num_format("0.00");
+
+    let sheet = workbook.add_worksheet();
+    sheet.set_name(sheet_name)?;
+
+    let Value::Object(header_fields) = serde_json::to_value(T::default())? else {
+        return Ok(());
+    };
+    let is_pc: Vec<bool> = header_fields.keys()
+        .map(|name| name == "pc" || name.starts_with("pc_") || name.ends_with("_pc")).collect();
+    for (j, name) in header_fields.keys().enumerate() {
+        sheet.write_string_with_format(0, j as u16, name, &header_format)?;
+    }
+
+    for (i, row) in rows.iter().enumerate() {
+        let Value::Object(fields) = serde_json::to_value(row)? else {
+            continue;
+        };
+        let row_num = i as u32 + 1;
+        for (j, value) in fields.values().enumerate() {
+            let col_num = j as u16;
+            match value {
+                Value::Number(n) if is_pc.get(j).copied().unwrap_or(false) => {
+                    let pc = (n.as_f64().unwrap_or_default() * 100.0).round() / 100.0;
+                    sheet.write_number_with_format(row_num, col_num, pc, &pc_format)?;
+                },
+                Value::Number(n) => { sheet.write_number(row_num, col_num, n.as_f64().unwrap_or_default())?; },
+                Value::Bool(b) => { sheet.write_boolean(row_num, col_num, *b)?; },
+                Value::String(s) => { sheet.write_string(row_num, col_num, s)?; },
+                _ => (),   // nulls are left as empty cells
+            }
+        }
+    }
+
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofit();
+    Ok(())
+}
@@ -1,5 +1,21 @@
 mod export_text;
 mod export_csv;
+mod export_xlsx;
+mod export_json;
+mod export_html;
+mod export_md;
+mod export_report;
+mod export_compare;
+mod export_ror;
+mod export_sqlite;
+mod export_rdf;
+mod export_graph;
+mod export_geojson;
+mod export_tables;
+mod export_parquet;
+mod export_ndjson;
+mod export_crosswalk;
+mod export_data;
 mod export_structs;
 mod export_helpers;
 
@@ -7,6 +23,8 @@ use log::{info, error};
 use sqlx::{Pool, Postgres};
 use crate::{err::AppError, setup::InitParams};
 
+pub use export_helpers::{escape_markup, fetch_rows, float_col, NAME_IN_PPR};
+
 pub async fn export_as_text(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
 {
     // Write out summary data for this dataset into the designated file
@@ -15,8 +33,20 @@ pub async fn export_as_text(params: &InitParams, pool : &Pool<Postgres>) -> Resu
     let dv = get_current_data_version(pool).await?;    
     check_data_version_present_in_summary_data(&dv, params.flags.inc_withdrawn, pool).await?;  // ensure version summary is present
 
-    export_text::generate_text(&params.output_folder, &dv, params.flags.inc_withdrawn, pool).await?;
-    info!("Data summary generated as text file"); 
+    match params.report_format.as_str() {
+        "html" => {
+            export_html::generate_html(&params.output_folder, &dv, params.flags.inc_withdrawn, pool).await?;
+            info!("Data summary generated as html file"); 
+        },
+        "md" => {
+            export_md::generate_md(&params.output_folder, &dv, params.flags.inc_withdrawn, pool).await?;
+            info!("Data summary generated as markdown file"); 
+        },
+        _ => {
+            export_text::generate_text(&params.output_folder, &dv, params.flags.inc_withdrawn, pool).await?;
+            info!("Data summary generated as text file"); 
+        },
+    }
     Ok(())
 }
 
@@ -35,17 +65,205 @@ pub async fn export_as_csv(params: &InitParams, pool : &Pool<Postgres>) -> Resul
     
     check_data_version_present_in_summary_data(&dv, params.flags.inc_withdrawn, pool).await?;
     
-    // Write out summary data for this as a set of csv files into the designated folder
+    // Write out summary data for this as a set of csv files, a single xlsx workbook
+    // or a single json document, into the designated folder
 
-    export_csv::generate_csv(&params.output_folder, &dv, params.flags.inc_withdrawn, pool).await?;
-    info!("Data summary generated as csv files"); 
+    match params.output_format.as_str() {
+        "xlsx" => {
+            export_xlsx::generate_xlsx(&params.output_folder, &dv, params.flags.inc_withdrawn, pool).await?;
+            info!("Data summary generated as xlsx workbook"); 
+        },
+        "json" => {
+            export_json::generate_json(&params.output_folder, &dv, params.flags.inc_withdrawn, pool).await?;
+            info!("Data summary generated as json file"); 
+        },
+        _ => {
+            export_csv::generate_csv(&params.output_folder, &dv, params.flags.inc_withdrawn, pool).await?;
+            info!("Data summary generated as csv files"); 
+        },
+    }
     Ok(())
    
 }
 
+pub async fn export_country_report(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // Write out the country summaries for the designated (or current) version as a text file.
+
+    let mut dv = params.data_version.to_string();
+    if params.data_version.is_empty() {     // no data version given explicitly
+        dv = get_current_data_version(pool).await?;
+    }
+    check_data_version_present_in_summary_data(&dv, params.flags.inc_withdrawn, pool).await?;
+
+    export_text::generate_country_text(&params.output_folder, &dv, params.flags.inc_withdrawn, pool).await?;
+    info!("Country report generated as text file"); 
+    Ok(())
+}
+
+pub async fn export_comparison(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // Write out a comparison of the two designated versions, as a text file and a csv file.
+    // Both versions must already be present in the summary tables.
+
+    let (v1, v2) = (&params.compare_versions[0], &params.compare_versions[1]);
+    check_data_version_present_in_summary_data(v1, params.flags.inc_withdrawn, pool).await?;
+    check_data_version_present_in_summary_data(v2, params.flags.inc_withdrawn, pool).await?;
+
+    export_compare::generate_comparison(&params.output_folder, v1, v2, params.change_threshold, 
+                                        params.flags.inc_withdrawn, pool).await?;
+    info!("Version comparison generated as text and csv files"); 
+    Ok(())
+}
+
+pub async fn export_ror_json(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // Write out the current ppr data as a ROR v2 json file, in clean or enriched mode.
+
+    let dv = get_current_data_version(pool).await?;
+    let enriched = params.ror_json_mode == "enriched";
+    export_ror::generate_ror_json(&params.output_folder, &dv, enriched, pool).await?;
+    info!("Processed data written as ror json file ({})", params.ror_json_mode); 
+    Ok(())
+}
+
+pub async fn export_as_sqlite(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // Write out the ppr and lup tables, and the smm data of the current version, to a SQLite file.
+
+    let dv = get_current_data_version(pool).await?;
+    check_data_version_present_in_summary_data(&dv, params.flags.inc_withdrawn, pool).await?;
+    export_sqlite::generate_sqlite(&params.output_folder, &dv, params.flags.inc_withdrawn, pool).await?;
+    info!("Processed and summary data written to sqlite file"); 
+    Ok(())
+}
+
+pub async fn export_rdf(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // Write out the current ppr data as schema.org linked data, in the requested format.
+
+    let dv = get_current_data_version(pool).await?;
+    export_rdf::generate_rdf(&params.output_folder, &dv, &params.rdf_format, pool).await?;
+    info!("Processed data written as linked data ({})", params.rdf_format); 
+    Ok(())
+}
+
+pub async fn export_graph(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // Write out the relationships in the current ppr data as a graph, possibly filtered.
+
+    let dv = get_current_data_version(pool).await?;
+    let filters = export_helpers::OrgFilters {
+        country: params.filter_country.clone(),
+        org_type: params.filter_org_type.clone(),
+        root: params.filter_root.clone(),
+    };
+    export_graph::generate_graph(&params.output_folder, &dv, &params.graph_format, &filters, pool).await?;
+    info!("Relationship graph written as {} file(s)", params.graph_format); 
+    Ok(())
+}
+
+pub async fn export_geojson(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // Write out the locations in the current ppr data as GeoJSON, possibly filtered.
+
+    let dv = get_current_data_version(pool).await?;
+    let filters = export_helpers::OrgFilters {
+        country: params.filter_country.clone(),
+        org_type: params.filter_org_type.clone(),
+        root: params.filter_root.clone(),
+    };
+    let by_country = params.geojson_mode == "by_country";
+    export_geojson::generate_geojson(&params.output_folder, &dv, by_country, &filters, pool).await?;
+    info!("Organisation locations written as geojson ({})", params.geojson_mode); 
+    Ok(())
+}
+
+pub async fn export_ppr_tables(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // Write out the selected ppr tables, at row level, as delimited files.
+
+    let dv = get_current_data_version(pool).await?;
+    let tab_delimited = params.delimiter == "tab";
+    export_tables::generate_table_files(&params.output_folder, &dv, &params.export_tables, 
+                                        tab_delimited, params.flags.gzip_output, pool).await?;
+    info!("Processed tables written as delimited files"); 
+    Ok(())
+}
+
+pub async fn export_parquet(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // Write out the ppr tables, and the smm tables for all versions, as parquet files.
+
+    let dv = get_current_data_version(pool).await?;
+    export_parquet::generate_parquet(&params.output_folder, &dv, pool).await?;
+    info!("Processed and summary tables written as parquet files"); 
+    Ok(())
+}
+
+pub async fn export_ndjson(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // Write out the current ppr data as a search engine bulk file, one document per organisation.
+
+    let dv = get_current_data_version(pool).await?;
+    export_ndjson::generate_ndjson(&params.output_folder, &dv, &params.ndjson_index, &params.id_field, pool).await?;
+    info!("Processed data written as ndjson bulk file (index '{}')", params.ndjson_index); 
+    Ok(())
+}
+
+pub async fn export_crosswalk(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // Rebuild the external id crosswalk tables from the current ppr data, and write them out.
+
+    let dv = get_current_data_version(pool).await?;
+    export_crosswalk::generate_crosswalk(&params.output_folder, &dv, pool).await?;
+    info!("External id crosswalk tables written as csv files"); 
+    Ok(())
+}
+
+pub async fn export_candidate_dups(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // Write out the candidate duplicate organisations found in the current ppr data, 
+    // so that they can be reviewed and, where appropriate, submitted to ROR.
+
+    let dv = get_current_data_version(pool).await?;
+    export_csv::generate_candidate_dups_csv(&params.output_folder, &dv, pool).await?;
+    info!("Candidate duplicate organisations written to csv file"); 
+    Ok(())
+}
+
 pub async fn export_all_as_csv(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
 {
-    // Write out summary data for all versions as a set of csv files into the designated folder
+    // Write out summary data for all versions as a set of csv files, a single xlsx workbook
+    // or a single json document (an array of version objects), into the designated folder
+
+    if params.output_format == "xlsx" {
+        let r = export_xlsx::generate_all_versions_xlsx(&params.output_folder, params.flags.inc_withdrawn, pool).await;
+        match r {
+            Ok(()) => {
+                info!("Data summary generated as xlsx workbook"); 
+                return Ok(())
+            },
+            Err(e) => {
+                error!("An error occured while writing out the xlsx workbook: {}", e);
+                return Err(e)
+            },
+        }
+    }
+
+    if params.output_format == "json" {
+        let r = export_json::generate_all_versions_json(&params.output_folder, params.flags.inc_withdrawn, pool).await;
+        match r {
+            Ok(()) => {
+                info!("Data summary generated as json file"); 
+                return Ok(())
+            },
+            Err(e) => {
+                error!("An error occured while writing out the json file: {}", e);
+                return Err(e)
+            },
+        }
+    }
 
     let r = export_csv::generate_all_versions_csv(&params.output_folder, params.flags.inc_withdrawn, pool).await;
     match r {
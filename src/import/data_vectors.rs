@@ -529,6 +529,23 @@ pub fn extract_id_from(full_id: &String) -> &str {
 }
 
 
+pub fn parse_ror_id(id: &str) -> Option<String> {
+
+    // Validates a ROR id supplied by a user (on the command line or in a file), in either the
+    // 9 character short form or the full URL form, with or without a trailing slash. ROR ids
+    // are lower case, start with 0, and are otherwise letters and digits. Returns the short form.
+
+    let id = id.trim().trim_end_matches('/').to_lowercase();
+    let short_id = id.rsplit('/').next().unwrap_or("");
+    if short_id.len() == 9 && short_id.starts_with('0') && short_id.chars().all(|ch| ch.is_ascii_alphanumeric()) {
+        Some(short_id.to_string())
+    }
+    else {
+        None
+    }
+}
+
+
 pub async fn store_strange_ror_record(id: &str, name: &str, oddity_type: i32, pool : &Pool<Postgres>) -> Result<PgQueryResult, AppError> {
 
     let sql = r#"INSERT INTO rec.strange_ror_names (id, value, oddity_type) values ($1, $2, $3);"#;
@@ -551,5 +568,17 @@ mod tests {
         let test_id = "https://src.org/123456789".to_string();
         assert_eq!(extract_id_from(&(test_id)), "123456789")
     }
+
+    // And that user supplied ids are validated in the same way, whatever their form.
+
+    #[test]
+    fn test_parsing_user_supplied_id() {
+        assert_eq!(parse_ror_id("04ttjf776"), Some("04ttjf776".to_string()));
+        assert_eq!(parse_ror_id(" https://ror.org/04TTJF776/ "), Some("04ttjf776".to_string()));
+        assert_eq!(parse_ror_id("ror.org/04ttjf776"), Some("04ttjf776".to_string()));
+        assert_eq!(parse_ror_id("x04ttjf776"), None);
+        assert_eq!(parse_ror_id("ror.org/0-x"), None);
+        assert_eq!(parse_ror_id(""), None);
+    }
 }
 
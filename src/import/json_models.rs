@@ -21,6 +21,14 @@ use serde::{Serialize, Deserialize};
         pub value: String,
         pub lang: Option<String>,
         pub types: Vec<String>,
+
+        // Extension fields, not part of the ROR schema, only
+        // written when exporting 'enriched' ROR json.
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub derived_lang: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub script: Option<String>,
     }
 
     #[derive(Serialize, Deserialize, Debug)]
@@ -44,7 +52,7 @@ use serde::{Serialize, Deserialize};
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct ExternalId {
-        #[serde(rename(deserialize = "type"))]
+        #[serde(rename = "type")]
         pub id_type: String,
         pub all: Vec<String>,
         pub preferred: Option<String>,
@@ -53,7 +61,7 @@ use serde::{Serialize, Deserialize};
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct Link {
-        #[serde(rename(deserialize = "type"))]
+        #[serde(rename = "type")]
         pub link_type: String,
         pub value: String,
     }
@@ -61,7 +69,7 @@ use serde::{Serialize, Deserialize};
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct Relationship {
-        #[serde(rename(deserialize = "type"))]
+        #[serde(rename = "type")]
         pub rel_type: String,
         pub label: String,
         pub id: String,
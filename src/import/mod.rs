@@ -1,6 +1,6 @@
 
-mod json_models;
-mod data_vectors;
+pub mod json_models;
+pub mod data_vectors;
 
 use crate::{setup::InitParams, sql::create_src_tables};
 use log::info;
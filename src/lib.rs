@@ -5,7 +5,11 @@ mod import;
 mod process;
 mod summarise;
 mod export;
+mod lookup;
+mod matching;
+mod server;
 mod sql;
+mod text_norm;
 
 use err::AppError;
 use std::ffi::OsString;
@@ -49,6 +53,7 @@ pub async fn run(args: Vec<OsString>) -> Result<(), AppError> {
         process::process_data(&params, &pool).await?;
         summarise::store_summary_data(&params, &pool).await?;
         export::export_as_text(&params, &pool).await?;
+        export::export_candidate_dups(&params, &pool).await?;
     }
 
 
@@ -62,9 +67,94 @@ pub async fn run(args: Vec<OsString>) -> Result<(), AppError> {
         export::export_all_as_csv(&params, &pool).await?;
     }
 
+    if flags.country_report  // write out the country summaries in the smm tables as a text report
+    {
+        export::export_country_report(&params, &pool).await?;
+    }
+
+    if flags.compare_versions  // write out a comparison of two versions in the smm tables
+    {
+        export::export_comparison(&params, &pool).await?;
+    }
+
+    if flags.export_ror_json  // write out the processed data in the ppr tables as ror json
+    {
+        export::export_ror_json(&params, &pool).await?;
+    }
+
+    if flags.export_sqlite  // write out the ppr, lup and (current version) smm tables to a sqlite file
+    {
+        export::export_as_sqlite(&params, &pool).await?;
+    }
+
+    if flags.export_rdf  // write out the ppr data as schema.org linked data
+    {
+        export::export_rdf(&params, &pool).await?;
+    }
+
+    if flags.export_graph  // write out the relationships in the ppr data as a graph
+    {
+        export::export_graph(&params, &pool).await?;
+    }
+
+    if flags.export_geojson  // write out the locations in the ppr data as geojson
+    {
+        export::export_geojson(&params, &pool).await?;
+    }
+
+    if flags.export_tables  // write out the selected ppr tables as delimited files
+    {
+        export::export_ppr_tables(&params, &pool).await?;
+    }
+
+    if flags.export_parquet  // write out the ppr and smm tables as parquet files
+    {
+        export::export_parquet(&params, &pool).await?;
+    }
+
+    if flags.export_ndjson  // write out the ppr data as a search engine bulk file
+    {
+        export::export_ndjson(&params, &pool).await?;
+    }
+
+    if flags.export_crosswalk  // rebuild and write out the external id crosswalk tables
+    {
+        export::export_crosswalk(&params, &pool).await?;
+    }
+
+    if flags.lookup_orgs  // print the processed records of the requested organisations
+    {
+        lookup::lookup_orgs(&params, &pool).await?;
+    }
+
+    if flags.search_names  // print the organisations with names matching the search text
+    {
+        lookup::search_orgs(&params, &pool).await?;
+    }
+
+    if flags.match_affiliation  // print the ranked candidate organisations for an affiliation string
+    {
+        matching::match_affiliation(&params, &pool).await?;
+    }
+
+    if flags.match_file  // match each affiliation in a csv file, writing the results to a csv file
+    {
+        matching::match_file(&params, &pool).await?;
+    }
+
+    if flags.enrich_ids  // add data about each organisation in a csv file of ROR ids, writing to a new csv file
+    {
+        lookup::enrich_ids::enrich_file(&params, &pool).await?;
+    }
+
     if flags.test_run {  // Clear any test data from the smm tables.
         summarise::smm_helper::delete_any_existing_data(&"v99".to_string(), false, &pool).await?;
     }
 
+    if flags.reconcile_server || flags.rest_server  // run the reconciliation service and / or REST API, until stopped
+    {
+        server::run_server(&params, &pool).await?;
+    }
+
     Ok(())
 }
@@ -0,0 +1,275 @@
+use std::collections::{HashMap, HashSet};
+use chrono::Local;
+use log::info;
+use sqlx::{Pool, Postgres, FromRow};
+use crate::{err::AppError, setup::InitParams};
+use crate::import::data_vectors::parse_ror_id;
+use crate::process::crosswalk::ID_SCHEMES;
+use super::org_record::withdrawn_table_exists;
+
+// Adds data from the ppr tables to each row of a csv file that includes a column of ROR ids
+// (short or full form). Ids of withdrawn organisations are resolved to their successors, using
+// rec.withdrawn (if withdrawn organisations were removed during processing) or the successor
+// relationships in ppr.relationships (if they were retained), following chains of successors.
+// Each row is written out again with a match status, the id actually used, and the requested
+// fields appended. Multiple values in a field (e.g. types, or several ISNIs) are separated
+// by '; ', with any preferred external id first.
+
+const MAX_SUCCESSOR_HOPS: usize = 5;
+
+// The fields that can be requested, and the columns each adds.
+
+pub const ENRICH_FIELDS: [(&str, &[&str]); 9] = [("ror_name", &["ror_name"]), ("country", &["country_code", "country_name"]),
+    ("types", &["types"]), ("status", &["status"]), ("parent", &["parent_id", "parent_name"]), ("grid", &["grid"]),
+    ("isni", &["isni"]), ("fundref", &["fundref"]), ("wikidata", &["wikidata"])];
+
+#[derive(FromRow)]
+struct EnrichData {
+    id: String,
+    ror_name: String,
+    status: String,
+    country_code: Option<String>,
+    country_name: Option<String>,
+    types: String,
+    parent_id: String,
+    parent_name: String,
+    grid: String,
+    isni: String,
+    fundref: String,
+    wikidata: String,
+}
+
+impl EnrichData {
+    fn get_value(&self, column: &str) -> &str {
+        match column {
+            "ror_name" => &self.ror_name,
+            "country_code" => self.country_code.as_deref().unwrap_or(""),
+            "country_name" => self.country_name.as_deref().unwrap_or(""),
+            "types" => &self.types,
+            "status" => &self.status,
+            "parent_id" => &self.parent_id,
+            "parent_name" => &self.parent_name,
+            "grid" => &self.grid,
+            "isni" => &self.isni,
+            "fundref" => &self.fundref,
+            "wikidata" => &self.wikidata,
+            _ => "",
+        }
+    }
+}
+
+
+pub async fn enrich_file(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let mut rdr = csv::Reader::from_path(&params.enrich_file)
+                .map_err(AppError::CsvError)?;
+    let headers = rdr.headers().map_err(AppError::CsvError)?.clone();
+    let col = match headers.iter().position(|h| h.trim() == params.id_column) {
+        Some(i) => i,
+        None => return Err(AppError::InconsistentProgramParameter(format!("column '{}' not found in {}",
+                            params.id_column, params.enrich_file.display()))),
+    };
+    let records = rdr.records().collect::<Result<Vec<csv::StringRecord>, csv::Error>>()
+                .map_err(AppError::CsvError)?;
+
+    // Resolve all the (valid) ids at once, then get the data for the organisations found.
+
+    let ids: Vec<String> = records.iter().filter_map(|r| parse_ror_id(r.get(col).unwrap_or("")))
+                                  .collect::<HashSet<String>>().into_iter().collect();
+    let resolved = resolve_ids(&ids, pool).await?;
+    let target_ids: Vec<String> = resolved.values().filter_map(|(_, r)| r.clone())
+                                  .collect::<HashSet<String>>().into_iter().collect();
+    let data: HashMap<String, EnrichData> = fetch_enrich_data(&target_ids, pool).await?
+                                  .into_iter().map(|d| (d.id.clone(), d)).collect();
+
+    let columns: Vec<&str> = ENRICH_FIELDS.iter().filter(|(f, _)| params.enrich_fields.iter().any(|ef| ef == f))
+                                  .flat_map(|(_, cs)| cs.iter().copied()).collect();
+
+    let stem = params.enrich_file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
+    let output_file_name = format!("{} enriched {}.csv", stem, datetime_string);
+    let output_file_path = params.output_folder.join(&output_file_name);
+    let mut wtr = csv::Writer::from_path(&output_file_path)
+                .map_err(AppError::CsvError)?;
+
+    let mut out_headers = headers.clone();
+    out_headers.push_field("match_status");
+    out_headers.push_field("resolved_id");
+    for c in &columns {
+        out_headers.push_field(c);
+    }
+    wtr.write_record(&out_headers).map_err(AppError::CsvError)?;
+
+    let mut status_counts: HashMap<&str, usize> = HashMap::new();
+    for mut record in records {
+        let raw = record.get(col).unwrap_or("").trim().to_string();
+        let (status, resolved_id) = if raw.is_empty() {
+            ("no id", None)
+        } else {
+            match parse_ror_id(&raw) {
+                None => ("invalid id", None),
+                Some(id) => match resolved.get(&id) {
+                    Some((s, r)) => (*s, r.clone()),
+                    None => ("not found", None),
+                },
+            }
+        };
+        *status_counts.entry(status).or_default() += 1;
+
+        record.push_field(status);
+        record.push_field(resolved_id.as_deref().unwrap_or(""));
+        let d = resolved_id.as_ref().and_then(|r| data.get(r));
+        for c in &columns {
+            record.push_field(d.map(|d| d.get_value(c)).unwrap_or(""));
+        }
+        wtr.write_record(&record).map_err(AppError::CsvError)?;
+    }
+    wtr.flush()?;
+
+    let mut counts: Vec<(&str, usize)> = status_counts.into_iter().collect();
+    counts.sort();
+    info!("{} rows enriched: {}", counts.iter().map(|(_, n)| n).sum::<usize>(),
+           counts.iter().map(|(s, n)| format!("{n} {s}")).collect::<Vec<String>>().join(", "));
+    info!("Results written to {}", output_file_name);
+    Ok(())
+}
+
+
+async fn resolve_ids(ids: &[String], pool : &Pool<Postgres>) -> Result<HashMap<String, (&'static str, Option<String>)>, AppError> {
+
+    // Returns, for each id found, its match status and the id whose data is to be used (if any).
+    // The statuses of the ids, and the successors of any withdrawn (or absent) ones, are loaded
+    // in bulk, one step along the chains of successors at a time, and the chains then followed.
+
+    let mut statuses = fetch_statuses(ids, pool).await?;
+    let has_withdrawn_table = withdrawn_table_exists(pool).await?;
+    let mut successors: HashMap<String, Vec<String>> = HashMap::new();
+    let mut withdrawn: HashSet<String> = HashSet::new();
+    let mut looked_up: HashSet<String> = HashSet::new();
+
+    let mut pending: Vec<String> = ids.iter().filter(|id| statuses.get(*id).is_none_or(|s| *s == 3)).cloned().collect();
+    for _ in 0..MAX_SUCCESSOR_HOPS {
+        if pending.is_empty() {
+            break;
+        }
+        let (succs, wds) = fetch_successors(&pending, has_withdrawn_table, pool).await?;
+        looked_up.extend(pending);
+        withdrawn.extend(wds);
+        let new_ids: Vec<String> = succs.values().flatten().filter(|s| !statuses.contains_key(*s))
+                                        .cloned().collect::<HashSet<String>>().into_iter().collect();
+        statuses.extend(fetch_statuses(&new_ids, pool).await?);
+        pending = succs.values().filter_map(|v| v.first())
+                       .filter(|s| statuses.get(*s).is_none_or(|st| *st == 3) && !looked_up.contains(*s))
+                       .cloned().collect::<HashSet<String>>().into_iter().collect();
+        successors.extend(succs);
+    }
+
+    Ok(ids.iter().filter_map(|id| resolve_id(id, &statuses, &successors, &withdrawn).map(|r| (id.clone(), r))).collect())
+}
+
+
+fn resolve_id(id: &String, statuses: &HashMap<String, i32>, successors: &HashMap<String, Vec<String>>,
+              withdrawn: &HashSet<String>) -> Option<(&'static str, Option<String>)> {
+
+    // Withdrawn organisations are followed along their (first) successors, until one that has
+    // not been withdrawn is found. If that fails, the organisation's own data is used, if
+    // still in the ppr tables, with a status saying why.
+
+    match statuses.get(id) {
+        Some(3) => (),
+        Some(_) => return Some(("found", Some(id.clone()))),
+        None if withdrawn.contains(id) => (),
+        None => return None,
+    }
+    let own_data = statuses.contains_key(id).then(|| id.clone());
+
+    let mut current = id;
+    for _ in 0..MAX_SUCCESSOR_HOPS {
+        let succs = match successors.get(current) {
+            Some(s) if !s.is_empty() => s,
+            _ if current == id => return Some(("withdrawn, no successor", own_data)),
+            _ => return Some(("withdrawn, successor withdrawn", own_data)),
+        };
+        let s = &succs[0];
+        match statuses.get(s) {
+            Some(3) => (),
+            Some(_) => return Some((if succs.len() > 1 {"successor (first of several)"} else {"successor"}, Some(s.clone()))),
+            None if withdrawn.contains(s) => (),
+            None => return Some(("withdrawn, successor not found", own_data)),
+        }
+        current = s;
+    }
+    Some(("withdrawn, successor chain too long", own_data))
+}
+
+
+async fn fetch_statuses(ids: &[String], pool : &Pool<Postgres>) -> Result<HashMap<String, i32>, AppError> {
+    let sql = "select id, status from ppr.core_data where id = any($1);";
+    Ok(sqlx::query_as::<_, (String, i32)>(sql).bind(ids).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?.into_iter().collect())
+}
+
+
+async fn fetch_successors(ids: &[String], has_withdrawn_table: bool,
+                          pool : &Pool<Postgres>) -> Result<(HashMap<String, Vec<String>>, HashSet<String>), AppError> {
+
+    // Returns the successors of each id, and those ids listed in rec.withdrawn. Successors are in
+    // rec.withdrawn if withdrawn organisations were removed (when the table exists), otherwise
+    // in ppr.relationships.
+
+    let sql = r#"select id, related_id from ppr.relationships
+                 where id = any($1) and rel_type = 5
+                 order by id, related_id;"#;
+    let rows: Vec<(String, String)> = sqlx::query_as(sql).bind(ids).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    let mut successors: HashMap<String, Vec<String>> = HashMap::new();
+    for (id, s) in rows {
+        successors.entry(id).or_default().push(s);
+    }
+
+    let mut withdrawn = HashSet::new();
+    if has_withdrawn_table {
+        let sql = r#"select ror_id, successor_id from rec.withdrawn
+                     where ror_id = any($1)
+                     order by ror_id, successor_id;"#;
+        let rows: Vec<(String, Option<String>)> = sqlx::query_as(sql).bind(ids).fetch_all(pool).await
+            .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+        let mut wd_successors: HashMap<String, Vec<String>> = HashMap::new();
+        for (id, s) in rows {
+            withdrawn.insert(id.clone());
+            if let Some(s) = s {
+                wd_successors.entry(id).or_default().push(s);
+            }
+        }
+        for (id, s) in wd_successors {
+            successors.entry(id).or_insert(s);
+        }
+    }
+    Ok((successors, withdrawn))
+}
+
+
+async fn fetch_enrich_data(ids: &[String], pool : &Pool<Postgres>) -> Result<Vec<EnrichData>, AppError> {
+
+    // The external ids are those of the crosswalk schemes, each named by the scheme.
+
+    let ext_ids: String = ID_SCHEMES.iter().map(|(scheme, code)| format!(r#",
+        coalesce((select string_agg(e.id_value, '; ' order by e.is_preferred desc, e.id_value)
+                  from ppr.external_ids e where e.id = c.id and e.id_type = {code}), '') as {scheme}"#)).collect();
+    let sql = format!(r#"select c.id, c.ror_name, s.name as status, c.country_code,
+        (select l.country_name from ppr.locations l
+         where l.id = c.id and l.country_code = c.country_code limit 1) as country_name,
+        coalesce((select string_agg(t.name, '; ' order by p.org_type) from ppr.type p
+                  inner join lup.ror_org_types t on p.org_type = t.id
+                  where p.id = c.id and p.org_type <> 1000), '') as types,
+        coalesce((select string_agg(r.related_id, '; ' order by r.related_id) from ppr.relationships r
+                  where r.id = c.id and r.rel_type = 1), '') as parent_id,
+        coalesce((select string_agg(r.related_name, '; ' order by r.related_id) from ppr.relationships r
+                  where r.id = c.id and r.rel_type = 1), '') as parent_name{ext_ids}
+        from ppr.core_data c
+        inner join lup.ror_status_types s on c.status = s.id
+        where c.id = any($1);"#);
+
+    sqlx::query_as(&sql).bind(ids).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
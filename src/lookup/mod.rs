@@ -0,0 +1,68 @@
+pub mod org_record;
+mod org_text;
+pub mod name_search;
+pub mod org_list;
+pub mod enrich_ids;
+
+use log::info;
+use sqlx::{Pool, Postgres};
+use crate::{err::AppError, setup::InitParams};
+
+pub async fn lookup_orgs(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // Print the full processed record of each requested organisation to stdout, as text or as
+    // a json array (the log goes to stderr, so json output can be piped). Ids not in the ppr
+    // data are reported in the log, with any successor if the organisation was withdrawn.
+
+    let mut records = Vec::new();
+    for id in &params.org_ids {
+        match org_record::fetch_org_record(id, pool).await? {
+            Some(r) => records.push(r),
+            None => {
+                let wds = org_record::fetch_withdrawn_org(id, pool).await?;
+                if wds.is_empty() {
+                    info!("{} not found in the processed data", id);
+                }
+                for w in wds {
+                    match w.successor_id {
+                        Some(s) => info!("{} ({}) has been withdrawn - successor {} ({})", id, w.ror_name,
+                                         s, w.succ_name.unwrap_or_default()),
+                        None => info!("{} ({}) has been withdrawn, with no successor", id, w.ror_name),
+                    }
+                }
+            },
+        }
+    }
+
+    if params.org_format == "json" {
+        println!("{}", serde_json::to_string_pretty(&records)?);
+    }
+    else {
+        for r in &records {
+            println!("{}", org_text::get_org_text(r));
+        }
+    }
+    info!("{} of {} organisation records found", records.len(), params.org_ids.len());
+    Ok(())
+}
+
+pub async fn search_orgs(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // Print the organisations with names matching the search text, best matches first, to stdout.
+
+    let filters = name_search::SearchFilters {
+        country: params.filter_country.clone(),
+        org_type: params.filter_org_type.clone(),
+        status: params.filter_status.clone(),
+    };
+    let results = name_search::search_names(&params.search_text, &filters, params.search_limit, 0, pool).await?;
+
+    println!("rank score  id         cc  status    name [types] (matched name)");
+    for (i, r) in results.iter().enumerate() {
+        let matched = if r.matched_name != r.ror_name {format!(" ({}: {})", r.match_type, r.matched_name)} else {"".to_string()};
+        println!("{:<4} {:<6} {:<10} {:<3} {:<9} {} [{}]{}", i + 1, r.score, r.id, r.country_code.as_deref().unwrap_or("-"),
+                 r.status, r.ror_name, r.types, matched);
+    }
+    info!("{} organisations found matching '{}'", results.len(), params.search_text);
+    Ok(())
+}
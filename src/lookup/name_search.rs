@@ -0,0 +1,114 @@
+use sqlx::{Pool, Postgres, FromRow};
+use serde::Serialize;
+use crate::err::AppError;
+use crate::text_norm::{FOLDS, MATCH_STOP_WORDS};
+
+// Searches the names of the processed organisations, i.e. both the cleaned names in ppr.names
+// and the simplified match names in ppr.names_to_match. Both the query and the names are put
+// into lower case, have the more common diacritics removed and have punctuation replaced by
+// spaces, so that the search is case and accent insensitive. For match names the stop words
+// removed by add_scripts::prepare_match_names are also removed from the query.
+// A name can match exactly, at its start (prefix), at the start of a later word, or anywhere
+// (substring), with scores of 100, 80, 60 and 40 respectively, 5 less if the match is to a match
+// name, and 5 more if the name is the ROR name. Organisations are ranked by their best match.
+
+// Filters that restrict the organisations returned (empty strings if not used).
+
+pub struct SearchFilters {
+    pub country: String,
+    pub org_type: String,
+    pub status: String,
+}
+
+#[derive(FromRow, Serialize)]
+pub struct SearchResult {
+    pub id: String,
+    pub ror_name: String,
+    pub matched_name: String,
+    pub match_type: String,
+    pub score: i32,
+    pub country_code: Option<String>,
+    pub status: String,
+    pub types: String,
+    #[serde(skip_serializing)]
+    pub total: i64,                     // all matching organisations, before the limit and offset
+}
+
+
+pub async fn search_names(query: &str, filters: &SearchFilters, limit: i64, offset: i64,
+                          pool : &Pool<Postgres>) -> Result<Vec<SearchResult>, AppError> {
+
+    let sql = format!(r#"with q as (select {} as qn, {} as qm),
+        cands as (
+            select n.id, n.value as matched_name, {} as nn, n.is_ror_name, false as is_match_name
+            from ppr.names n
+            union all
+            select m.id, m.match_name, {}, false, true
+            from ppr.names_to_match m
+            where m.match_name is not null
+        ),
+        scored as (
+            select c.id, c.matched_name, c.is_ror_name, c.is_match_name,
+            case
+                when c.nn = x.qv then 100
+                when c.nn like x.qv || '%' then 80
+                when c.nn like '% ' || x.qv || '%' then 60
+                when c.nn like '%' || x.qv || '%' then 40
+                else 0
+            end as base
+            from cands c
+            cross join q
+            cross join lateral (select case when c.is_match_name then q.qm else q.qn end as qv) x
+            where x.qv <> ''
+        ),
+        best as (
+            select distinct on (s.id) s.id, s.matched_name,
+            case s.base when 100 then 'exact' when 80 then 'prefix' when 60 then 'word prefix' else 'substring' end
+                || case when s.is_match_name then ' (match name)' else '' end as match_type,
+            s.base - case when s.is_match_name then 5 else 0 end + case when s.is_ror_name then 5 else 0 end as score
+            from scored s
+            where s.base > 0
+            order by s.id, score desc, length(s.matched_name)
+        )
+        select b.id, c.ror_name, b.matched_name, b.match_type, b.score, c.country_code, st.name as status,
+        coalesce((select string_agg(t.name, ', ' order by p.org_type) from ppr.type p
+                  inner join lup.ror_org_types t on p.org_type = t.id
+                  where p.id = c.id and p.org_type <> 1000), '') as types,
+        count(*) over () as total
+        from best b
+        inner join ppr.core_data c on b.id = c.id
+        inner join lup.ror_status_types st on c.status = st.id
+        where ($2 = '' or c.country_code = $2)
+        and ($3 = '' or c.id in (select p.id from ppr.type p
+                                 inner join lup.ror_org_types t on p.org_type = t.id
+                                 where t.name = $3))
+        and ($4 = '' or st.name = $4)
+        order by b.score desc, length(b.matched_name), c.ror_name
+        limit $5 offset $6;"#,
+        get_norm_expr("$1"), get_norm_match_expr("$1"), get_norm_expr("n.value"), get_norm_match_expr("m.match_name"));
+
+    sqlx::query_as(&sql)
+        .bind(query).bind(&filters.country).bind(&filters.org_type).bind(&filters.status).bind(limit).bind(offset)
+        .fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
+
+
+fn get_norm_expr(expr: &str) -> String {
+
+    // Lower case, diacritics removed (as by text_norm::fold_char), and runs of anything other than letters and digits
+    // replaced by a single space.
+
+    let from: String = FOLDS.iter().map(|(cs, _)| *cs).collect();
+    let to: String = FOLDS.iter().map(|(cs, c)| c.to_string().repeat(cs.chars().count())).collect();
+    format!("trim(regexp_replace(translate(lower({expr}), '{from}', '{to}'), '[^[:alnum:]]+', ' ', 'g'))")
+}
+
+
+fn get_norm_match_expr(expr: &str) -> String {
+
+    // As above, with the stop words also removed.
+
+    let stop_words = MATCH_STOP_WORDS.iter().map(|(w, _)| *w).collect::<Vec<&str>>().join("|");
+    format!("trim(regexp_replace(' ' || {} || ' ', ' ({stop_words})(?= )', '', 'g'))", get_norm_expr(expr))
+}
@@ -0,0 +1,43 @@
+use sqlx::{Pool, Postgres, FromRow};
+use serde::Serialize;
+use crate::err::AppError;
+use super::name_search::SearchFilters;
+
+// A page of the processed organisations, in ROR name order, optionally restricted by country,
+// organisation type and status (as for a name search), for listing without a search text.
+
+#[derive(FromRow, Serialize)]
+pub struct OrgSummary {
+    pub id: String,
+    pub ror_name: String,
+    pub status: String,
+    pub country_code: Option<String>,
+    pub types: String,
+    #[serde(skip_serializing)]
+    pub total: i64,                     // all matching organisations, before the limit and offset
+}
+
+
+pub async fn list_orgs(filters: &SearchFilters, limit: i64, offset: i64,
+                       pool : &Pool<Postgres>) -> Result<Vec<OrgSummary>, AppError> {
+
+    let sql = r#"select c.id, c.ror_name, st.name as status, c.country_code,
+        coalesce((select string_agg(t.name, ', ' order by p.org_type) from ppr.type p
+                  inner join lup.ror_org_types t on p.org_type = t.id
+                  where p.id = c.id and p.org_type <> 1000), '') as types,
+        count(*) over () as total
+        from ppr.core_data c
+        inner join lup.ror_status_types st on c.status = st.id
+        where ($1 = '' or c.country_code = $1)
+        and ($2 = '' or c.id in (select p.id from ppr.type p
+                                 inner join lup.ror_org_types t on p.org_type = t.id
+                                 where t.name = $2))
+        and ($3 = '' or st.name = $3)
+        order by c.ror_name, c.id
+        limit $4 offset $5;"#;
+
+    sqlx::query_as(sql)
+        .bind(&filters.country).bind(&filters.org_type).bind(&filters.status).bind(limit).bind(offset)
+        .fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
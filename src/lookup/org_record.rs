@@ -0,0 +1,190 @@
+use sqlx::{Pool, Postgres, FromRow};
+use serde::Serialize;
+use crate::err::AppError;
+use crate::export::{fetch_rows, float_col, NAME_IN_PPR};
+
+// The full processed record of an organisation, assembled from the ppr tables, with the names
+// taken from rec.names, so that the original value of each name, its cleaned (display) and match
+// values, and the changes made during processing can be shown. Names removed as duplicates are
+// included, but marked as not being in the ppr data.
+
+#[derive(Serialize)]
+pub struct OrgRecord {
+    #[serde(flatten)]
+    pub core: OrgCore,
+    pub types: Vec<String>,
+    pub names: Vec<OrgName>,
+    pub locations: Vec<OrgLocation>,
+    pub external_ids: Vec<OrgExternalId>,
+    pub links: Vec<OrgLink>,
+    pub relationships: Vec<OrgRelationship>,
+    pub admin: serde_json::Value,
+}
+
+#[derive(FromRow, Serialize)]
+pub struct OrgCore {
+    pub id: String,
+    pub ror_full_id: String,
+    pub ror_name: String,
+    pub status: String,
+    pub established: Option<i32>,
+    pub location: Option<String>,
+    pub csubdiv_code: Option<String>,
+    pub country_code: Option<String>,
+}
+
+#[derive(FromRow, Serialize)]
+pub struct OrgName {
+    pub value: String,
+    pub original_value: String,
+    pub match_name: Option<String>,
+    pub name_type: String,
+    pub is_ror_name: bool,
+    pub lang_code: Option<String>,
+    pub ror_lang: Option<String>,
+    pub der_lang: Option<String>,
+    pub script_code: Option<String>,
+    pub change_type_id: Option<String>,
+    pub change_type: Option<String>,
+    pub qflags: Option<String>,
+    pub in_ppr: bool,
+}
+
+#[derive(FromRow, Serialize)]
+pub struct OrgLocation {
+    pub geonames_id: Option<i32>,
+    pub location: Option<String>,
+    pub csubdiv_code: Option<String>,
+    pub csubdiv_name: Option<String>,
+    pub country_code: Option<String>,
+    pub country_name: Option<String>,
+    pub lat: Option<f64>,
+    pub lng: Option<f64>,
+}
+
+#[derive(FromRow, Serialize)]
+pub struct OrgExternalId {
+    pub id_type: String,
+    pub id_value: String,
+    pub is_preferred: bool,
+}
+
+#[derive(FromRow, Serialize)]
+pub struct OrgLink {
+    pub link_type: String,
+    pub link: String,
+}
+
+#[derive(FromRow, Serialize)]
+pub struct OrgRelationship {
+    pub rel_type: String,
+    pub related_id: String,
+    pub related_name: String,
+}
+
+#[derive(FromRow)]
+pub struct WithdrawnOrg {
+    pub ror_name: String,
+    pub successor_id: Option<String>,
+    pub succ_name: Option<String>,
+}
+
+
+pub async fn fetch_org_record(id: &str, pool : &Pool<Postgres>) -> Result<Option<OrgRecord>, AppError> {
+
+    let sql = r#"select c.id, c.ror_full_id, c.ror_name, s.name as status, c.established,
+                c.location, c.csubdiv_code, c.country_code
+                from ppr.core_data c
+                inner join lup.ror_status_types s on c.status = s.id
+                where c.id = $1;"#;
+    let core: Option<OrgCore> = sqlx::query_as(sql).bind(id).fetch_optional(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    let core = match core {
+        Some(c) => c,
+        None => return Ok(None),
+    };
+
+    let sql = r#"select t.name from ppr.type p
+                inner join lup.ror_org_types t on p.org_type = t.id
+                where p.id = $1 and p.org_type <> 1000
+                order by p.org_type;"#;
+    let types: Vec<String> = sqlx::query_scalar(sql).bind(id).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    // All the names in rec.names, including any not carried over to ppr.names.
+
+    let sql = format!(r#"select n.display_name as value, n.orig_name as original_value, n.match_name,
+                t.name as name_type, n.is_ror_name, n.lang as lang_code, n.ror_lang, n.der_lang,
+                nullif(n.der_script, '') as script_code, n.change_type_id, n.change_type, n.qflags,
+                {NAME_IN_PPR} as in_ppr
+                from rec.names n
+                inner join lup.ror_name_types t on n.name_type = t.id
+                where n.id = $1
+                order by n.is_ror_name desc, n.name_type, n.display_name;"#);
+    let names: Vec<OrgName> = fetch_rows(&sql, &[id], pool).await?;
+
+    let sql = format!(r#"select geonames_id, location, csubdiv_code, csubdiv_name, country_code, country_name,
+                {} as lat, {} as lng
+                from ppr.locations
+                where id = $1
+                order by geonames_id;"#, float_col("lat"), float_col("lng"));
+    let locations: Vec<OrgLocation> = fetch_rows(&sql, &[id], pool).await?;
+
+    let sql = r#"select t.name as id_type, e.id_value, e.is_preferred
+                from ppr.external_ids e
+                inner join lup.ror_id_types t on e.id_type = t.id
+                where e.id = $1
+                order by t.name, e.is_preferred desc, e.id_value;"#;
+    let external_ids: Vec<OrgExternalId> = fetch_rows(sql, &[id], pool).await?;
+
+    let sql = r#"select t.name as link_type, k.link
+                from ppr.links k
+                inner join lup.ror_link_types t on k.link_type = t.id
+                where k.id = $1
+                order by t.name, k.link;"#;
+    let links: Vec<OrgLink> = fetch_rows(sql, &[id], pool).await?;
+
+    let sql = r#"select t.name as rel_type, r.related_id, r.related_name
+                from ppr.relationships r
+                inner join lup.ror_rel_types t on r.rel_type = t.id
+                where r.id = $1
+                order by r.rel_type, r.related_id;"#;
+    let relationships: Vec<OrgRelationship> = fetch_rows(sql, &[id], pool).await?;
+
+    // The admin counts and dates are taken as a json object, in column order.
+
+    let sql = r#"select coalesce((select json_object_agg(j.key, j.value)
+                from ppr.admin_data a, json_each(row_to_json(a)) j
+                where a.id = $1 and j.key not in ('id', 'ror_name')), '{}')::text;"#;
+    let admin: String = sqlx::query_scalar(sql).bind(id).fetch_one(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    let admin: serde_json::Value = serde_json::from_str(&admin)?;
+
+    Ok(Some(OrgRecord { core, types, names, locations, external_ids, links, relationships, admin }))
+}
+
+
+pub async fn fetch_withdrawn_org(id: &str, pool : &Pool<Postgres>) -> Result<Vec<WithdrawnOrg>, AppError> {
+
+    // There is one row per successor (or a single row with no successor).
+
+    if !withdrawn_table_exists(pool).await? {
+        return Ok(Vec::new());
+    }
+
+    let sql = r#"select ror_name, successor_id, succ_name
+                from rec.withdrawn
+                where ror_id = $1
+                order by successor_id;"#;
+    fetch_rows(sql, &[id], pool).await
+}
+
+
+pub async fn withdrawn_table_exists(pool : &Pool<Postgres>) -> Result<bool, AppError> {
+
+    // rec.withdrawn only exists if withdrawn organisations were removed during processing.
+
+    let sql = "select to_regclass('rec.withdrawn') is not null;";
+    sqlx::query_scalar(sql).fetch_one(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
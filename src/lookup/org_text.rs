@@ -0,0 +1,101 @@
+use super::org_record::OrgRecord;
+
+// Formats an organisation's processed record as plain text, section by section, for reading
+// in a terminal. Empty sections are shown as '(none)' so that missing data is obvious.
+
+pub fn get_org_text(r: &OrgRecord) -> String {
+
+    let c = &r.core;
+    let mut s = format!("{}\n", "=".repeat(80));
+    s += &format!("{} ({})\n", c.ror_name, c.ror_full_id);
+    s += &format!("{}\n", "=".repeat(80));
+    s += &line("status", &c.status);
+    s += &line("established", &opt_i32(c.established));
+    s += &line("location", &format!("{}, {}, {}", opt(&c.location), opt(&c.csubdiv_code), opt(&c.country_code)));
+    s += &line("types", &if r.types.is_empty() {"(none)".to_string()} else {r.types.join(", ")});
+
+    s += &heading("names", r.names.len());
+    for n in &r.names {
+        let ror_name = if n.is_ror_name {", ror name"} else {""};
+        let in_ppr = if n.in_ppr {""} else {"  [removed as duplicate]"};
+        s += &format!("  {} ({}{}){}\n", n.value, n.name_type, ror_name, in_ppr);
+        s += &format!("      lang: {} (ror: {}, derived: {}), script: {}\n", opt(&n.lang_code),
+                      opt(&n.ror_lang), opt(&n.der_lang), opt(&n.script_code));
+        if n.original_value != n.value {
+            s += &format!("      original: {}\n", n.original_value);
+        }
+        s += &format!("      match name: {}\n", opt(&n.match_name));
+        if let Some(ct) = &n.change_type {
+            s += &format!("      changes: {} ({})\n", ct, opt(&n.change_type_id));
+        }
+        if let Some(q) = &n.qflags {
+            s += &format!("      quality flags: {}\n", q);
+        }
+    }
+
+    s += &heading("locations", r.locations.len());
+    for l in &r.locations {
+        let coords = match (l.lat, l.lng) {
+            (Some(lat), Some(lng)) => format!("{lat}, {lng}"),
+            _ => "no coordinates".to_string(),
+        };
+        s += &format!("  {} - {}, {} ({}), {} ({}) - {}\n", opt_i32(l.geonames_id), opt(&l.location),
+                      opt(&l.csubdiv_name), opt(&l.csubdiv_code), opt(&l.country_name), opt(&l.country_code), coords);
+    }
+
+    s += &heading("external ids", r.external_ids.len());
+    for e in &r.external_ids {
+        let preferred = if e.is_preferred {" (preferred)"} else {""};
+        s += &format!("  {:<10} {}{}\n", e.id_type, e.id_value, preferred);
+    }
+
+    s += &heading("links", r.links.len());
+    for k in &r.links {
+        s += &format!("  {:<10} {}\n", k.link_type, k.link);
+    }
+
+    s += &heading("relationships", r.relationships.len());
+    for rel in &r.relationships {
+        s += &format!("  {:<16} {} {}\n", rel.rel_type, rel.related_id, rel.related_name);
+    }
+
+    s += "\nadmin data:\n";
+    match r.admin.as_object() {
+        Some(m) if !m.is_empty() => {
+            for (k, v) in m {
+                let v = match v {
+                    serde_json::Value::String(t) => t.clone(),
+                    _ => v.to_string(),
+                };
+                s += &format!("  {:<16} {}\n", k, v);
+            }
+        },
+        _ => s += "  (none)\n",
+    }
+    s
+}
+
+
+fn line(label: &str, value: &str) -> String {
+    format!("{:<14}{}\n", format!("{label}:"), value)
+}
+
+
+fn heading(label: &str, n: usize) -> String {
+    if n == 0 {
+        format!("\n{label}: (none)\n")
+    }
+    else {
+        format!("\n{label} ({n}):\n")
+    }
+}
+
+
+fn opt(s: &Option<String>) -> &str {
+    s.as_deref().unwrap_or("-")
+}
+
+
+fn opt_i32(n: Option<i32>) -> String {
+    n.map(|n| n.to_string()).unwrap_or("-".to_string())
+}
@@ -8,7 +8,7 @@ async fn main() {
     let args: Vec<_> = env::args_os().collect();
     match run(args).await
     {
-        Ok(_) => println!("Done!"),
+        Ok(_) => eprintln!("Done!"),   // stderr, like the log, so that stdout holds only any printed records
         Err(e) => err::report_error(e),
     };
 }
\ No newline at end of file
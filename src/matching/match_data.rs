@@ -0,0 +1,218 @@
+use std::collections::{HashMap, HashSet};
+use sqlx::{Pool, Postgres, FromRow};
+use crate::err::AppError;
+use super::parse::{get_tokens, COUNTRY_ALIASES};
+
+// The data used for matching, read once from the ppr tables and held in memory: each organisation
+// with its status, countries, cities and type prior, and each of its names (cleaned names from
+// ppr.names and simplified names from ppr.names_to_match) as normalised tokens. An inverted index
+// from tokens to names is used to find candidates, and the inverse document frequency of each
+// token to weight the comparison, so that rare words count for more than 'university' or 'of'.
+
+// Prior weights for organisation types, reflecting how often each appears in affiliations.
+// Organisations with several types take the highest, those with none a default of 0.5.
+
+const TYPE_PRIORS: [(&str, f64); 9] = [("education", 1.0), ("healthcare", 0.9), ("facility", 0.9),
+    ("government", 0.8), ("nonprofit", 0.8), ("company", 0.7), ("funder", 0.6), ("archive", 0.6), ("other", 0.5)];
+
+pub struct MatchOrg {
+    pub id: String,
+    pub ror_name: String,
+    pub status: String,
+    pub countries: Vec<String>,
+    pub cities: Vec<String>,            // normalised
+    pub types: Vec<String>,
+    pub type_prior: f64,
+}
+
+pub struct MatchName {
+    pub org: usize,                     // index into orgs
+    pub value: String,
+    pub tokens: Vec<String>,            // unique, in order
+    pub is_acronym: bool,
+}
+
+pub struct MatchData {
+    pub orgs: Vec<MatchOrg>,
+    pub names: Vec<MatchName>,
+    pub token_index: HashMap<String, Vec<usize>>,       // token to names (not acronyms)
+    pub acronym_index: HashMap<String, Vec<usize>>,     // normalised acronym to names
+    pub idf: HashMap<String, f64>,
+    pub countries: HashMap<String, String>,             // normalised name to country code
+    pub cities: HashSet<String>,
+}
+
+#[derive(FromRow)]
+struct OrgRow {
+    id: String,
+    ror_name: String,
+    status: String,
+    countries: Vec<String>,
+    cities: Vec<String>,
+    types: Vec<String>,
+}
+
+#[derive(FromRow)]
+struct NameRow {
+    id: String,
+    value: String,
+    is_acronym: bool,
+}
+
+#[derive(FromRow)]
+struct CountryRow {
+    code: String,
+    name: String,
+}
+
+
+impl MatchData {
+
+    pub fn new(orgs: Vec<MatchOrg>, org_names: Vec<(usize, String, bool)>, country_names: Vec<(String, String)>) -> Self {
+
+        let mut countries: HashMap<String, String> = country_names.into_iter()
+            .map(|(code, name)| (get_tokens(&name).join(" "), code))
+            .filter(|(name, _)| !name.is_empty())
+            .collect();
+        for (alias, code) in COUNTRY_ALIASES {
+            countries.entry(alias.to_string()).or_insert(code.to_string());
+        }
+
+        // Match names derived from acronyms (e.g. 'aecc') are treated as acronyms.
+
+        let acronyms: HashSet<(usize, String)> = org_names.iter().filter(|(_, _, is_acronym)| *is_acronym)
+            .map(|(org, value, _)| (*org, get_tokens(value).concat()))
+            .collect();
+        let mut names = Vec::new();
+        let mut seen = HashSet::new();
+        for (org, value, is_acronym) in org_names {
+
+            // Names qualified by a country, e.g. 'BP (United Kingdom)', are also matched without it.
+
+            let mut variants = vec![value.as_str()];
+            if let Some(stripped) = strip_country_qualifier(&value, &countries) {
+                variants.push(stripped);
+            }
+            for v in variants {
+                let mut tokens: Vec<String> = Vec::new();
+                for t in get_tokens(v) {
+                    if !tokens.contains(&t) {
+                        tokens.push(t);
+                    }
+                }
+                let is_acronym = is_acronym || acronyms.contains(&(org, tokens.concat()));
+                if !tokens.is_empty() && seen.insert((org, tokens.join(" "), is_acronym)) {
+                    names.push(MatchName { org, value: value.clone(), tokens, is_acronym });
+                }
+            }
+        }
+
+        let mut token_index: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut acronym_index: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, n) in names.iter().enumerate() {
+            if n.is_acronym {
+                acronym_index.entry(n.tokens.concat()).or_default().push(i);
+            }
+            else {
+                for t in &n.tokens {
+                    token_index.entry(t.clone()).or_default().push(i);
+                }
+            }
+        }
+        let num_names = names.len() as f64;
+        let idf = token_index.iter()
+            .map(|(t, p)| (t.clone(), ((num_names + 1.0) / (p.len() as f64 + 1.0)).ln() + 1.0))
+            .collect();
+
+        let cities = orgs.iter().flat_map(|o| o.cities.iter().cloned()).collect();
+
+        MatchData { orgs, names, token_index, acronym_index, idf, countries, cities }
+    }
+
+
+    pub fn get_country_code(&self, country: &str) -> Option<String> {
+
+        // Accepts either a two letter code or a (normalised) country name.
+
+        let c = country.trim();
+        if c.len() == 2 && self.countries.values().any(|code| code.eq_ignore_ascii_case(c)) {
+            return Some(c.to_uppercase());
+        }
+        self.countries.get(&get_tokens(c).join(" ")).cloned()
+    }
+
+
+    pub fn get_idf(&self, token: &str) -> f64 {
+
+        // Tokens not in any name are given the highest weight.
+
+        self.idf.get(token).copied().unwrap_or_else(|| (self.names.len() as f64 + 1.0).ln() + 1.0)
+    }
+}
+
+
+fn strip_country_qualifier<'a>(value: &'a str, countries: &HashMap<String, String>) -> Option<&'a str> {
+
+    let v = value.trim_end();
+    let start = v.rfind('(')?;
+    if !v.ends_with(')') || start == 0 {
+        return None;
+    }
+    let qualifier = get_tokens(&v[start + 1..v.len() - 1]).join(" ");
+    countries.contains_key(&qualifier).then(|| v[..start].trim_end())
+}
+
+
+pub async fn load_match_data(pool : &Pool<Postgres>) -> Result<MatchData, AppError> {
+
+    let sql = r#"select c.id, c.ror_name, s.name as status,
+                array(select distinct l.country_code::text from ppr.locations l
+                      where l.id = c.id and l.country_code is not null) as countries,
+                array(select distinct l.location::text from ppr.locations l
+                      where l.id = c.id and l.location is not null) as cities,
+                array(select t.name::text from ppr.type p
+                      inner join lup.ror_org_types t on p.org_type = t.id
+                      where p.id = c.id and p.org_type <> 1000
+                      order by p.org_type) as types
+                from ppr.core_data c
+                inner join lup.ror_status_types s on c.status = s.id
+                order by c.id;"#;
+    let org_rows: Vec<OrgRow> = sqlx::query_as(sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let mut org_indices = HashMap::new();
+    let mut orgs = Vec::with_capacity(org_rows.len());
+    for (i, r) in org_rows.into_iter().enumerate() {
+        let type_prior = TYPE_PRIORS.iter().filter(|(t, _)| r.types.iter().any(|rt| rt == t))
+            .map(|(_, p)| *p).fold(0.0, f64::max);
+        org_indices.insert(r.id.clone(), i);
+        orgs.push(MatchOrg {
+            id: r.id,
+            ror_name: r.ror_name,
+            status: r.status,
+            countries: r.countries,
+            cities: r.cities.iter().map(|c| get_tokens(c).join(" ")).collect(),
+            types: r.types,
+            type_prior: if type_prior > 0.0 {type_prior} else {0.5},
+        });
+    }
+
+    let sql = r#"select id, value, name_type = 10 as is_acronym
+                from ppr.names
+                union all
+                select id, match_name, false
+                from ppr.names_to_match
+                where match_name is not null;"#;
+    let name_rows: Vec<NameRow> = sqlx::query_as(sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    let org_names = name_rows.into_iter()
+        .filter_map(|r| org_indices.get(&r.id).map(|i| (*i, r.value, r.is_acronym)))
+        .collect();
+
+    let sql = "select code, name from lup.countries;";
+    let country_rows: Vec<CountryRow> = sqlx::query_as(sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    let country_names = country_rows.into_iter().map(|r| (r.code, r.name)).collect();
+
+    Ok(MatchData::new(orgs, org_names, country_names))
+}
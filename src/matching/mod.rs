@@ -0,0 +1,151 @@
+mod match_data;
+mod parse;
+mod scoring;
+
+use chrono::Local;
+use log::info;
+use sqlx::{Pool, Postgres};
+use crate::{err::AppError, setup::InitParams};
+
+pub use match_data::{MatchData, load_match_data};
+pub use scoring::MatchCandidate;
+
+// Matches free text affiliation strings (e.g. from publication metadata) against the processed
+// organisations. Each string is split into department, institution, city and country (parse.rs),
+// candidates are found from the cleaned names, match names and acronyms, and scored using the
+// similarity of the names and the agreement of the locations (scoring.rs). The matching data is
+// read from the ppr tables once, and held in memory, so the ppr data must have been processed.
+
+// Thresholds for the match status reported in batch mode.
+
+const MATCHED_SCORE: f64 = 0.8;
+const MATCHED_MARGIN: f64 = 0.05;
+const UNCERTAIN_SCORE: f64 = 0.6;
+
+pub async fn match_affiliation(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // Print the parsed parts of the affiliation, and the ranked candidates, to stdout.
+
+    let data = load_match_data(pool).await?;
+    let parts = parse::parse_affiliation(&params.match_text, &data.countries, &data.cities);
+    let cands = scoring::score_candidates(&parts, &data, None, params.max_candidates as usize);
+
+    println!("affiliation:   {}", params.match_text);
+    println!("department:    {}", parts.department.as_deref().unwrap_or("-"));
+    println!("institutions:  {}", if parts.institutions.is_empty() {"-".to_string()} else {parts.institutions.join(" | ")});
+    println!("city:          {}", parts.city.as_deref().unwrap_or("-"));
+    println!("country:       {}", parts.country_code.as_deref().unwrap_or("-"));
+    println!("status:        {}\n", get_match_status(&cands));
+
+    println!("rank score  name   id         cc  name (matched name)");
+    for (i, c) in cands.iter().enumerate() {
+        let matched = if c.matched_name != c.ror_name {format!(" ({})", c.matched_name)} else {"".to_string()};
+        println!("{:<4} {:<6.3} {:<6.3} {:<10} {:<3} {}{}", i + 1, c.score, c.name_score, c.id,
+                 c.country_code.as_deref().unwrap_or("-"), c.ror_name, matched);
+    }
+    info!("{} candidate organisations found for '{}'", cands.len(), params.match_text);
+    Ok(())
+}
+
+
+pub async fn match_file(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // Match the affiliation in each row of the csv file, writing the rows out again to a csv file
+    // in the output folder, with the match status, the best candidate and any others appended.
+
+    let mut rdr = csv::Reader::from_path(&params.match_file)
+                .map_err(AppError::CsvError)?;
+    let headers = rdr.headers().map_err(AppError::CsvError)?.clone();
+    let col = match headers.iter().position(|h| h.trim() == params.match_column) {
+        Some(i) => i,
+        None => return Err(AppError::InconsistentProgramParameter(format!("column '{}' not found in {}",
+                            params.match_column, params.match_file.display()))),
+    };
+
+    let data = load_match_data(pool).await?;
+
+    let stem = params.match_file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let datetime_string = Local::now().format("%Y-%m-%d %H%M%S").to_string();
+    let output_file_name = format!("{} matched {}.csv", stem, datetime_string);
+    let output_file_path = params.output_folder.join(&output_file_name);
+    let mut wtr = csv::Writer::from_path(&output_file_path)
+                .map_err(AppError::CsvError)?;
+
+    let mut out_headers = headers.clone();
+    for h in ["match_status", "ror_id", "ror_name", "country_code", "score", "other_candidates"] {
+        out_headers.push_field(h);
+    }
+    wtr.write_record(&out_headers).map_err(AppError::CsvError)?;
+
+    let (mut n, mut n_matched, mut n_uncertain) = (0, 0, 0);
+    for result in rdr.records() {
+        let mut record = result.map_err(AppError::CsvError)?;
+        let text = record.get(col).unwrap_or("").to_string();
+        let cands = find_candidates(&text, None, None, &data, params.max_candidates as usize);
+        let status = get_match_status(&cands);
+        match status {
+            "matched" => n_matched += 1,
+            "uncertain" => n_uncertain += 1,
+            _ => (),
+        }
+
+        match cands.first() {
+            Some(c) if status != "no match" => {
+                let others = cands[1..].iter().map(|o| format!("{} {} ({})", o.id, o.ror_name, o.score))
+                                .collect::<Vec<String>>().join("; ");
+                for f in [status, &c.id, &c.ror_name, c.country_code.as_deref().unwrap_or(""), &c.score.to_string(), &others] {
+                    record.push_field(f);
+                }
+            },
+            _ => {
+                for f in [status, "", "", "", "", ""] {
+                    record.push_field(f);
+                }
+            },
+        }
+        wtr.write_record(&record).map_err(AppError::CsvError)?;
+        n += 1;
+    }
+    wtr.flush()?;
+
+    info!("{} affiliations matched: {} matched, {} uncertain, {} with no match", n, n_matched,
+           n_uncertain, n - n_matched - n_uncertain);
+    info!("Results written to {}", output_file_name);
+    Ok(())
+}
+
+
+pub fn find_candidates(text: &str, country_code: Option<&str>, org_type: Option<&str>,
+                       data: &MatchData, max_candidates: usize) -> Vec<MatchCandidate> {
+
+    // Ranked candidates for an affiliation or name, with any known country taking the place of
+    // one found in the text, and, optionally, only organisations of the given type included.
+
+    let mut parts = parse::parse_affiliation(text, &data.countries, &data.cities);
+    if let Some(cc) = country_code {
+        parts.country_code = Some(cc.to_string());
+    }
+    scoring::score_candidates(&parts, data, org_type, max_candidates)
+}
+
+
+pub fn get_match_status(cands: &[MatchCandidate]) -> &'static str {
+
+    // A match needs a high score and a clear lead over the next candidate.
+
+    match cands {
+        [] => "no match",
+        [c, rest @ ..] => {
+            let margin = rest.first().map(|n| c.score - n.score).unwrap_or(c.score);
+            if c.score >= MATCHED_SCORE && margin >= MATCHED_MARGIN {
+                "matched"
+            }
+            else if c.score >= UNCERTAIN_SCORE {
+                "uncertain"
+            }
+            else {
+                "no match"
+            }
+        },
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::{HashMap, HashSet};
+use crate::text_norm::{fold_lower, get_stop_words};
+
+// Splits an affiliation string, e.g. 'Dept. of Physics, Univ. of Melbourne, Parkville VIC 3010,
+// Australia', into its likely components. The string is split on commas, semi-colons and similar
+// separators. A country is looked for at the end, either as a whole segment or as the last words
+// of one, and a city in the segments before it (ignoring postcodes and other tokens with digits).
+// Segments starting with words like 'department' or 'division' are treated as departments, and
+// the remaining segments as possible institutions, with those containing words like 'university'
+// or 'hospital' put first. Departments are kept as a last resort, as some organisations, e.g.
+// laboratories, are named in that way.
+
+const SEPARATORS: [char; 5] = [',', ';', '|', '\n', '\t'];
+
+const DEPT_WORDS: [&str; 14] = ["department", "division", "section", "unit", "laboratory", "laboratories", "group",
+    "program", "programme", "service", "faculty", "chair", "team", "school"];
+
+const INST_WORDS: [&str; 30] = ["university", "universite", "universitat", "universidad", "universidade", "universita",
+    "universiteit", "college", "hospital", "hopital", "ospedale", "klinikum", "clinic", "institute", "institut", "instituto",
+    "istituto", "academy", "akademie", "center", "foundation", "council", "agency", "ministry", "museum", "library",
+    "society", "corporation", "inc", "gmbh"];
+
+// Common abbreviations, expanded (on both sides of any comparison) before matching.
+
+const EXPANSIONS: [(&str, &str); 14] = [("univ", "university"), ("inst", "institute"), ("hosp", "hospital"),
+    ("natl", "national"), ("nat", "national"), ("intl", "international"), ("ctr", "center"), ("cntr", "center"),
+    ("centre", "center"), ("coll", "college"), ("dept", "department"), ("dep", "department"), ("lab", "laboratory"),
+    ("labs", "laboratories")];
+
+// Country names used in affiliations, but not in lup.countries (already normalised).
+
+pub const COUNTRY_ALIASES: [(&str, &str); 21] = [("usa", "US"), ("us", "US"), ("united states america", "US"),
+    ("uk", "GB"), ("england", "GB"), ("scotland", "GB"), ("wales", "GB"), ("northern ireland", "GB"),
+    ("great britain", "GB"), ("holland", "NL"), ("korea", "KR"), ("republic korea", "KR"), ("pr china", "CN"),
+    ("p r china", "CN"), ("peoples republic china", "CN"), ("russian federation", "RU"), ("viet nam", "VN"),
+    ("czech republic", "CZ"), ("turkiye", "TR"), ("brasil", "BR"), ("deutschland", "DE")];
+
+#[derive(Debug, Default)]
+pub struct AffiliationParts {
+    pub department: Option<String>,
+    pub institutions: Vec<String>,      // most likely first
+    pub city: Option<String>,           // normalised
+    pub country_code: Option<String>,
+}
+
+
+pub fn parse_affiliation(text: &str, countries: &HashMap<String, String>, cities: &HashSet<String>) -> AffiliationParts {
+
+    let mut segments: Vec<(String, Vec<String>)> = text.split(SEPARATORS)
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .map(|s| { let t = get_tokens(&s); (s, t) })
+        .filter(|(_, t)| !t.is_empty())
+        .collect();
+    let mut parts = AffiliationParts::default();
+
+    // Country - the last segment, or its last (up to three) words.
+
+    if let Some((_, toks)) = segments.last() {
+        for n in (1..=toks.len().min(3)).rev() {
+            if let Some(code) = countries.get(&toks[toks.len() - n..].join(" ")) {
+                parts.country_code = Some(code.clone());
+                let remaining = toks[..toks.len() - n].to_vec();
+                if remaining.is_empty() {
+                    segments.pop();
+                }
+                else if let Some(last) = segments.last_mut() {
+                    last.1 = remaining;
+                }
+                break;
+            }
+        }
+    }
+
+    // City - working back from the end, the first segment without institution or department
+    // words that is, or starts with, a known city (tokens with digits are ignored).
+
+    let mut city_segment = None;
+    for (i, (_, toks)) in segments.iter().enumerate().rev() {
+        if toks.iter().any(|t| INST_WORDS.contains(&t.as_str()) || DEPT_WORDS.contains(&t.as_str())) {
+            continue;
+        }
+        let words: Vec<&str> = toks.iter().filter(|t| !t.chars().any(|c| c.is_ascii_digit())).map(|t| t.as_str()).collect();
+        for n in (1..=words.len().min(3)).rev() {
+            let candidate = words[..n].join(" ");
+            if cities.contains(&candidate) {
+                parts.city = Some(candidate);
+                city_segment = Some(i);
+                break;
+            }
+        }
+        if city_segment.is_some() {
+            break;
+        }
+    }
+    if let Some(i) = city_segment {
+        segments.remove(i);
+    }
+
+    // Departments and institutions, from whatever remains.
+
+    let mut inst_segs: Vec<(bool, String)> = Vec::new();
+    let mut dept_segs: Vec<String> = Vec::new();
+    for (_, toks) in segments {
+        if toks.iter().all(|t| t.chars().any(|c| c.is_ascii_digit())) {
+            continue;    // e.g. a postcode or street number
+        }
+        let joined = toks.join(" ");
+        if DEPT_WORDS.contains(&toks[0].as_str()) {
+            if parts.department.is_none() {
+                parts.department = Some(joined.clone());
+            }
+            dept_segs.push(joined);
+        }
+        else {
+            let has_inst_word = toks.iter().any(|t| INST_WORDS.contains(&t.as_str()));
+            inst_segs.push((has_inst_word, joined));
+        }
+    }
+    inst_segs.sort_by_key(|(has_inst_word, _)| !has_inst_word);    // stable, so order otherwise kept
+    parts.institutions = inst_segs.into_iter().map(|(_, s)| s).collect();
+    parts.institutions.extend(dept_segs);
+    parts
+}
+
+
+pub fn get_tokens(s: &str) -> Vec<String> {
+
+    // Lower case, folded words, split on anything other than a letter or digit, with
+    // abbreviations expanded and stop words removed.
+
+    let stop_words = get_stop_words(None);
+    fold_lower(s).split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| match EXPANSIONS.iter().find(|(a, _)| *a == w) {
+            Some((_, e)) => e.to_string(),
+            None => w.to_string(),
+        })
+        .filter(|w| !stop_words.contains(&w.as_str()))
+        .collect()
+}
+
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_lookups() -> (HashMap<String, String>, HashSet<String>) {
+        let mut countries = HashMap::new();
+        countries.insert("australia".to_string(), "AU".to_string());
+        countries.insert("united kingdom".to_string(), "GB".to_string());
+        countries.insert("uk".to_string(), "GB".to_string());
+        let cities = ["melbourne", "parkville", "london"].iter().map(|c| c.to_string()).collect();
+        (countries, cities)
+    }
+
+    #[test]
+    fn check_tokens_are_folded_expanded_and_filtered() {
+        assert_eq!(get_tokens("Univ. of São Paulo"), vec!["university", "sao", "paulo"]);
+        assert_eq!(get_tokens("Centre for Ecology & Hydrology"), vec!["center", "ecology", "hydrology"]);
+    }
+
+    #[test]
+    fn check_affiliation_with_department_city_and_country() {
+        let (countries, cities) = get_lookups();
+        let p = parse_affiliation("Dept. of Physics, University of Melbourne, Parkville VIC 3010, Australia", &countries, &cities);
+        assert_eq!(p.department, Some("department physics".to_string()));
+        assert_eq!(p.institutions, vec!["university melbourne", "department physics"]);
+        assert_eq!(p.city, Some("parkville".to_string()));
+        assert_eq!(p.country_code, Some("AU".to_string()));
+    }
+
+    #[test]
+    fn check_affiliation_with_country_at_end_of_segment() {
+        let (countries, cities) = get_lookups();
+        let p = parse_affiliation("Wellcome Trust; Imperial College London, London SW7 2AZ UK", &countries, &cities);
+        assert_eq!(p.country_code, Some("GB".to_string()));
+        assert_eq!(p.city, Some("london".to_string()));
+        assert_eq!(p.institutions, vec!["imperial college london", "wellcome trust"]);
+        assert_eq!(p.department, None);
+    }
+}
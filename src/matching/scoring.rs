@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use serde::Serialize;
+use super::match_data::MatchData;
+use super::parse::AffiliationParts;
+
+// Generates and scores candidate organisations for a parsed affiliation. Candidates are the
+// organisations with a name sharing at least one (reasonably rare) token with one of the
+// institution segments, or with an acronym equal to a whole segment. Each name is compared with
+// each segment using an idf weighted Dice coefficient, or 0.9 of the proportion of the name
+// found in the segment if that is higher (so that 'Harvard University' still scores well
+// against 'Harvard University Medical School Boston'). The best name similarity of each
+// organisation is then combined with agreement on country and city, and the org type prior:
+//      score = 0.70 * name + 0.15 * country + 0.05 * city + 0.10 * type prior
+// where the country and city are 1 if they agree, 0 if they do not and 0.5 if unknown.
+// Inactive and withdrawn organisations have their scores reduced slightly.
+
+const MAX_CANDIDATE_NAMES: usize = 5000;
+const MIN_NAME_SIMILARITY: f64 = 0.3;
+const ACRONYM_SIMILARITY: f64 = 0.85;
+
+#[derive(Clone, Serialize)]
+pub struct MatchCandidate {
+    pub id: String,
+    pub ror_name: String,
+    pub country_code: Option<String>,
+    pub types: Vec<String>,
+    pub matched_name: String,
+    pub name_score: f64,
+    pub score: f64,
+}
+
+
+pub fn score_candidates(parts: &AffiliationParts, data: &MatchData, org_type: Option<&str>,
+                        max_candidates: usize) -> Vec<MatchCandidate> {
+
+    // Best similarity, and the name giving it, for each organisation (of the given type, if any).
+
+    let mut best: HashMap<usize, (f64, usize)> = HashMap::new();
+    for segment in &parts.institutions {
+        let tokens: Vec<String> = segment.split(' ').map(|t| t.to_string()).collect();
+
+        if let Some(ns) = data.acronym_index.get(&tokens.concat()) {
+            for &n in ns {
+                update_best(&mut best, data.names[n].org, ACRONYM_SIMILARITY, n);
+            }
+        }
+
+        for n in get_candidate_names(&tokens, data) {
+            let sim = get_similarity(&tokens, &data.names[n].tokens, data);
+            if sim >= MIN_NAME_SIMILARITY {
+                update_best(&mut best, data.names[n].org, sim, n);
+            }
+        }
+    }
+
+    if let Some(t) = org_type {
+        best.retain(|o, _| data.orgs[*o].types.iter().any(|ot| ot == t));
+    }
+
+    let mut candidates: Vec<MatchCandidate> = best.into_iter().map(|(o, (sim, n))| {
+        let org = &data.orgs[o];
+        let country = match &parts.country_code {
+            Some(cc) => if org.countries.contains(cc) {1.0} else {0.0},
+            None => 0.5,
+        };
+        let city = match &parts.city {
+            Some(c) => if org.cities.contains(c) {1.0} else {0.0},
+            None => 0.5,
+        };
+        let status_factor = match org.status.as_str() {
+            "active" => 1.0,
+            "inactive" => 0.95,
+            _ => 0.9,
+        };
+        let score = (0.70 * sim + 0.15 * country + 0.05 * city + 0.10 * org.type_prior) * status_factor;
+        MatchCandidate {
+            id: org.id.clone(),
+            ror_name: org.ror_name.clone(),
+            country_code: org.countries.first().cloned(),
+            types: org.types.clone(),
+            matched_name: data.names[n].value.clone(),
+            name_score: round3(sim),
+            score: round3(score),
+        }
+    }).collect();
+
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score)
+                        .then(b.name_score.total_cmp(&a.name_score))
+                        .then(a.ror_name.cmp(&b.ror_name)));
+    candidates.truncate(max_candidates);
+    candidates
+}
+
+
+fn get_candidate_names(tokens: &[String], data: &MatchData) -> Vec<usize> {
+
+    // Names sharing a token with the segment, taking the rarest tokens first and stopping once
+    // the cap is reached, so that very common tokens do not pull in most of the names.
+
+    let mut postings: Vec<&Vec<usize>> = tokens.iter().filter_map(|t| data.token_index.get(t)).collect();
+    postings.sort_by_key(|p| p.len());
+    let mut names: Vec<usize> = Vec::new();
+    for p in postings {
+        if !names.is_empty() && names.len() + p.len() > MAX_CANDIDATE_NAMES {
+            break;
+        }
+        names.extend(p);
+    }
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+
+fn get_similarity(query: &[String], name: &[String], data: &MatchData) -> f64 {
+
+    let weight = |ts: &[String]| -> f64 {
+        let mut seen: Vec<&String> = Vec::new();
+        ts.iter().filter(|t| if seen.contains(t) {false} else {seen.push(t); true}).map(|t| data.get_idf(t)).sum()
+    };
+    let (wq, wn) = (weight(query), weight(name));
+    if wq == 0.0 || wn == 0.0 {
+        return 0.0;
+    }
+    let shared: Vec<String> = name.iter().filter(|t| query.contains(t)).cloned().collect();
+    let ws = weight(&shared);
+    if ws == wq && ws == wn {
+        return 1.0;
+    }
+    let dice = 2.0 * ws / (wq + wn);
+    let containment = 0.9 * ws / wn;
+    dice.max(containment)
+}
+
+
+fn update_best(best: &mut HashMap<usize, (f64, usize)>, org: usize, sim: f64, name: usize) {
+    let e = best.entry(org).or_insert((sim, name));
+    if sim > e.0 {
+        *e = (sim, name);
+    }
+}
+
+
+fn round3(x: f64) -> f64 {
+    (x * 1000.0).round() / 1000.0
+}
+
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::match_data::MatchOrg;
+    use super::super::parse::parse_affiliation;
+
+    fn get_org(id: &str, name: &str, country: &str, city: &str, type_prior: f64) -> MatchOrg {
+        MatchOrg { id: id.to_string(), ror_name: name.to_string(), status: "active".to_string(),
+                   countries: vec![country.to_string()], cities: vec![city.to_string()], types: Vec::new(), type_prior }
+    }
+
+    fn get_data() -> MatchData {
+        let orgs = vec![get_org("04ttjf776", "RMIT University", "AU", "melbourne", 1.0),
+                        get_org("01ej9dk98", "University of Melbourne", "AU", "melbourne", 1.0),
+                        get_org("05xxxxx01", "University of Melbourne Foundation", "AU", "melbourne", 0.6),
+                        get_org("05xxxxx02", "Melbourne University Hospital", "US", "melbourne", 0.9)];
+        let names = vec![(0, "RMIT University".to_string(), false), (0, "RMIT".to_string(), true),
+                         (1, "University of Melbourne".to_string(), false),
+                         (2, "University of Melbourne Foundation".to_string(), false),
+                         (3, "Melbourne University Hospital".to_string(), false)];
+        let countries = vec![("AU".to_string(), "Australia".to_string()), ("US".to_string(), "United States".to_string())];
+        MatchData::new(orgs, names, countries)
+    }
+
+    #[test]
+    fn check_exact_name_with_agreeing_location_ranks_first() {
+        let data = get_data();
+        let parts = parse_affiliation("School of Physics, University of Melbourne, Melbourne, Australia", &data.countries, &data.cities);
+        let cands = score_candidates(&parts, &data, None, 5);
+        assert_eq!(cands[0].id, "01ej9dk98");
+        assert_eq!(cands[0].name_score, 1.0);
+        assert_eq!(cands[0].score, 1.0);
+        let rmit = cands.iter().find(|c| c.id == "04ttjf776").unwrap();
+        assert!(rmit.score < 0.6);    // only 'university' in common
+        let hosp = cands.iter().find(|c| c.id == "05xxxxx02").unwrap();
+        assert!(hosp.score < cands[1].score);    // wrong country
+    }
+
+    #[test]
+    fn check_acronym_only_matches_whole_segment() {
+        let data = get_data();
+        let parts = parse_affiliation("RMIT, Melbourne, Australia", &data.countries, &data.cities);
+        let cands = score_candidates(&parts, &data, None, 5);
+        assert_eq!(cands.len(), 1);
+        assert_eq!(cands[0].id, "04ttjf776");
+        assert_eq!(cands[0].name_score, 0.85);
+
+        let parts = parse_affiliation("Rmit Road Clinic", &data.countries, &data.cities);
+        assert!(score_candidates(&parts, &data, None, 5).iter().all(|c| c.matched_name != "RMIT"));
+    }
+}
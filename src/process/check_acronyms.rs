@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use sqlx::{postgres::PgQueryResult, Pool, Postgres};
+use log::info;
+use crate::AppError;
+use crate::text_norm::{fold_lower, get_stop_words};
+
+// Results of the acronym checks. The ids are used in rec.acronym_checks and the smm summaries.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AcronymMatch {
+    Derivable = 1,
+    Partial = 2,
+    Unexplained = 3,
+    NoComparableNames = 4,
+}
+
+impl AcronymMatch {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AcronymMatch::Derivable => "derivable",
+            AcronymMatch::Partial => "partially derivable",
+            AcronymMatch::Unexplained => "unexplained",
+            AcronymMatch::NoComparableNames => "no comparable names",
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct CheckName {
+    ident: i32,
+    id: String,
+    name: String,
+    lang: Option<String>,
+    script: String,
+}
+
+struct AcronymCheckVecs {
+    idents: Vec<i32>,
+    ids: Vec<String>,
+    acronyms: Vec<String>,
+    langs: Vec<Option<String>>,
+    result_ids: Vec<i32>,
+    results: Vec<String>,
+    matched_names: Vec<Option<String>>,
+}
+
+impl AcronymCheckVecs {
+    fn new(vsize: usize) -> Self {
+        AcronymCheckVecs {
+            idents: Vec::with_capacity(vsize),
+            ids: Vec::with_capacity(vsize),
+            acronyms: Vec::with_capacity(vsize),
+            langs: Vec::with_capacity(vsize),
+            result_ids: Vec::with_capacity(vsize),
+            results: Vec::with_capacity(vsize),
+            matched_names: Vec::with_capacity(vsize),
+        }
+    }
+
+    fn add(&mut self, a: &CheckName, result: AcronymMatch, matched_name: Option<String>) {
+        self.idents.push(a.ident);
+        self.ids.push(a.id.clone());
+        self.acronyms.push(a.name.clone());
+        self.langs.push(a.lang.clone());
+        self.result_ids.push(result as i32);
+        self.results.push(result.name().to_string());
+        self.matched_names.push(matched_name);
+    }
+
+    async fn store(&self, pool: &Pool<Postgres>) -> Result<PgQueryResult, AppError> {
+
+        let sql = r#"INSERT INTO rec.acronym_checks (ident, id, acronym, lang, result_id, result, matched_name)
+            SELECT * FROM UNNEST($1::int[], $2::text[], $3::text[], $4::text[], $5::int[], $6::text[], $7::text[])"#;
+        sqlx::query(sql)
+        .bind(&self.idents).bind(&self.ids).bind(&self.acronyms).bind(&self.langs)
+        .bind(&self.result_ids).bind(&self.results).bind(&self.matched_names)
+        .execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+    }
+}
+
+
+pub async fn check_acronyms (pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    // Each acronym is compared with the labels and aliases of the same organisation, in the
+    // same script and (if the acronym has one) the same or no language. Only organisations
+    // still in the ppr tables are considered, i.e. normally withdrawn organisations are excluded.
+
+    info!("Checking acronyms against labels and aliases");
+
+    let sql = r#"select n.ident, n.id, n.display_name as name, n.lang, n.der_script as script
+        from rec.names n
+        inner join ppr.core_data c
+        on n.id = c.id
+        where n.name_type = 10
+        order by n.id, n.ident"#;
+    let acronyms: Vec<CheckName> = sqlx::query_as(sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let sql = r#"select n.ident, n.id, n.display_name as name, n.lang, n.der_script as script
+        from rec.names n
+        inner join ppr.core_data c
+        on n.id = c.id
+        where n.name_type in (5, 7)"#;
+    let other_names: Vec<CheckName> = sqlx::query_as(sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let mut org_names: HashMap<&str, Vec<&CheckName>> = HashMap::new();
+    for n in other_names.iter() {
+        org_names.entry(n.id.as_str()).or_default().push(n);
+    }
+
+    let mut checks = AcronymCheckVecs::new(acronyms.len());
+    for a in acronyms.iter() {
+        let candidates: Vec<&CheckName> = match org_names.get(a.id.as_str()) {
+            Some(names) => names.iter()
+                    .filter(|n| a.script.is_empty() || n.script.is_empty() || n.script == a.script)
+                    .filter(|n| a.lang.is_none() || n.lang.is_none() || n.lang == a.lang)
+                    .copied().collect(),
+            None => Vec::new(),
+        };
+
+        if candidates.is_empty() {
+            checks.add(a, AcronymMatch::NoComparableNames, None);
+            continue;
+        }
+
+        let mut best = AcronymMatch::Unexplained;
+        let mut best_name = None;
+        for n in candidates.iter() {
+            let lang = n.lang.as_deref().or(a.lang.as_deref());
+            let res = check_acronym_against_name(&a.name, &n.name, lang);
+            if (res as i32) < (best as i32) {
+                best = res;
+                best_name = Some(n.name.clone());
+                if best == AcronymMatch::Derivable {
+                    break;
+                }
+            }
+        }
+        checks.add(a, best, best_name);
+    }
+    checks.store(pool).await?;
+
+    for res in [AcronymMatch::Derivable, AcronymMatch::Partial,
+                AcronymMatch::Unexplained, AcronymMatch::NoComparableNames] {
+        let n = checks.result_ids.iter().filter(|r| **r == res as i32).count();
+        info!("{n} acronyms {}", res.name());
+    }
+    info!("");
+    Ok(())
+}
+
+
+pub fn check_acronym_against_name(acronym: &str, name: &str, lang: Option<&str>) -> AcronymMatch {
+
+    // An acronym is derivable if it matches the initial letters of the words in the name,
+    // with or without the stop words for the name's language. It is partially derivable if
+    // its first letter is the first initial and the remaining letters occur, in order, in the name.
+
+    let acro: String = fold_lower(acronym).chars().filter(|c| c.is_alphanumeric()).collect();
+    if acro.chars().count() < 2 {
+        return AcronymMatch::Unexplained;
+    }
+
+    let words = get_words(name);
+    if words.is_empty() {
+        return AcronymMatch::Unexplained;
+    }
+
+    let stop_words = get_stop_words(lang);
+    let all_initials: String = words.iter().filter_map(|w| w.chars().next()).collect();
+    let content_initials: String = words.iter()
+                .filter(|w| !stop_words.contains(&w.as_str()))
+                .filter_map(|w| w.chars().next()).collect();
+
+    if acro == all_initials || acro == content_initials {
+        return AcronymMatch::Derivable;
+    }
+
+    let first = acro.chars().next();
+    let letters: String = words.concat();
+    if (first == all_initials.chars().next() || first == content_initials.chars().next())
+        && is_subsequence(&acro, &letters) {
+        return AcronymMatch::Partial;
+    }
+
+    AcronymMatch::Unexplained
+}
+
+
+fn get_words(name: &str) -> Vec<String> {
+
+    // Lower case, folded, words - split on anything that is not a letter or a number,
+    // so that hyphenated words and elided articles (l', d') are separated.
+
+    fold_lower(name).split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_string())
+        .collect()
+}
+
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut hay = haystack.chars();
+    needle.chars().all(|c| hay.any(|h| h == c))
+}
+
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_acronyms_derived_from_initials() {
+        assert_eq!(check_acronym_against_name("MIT", "Massachusetts Institute of Technology", Some("en")), AcronymMatch::Derivable);
+        assert_eq!(check_acronym_against_name("UoM", "University of Manchester", Some("en")), AcronymMatch::Derivable);
+        assert_eq!(check_acronym_against_name("CNRS", "Centre national de la recherche scientifique", Some("fr")), AcronymMatch::Derivable);
+        assert_eq!(check_acronym_against_name("MPG", "Max-Planck-Gesellschaft", None), AcronymMatch::Derivable);
+    }
+
+    #[test]
+    fn check_acronyms_with_accents_and_elisions() {
+        assert_eq!(check_acronym_against_name("EPFL", "École Polytechnique Fédérale de Lausanne", Some("fr")), AcronymMatch::Derivable);
+        assert_eq!(check_acronym_against_name("ENSA", "École nationale supérieure d'architecture", Some("fr")), AcronymMatch::Derivable);
+    }
+
+    #[test]
+    fn check_acronyms_partially_derived() {
+        assert_eq!(check_acronym_against_name("DLR", "Deutsches Zentrum für Luft- und Raumfahrt", Some("de")), AcronymMatch::Partial);
+        assert_eq!(check_acronym_against_name("UniMelb", "University of Melbourne", Some("en")), AcronymMatch::Partial);
+    }
+
+    #[test]
+    fn check_acronyms_not_derivable() {
+        assert_eq!(check_acronym_against_name("CERN", "European Organization for Nuclear Research", Some("en")), AcronymMatch::Unexplained);
+        assert_eq!(check_acronym_against_name("X", "Xavier University", Some("en")), AcronymMatch::Unexplained);
+    }
+}
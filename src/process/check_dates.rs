@@ -0,0 +1,42 @@
+use sqlx::{Pool, Postgres};
+use log::info;
+use crate::AppError;
+
+pub async fn flag_date_anomalies (pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    // Established years that are unlikely to be correct are listed in rec.date_anomalies.
+    // An organisation can appear more than once if it has more than one anomaly.
+    // Run after any withdrawn organisations have been removed from the ppr tables.
+
+    info!("Checking established years");
+
+    let wh = r#"c.established > extract(year from to_date((select data_date from ppr.version_details), 'YYYY-MM-DD'))"#;
+    add_anomalies(wh, 1, "established in the future", pool).await?;
+
+    let wh = r#"c.established < 1000"#;
+    add_anomalies(wh, 2, "established before 1000", pool).await?;
+
+    let wh = r#"c.established > extract(year from a.created)"#;
+    add_anomalies(wh, 3, "established after record created", pool).await?;
+
+    info!("");
+    Ok(())
+}
+
+
+async fn add_anomalies(wh: &str, anomaly_id: i32, anomaly: &str, pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    let sql = format!(r#"insert into rec.date_anomalies (id, ror_name, established,
+            created, last_modified, anomaly_id, anomaly)
+        select c.id, c.ror_name, c.established, a.created, a.last_modified, {anomaly_id}, '{anomaly}'
+        from ppr.core_data c
+        inner join ppr.admin_data a
+        on c.id = a.id
+        where c.established is not null
+        and {wh}"#);
+
+    let n = sqlx::query(&sql).execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?.rows_affected();
+    info!("{n} organisations {anomaly}");
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use sqlx::{Pool, Postgres};
+use log::info;
+use crate::AppError;
+
+// The external id schemes, in the order used in the crosswalk, with their lup.ror_id_types codes.
+
+pub const ID_SCHEMES: [(&str, i32); 4] = [("grid", 13), ("isni", 11), ("fundref", 14), ("wikidata", 12)];
+
+
+pub async fn create_crosswalk_tables (pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    // ppr.crosswalk has one row per organisation, with the preferred value, all values (the
+    // preferred first, separated by '; ') and the number of values of each external id scheme.
+    // Each ppr.{scheme}_to_ror table maps the values of one scheme back to ROR ids, with the
+    // number of organisations sharing the value, so that one-to-many collisions are flagged.
+    // Run after any withdrawn organisations have been removed from the ppr tables.
+
+    info!("Creating external id crosswalk tables");
+
+    let mut cols = "".to_string();
+    let mut sels = "".to_string();
+    for (scheme, code) in ID_SCHEMES {
+        cols += &format!(r#"
+      , {scheme}_preferred    varchar     null
+      , {scheme}_all          varchar     null
+      , n_{scheme}            int         not null default 0"#);
+        sels += &format!(r#",
+        min(e.id_value) filter (where e.id_type = {code} and e.is_preferred),
+        string_agg(e.id_value, '; ' order by e.is_preferred desc, e.id_value) filter (where e.id_type = {code}),
+        count(e.id_value) filter (where e.id_type = {code})"#);
+    }
+
+    let sql = format!(r#"SET client_min_messages TO WARNING;
+    drop table if exists ppr.crosswalk;
+    create table ppr.crosswalk
+    (
+        id                varchar     not null primary key
+      , ror_full_id       varchar     not null
+      , ror_name          varchar     not null{cols}
+    );
+    insert into ppr.crosswalk
+    select c.id, c.ror_full_id, c.ror_name{sels}
+    from ppr.core_data c
+    left join ppr.external_ids e
+    on c.id = e.id
+    group by c.id, c.ror_full_id, c.ror_name;
+    SET client_min_messages TO NOTICE;"#);
+    sqlx::raw_sql(&sql).execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    for (scheme, code) in ID_SCHEMES {
+        let sql = format!(r#"SET client_min_messages TO WARNING;
+        drop table if exists ppr.{scheme}_to_ror;
+        create table ppr.{scheme}_to_ror
+        (
+            id_value          varchar     not null
+          , id                varchar     not null
+          , ror_name          varchar     not null
+          , is_preferred      bool        not null
+          , n_ror_ids         int         not null
+          , is_collision      bool        not null
+        );
+        insert into ppr.{scheme}_to_ror
+        select e.id_value, e.id, e.ror_name, e.is_preferred,
+        count(*) over (partition by e.id_value),
+        count(*) over (partition by e.id_value) > 1
+        from ppr.external_ids e
+        where e.id_type = {code};
+        create index {scheme}_to_ror_value_idx on ppr.{scheme}_to_ror(id_value);
+        create index {scheme}_to_ror_id_idx on ppr.{scheme}_to_ror(id);
+        SET client_min_messages TO NOTICE;"#);
+        sqlx::raw_sql(&sql).execute(pool).await
+            .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+        let sql = format!("select count(distinct id_value) from ppr.{scheme}_to_ror where is_collision");
+        let n: i64 = sqlx::query_scalar(&sql).fetch_one(pool).await
+            .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+        info!("{n} {scheme} ids linked to more than one organisation");
+    }
+
+    info!("");
+    Ok(())
+}
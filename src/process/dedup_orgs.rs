@@ -0,0 +1,232 @@
+use sqlx::{postgres::PgQueryResult, Pool, Postgres};
+use log::info;
+use crate::AppError;
+
+pub async fn find_candidate_dups (pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    // ROR sometimes holds the same institution under two different ids. This routine
+    // compares organisations across ids, rather than names within a single organisation
+    // (as is done in dedup_names), and lists pairs that may be duplicates in
+    // rec.candidate_duplicates, so that they can be reviewed and, if necessary, reported back to ROR.
+
+    // Candidate pairs are first collected, in a scratch pad table, from any of four types
+    // of evidence: a shared normalised (match) name within the same country, a shared
+    // external id, a shared domain, or a shared website. The evidence for each pair is then
+    // aggregated, geographic proximity added, and a score calculated. Proximity is not itself
+    // evidence - many unrelated organisations share a campus or a city centre - so it never
+    // generates a pair, it only raises the score of pairs found in one of the four ways above.
+
+    let sql = r#"SET client_min_messages TO WARNING;
+        drop table if exists rec.dup_org_pairs;
+        create table rec.dup_org_pairs
+        (
+          id1               varchar     not null
+        , id2               varchar     not null
+        , evidence_type     int         not null
+        , evidence          varchar     null
+        );
+        create index dup_org_pairs_idx on rec.dup_org_pairs(id1, id2);"#;
+    execute_sql(sql, pool).await?;
+
+    let res = add_shared_name_pairs(pool).await?;
+    info!("{} organisation pairs found with a shared name key in the same country", res);
+
+    let res = add_shared_ext_id_pairs(pool).await?;
+    info!("{} organisation pairs found with a shared external id", res);
+
+    let res = add_shared_domain_pairs(pool).await?;
+    info!("{} organisation pairs found with a shared domain", res);
+
+    let res = add_shared_website_pairs(pool).await?;
+    info!("{} organisation pairs found with a shared website", res);
+
+    let res = aggregate_pairs(pool).await?;
+    info!("{} candidate duplicate organisation pairs identified", res);
+
+    add_distances(pool).await?;
+    add_existing_relationships(pool).await?;
+    calculate_scores(pool).await?;
+
+    let sql = r#"drop table if exists rec.dup_org_pairs;"#;
+    execute_sql(sql, pool).await?;
+
+    info!("");
+    Ok(())
+}
+
+
+async fn add_shared_name_pairs(pool: &Pool<Postgres>) -> Result<u64, AppError> {
+
+    // Acronyms are excluded - they are too often shared by unrelated organisations.
+    // Generic names ('Ministry of Health') are common to many countries, so pairs
+    // are only considered if the organisations share a country.
+
+    let sql = r#"insert into rec.dup_org_pairs (id1, id2, evidence_type, evidence)
+        select n1.id, n2.id, 1, n1.match_name
+        from
+            (select distinct n.id, n.match_name, c.country_code
+             from rec.names n inner join ppr.core_data c on n.id = c.id
+             where n.name_type <> 10 and n.match_name is not null and n.match_name <> '') n1
+        inner join
+            (select distinct n.id, n.match_name, c.country_code
+             from rec.names n inner join ppr.core_data c on n.id = c.id
+             where n.name_type <> 10 and n.match_name is not null and n.match_name <> '') n2
+        on n1.match_name = n2.match_name
+        and n1.id < n2.id
+        and n1.country_code = n2.country_code;"#;
+
+    let res = execute_sql(sql, pool).await?.rows_affected();
+    Ok(res)
+}
+
+
+async fn add_shared_ext_id_pairs(pool: &Pool<Postgres>) -> Result<u64, AppError> {
+
+    let sql = r#"insert into rec.dup_org_pairs (id1, id2, evidence_type, evidence)
+        select distinct e1.id, e2.id, 2, t.name||': '||e1.id_value
+        from ppr.external_ids e1
+        inner join ppr.external_ids e2
+        on e1.id_type = e2.id_type
+        and e1.id_value = e2.id_value
+        and e1.id < e2.id
+        inner join lup.ror_id_types t
+        on e1.id_type = t.id;"#;
+
+    let res = execute_sql(sql, pool).await?.rows_affected();
+    Ok(res)
+}
+
+
+async fn add_shared_domain_pairs(pool: &Pool<Postgres>) -> Result<u64, AppError> {
+
+    let sql = r#"insert into rec.dup_org_pairs (id1, id2, evidence_type, evidence)
+        select distinct d1.id, d2.id, 3, lower(d1.domain)
+        from ppr.domains d1
+        inner join ppr.domains d2
+        on lower(d1.domain) = lower(d2.domain)
+        and d1.id < d2.id;"#;
+
+    let res = execute_sql(sql, pool).await?.rows_affected();
+    Ok(res)
+}
+
+
+async fn add_shared_website_pairs(pool: &Pool<Postgres>) -> Result<u64, AppError> {
+
+    // Websites are compared after removal of the scheme, any initial 'www.' and any trailing slash.
+
+    let sql = r#"insert into rec.dup_org_pairs (id1, id2, evidence_type, evidence)
+        select distinct w1.id, w2.id, 4, w1.site
+        from
+            (select id, regexp_replace(regexp_replace(lower(link), '^https?://(www\.)?', ''), '/+$', '') as site
+             from ppr.links where link_type = 22) w1
+        inner join
+            (select id, regexp_replace(regexp_replace(lower(link), '^https?://(www\.)?', ''), '/+$', '') as site
+             from ppr.links where link_type = 22) w2
+        on w1.site = w2.site
+        and w1.id < w2.id;"#;
+
+    let res = execute_sql(sql, pool).await?.rows_affected();
+    Ok(res)
+}
+
+
+async fn aggregate_pairs(pool: &Pool<Postgres>) -> Result<u64, AppError> {
+
+    let sql = r#"insert into rec.candidate_duplicates (id1, ror_name1, country_code1,
+            id2, ror_name2, country_code2, shared_names, shared_ext_ids, shared_domains, shared_websites)
+        select p.id1, c1.ror_name, c1.country_code,
+        p.id2, c2.ror_name, c2.country_code,
+        string_agg(distinct case when p.evidence_type = 1 then p.evidence end, '; '),
+        string_agg(distinct case when p.evidence_type = 2 then p.evidence end, '; '),
+        string_agg(distinct case when p.evidence_type = 3 then p.evidence end, '; '),
+        string_agg(distinct case when p.evidence_type = 4 then p.evidence end, '; ')
+        from rec.dup_org_pairs p
+        inner join ppr.core_data c1
+        on p.id1 = c1.id
+        inner join ppr.core_data c2
+        on p.id2 = c2.id
+        group by p.id1, c1.ror_name, c1.country_code, p.id2, c2.ror_name, c2.country_code;"#;
+
+    let res = execute_sql(sql, pool).await?.rows_affected();
+    Ok(res)
+}
+
+
+async fn add_distances(pool: &Pool<Postgres>) -> Result<PgQueryResult, AppError> {
+
+    // The minimum great circle distance (haversine formula, in km)
+    // between any of the two organisations' locations.
+
+    let sql = r#"update rec.candidate_duplicates cd
+        set distance_km = d.min_dist
+        from
+            (select cd2.id1, cd2.id2,
+             min(2 * 6371 * asin(least(1.0, sqrt(
+                power(sin(radians(l2.lat - l1.lat) / 2), 2) +
+                cos(radians(l1.lat)) * cos(radians(l2.lat)) *
+                power(sin(radians(l2.lng - l1.lng) / 2), 2))))) as min_dist
+             from rec.candidate_duplicates cd2
+             inner join ppr.locations l1
+             on cd2.id1 = l1.id
+             inner join ppr.locations l2
+             on cd2.id2 = l2.id
+             where l1.lat is not null and l2.lat is not null
+             group by cd2.id1, cd2.id2) d
+        where cd.id1 = d.id1
+        and cd.id2 = d.id2;"#;
+
+    execute_sql(sql, pool).await
+}
+
+
+async fn add_existing_relationships(pool: &Pool<Postgres>) -> Result<PgQueryResult, AppError> {
+
+    // Pairs already linked by a relationship (e.g. parent / child, predecessor / successor)
+    // share domains and websites for good reason. They are retained, but the relationship
+    // is listed so that they can be easily filtered out. Relationships are not always
+    // recorded in both directions, so each is keyed on the ordered id pair (id1 < id2, as
+    // in the candidate table), combining those held against either organisation.
+
+    let sql = r#"update rec.candidate_duplicates cd
+        set existing_rel = r.rels
+        from
+            (select least(r.id, r.related_id) as id1, greatest(r.id, r.related_id) as id2,
+             string_agg(distinct t.name, ', ') as rels
+             from ppr.relationships r
+             inner join lup.ror_rel_types t
+             on r.rel_type = t.id
+             group by least(r.id, r.related_id), greatest(r.id, r.related_id)) r
+        where cd.id1 = r.id1
+        and cd.id2 = r.id2;"#;
+
+    execute_sql(sql, pool).await
+}
+
+
+async fn calculate_scores(pool: &Pool<Postgres>) -> Result<PgQueryResult, AppError> {
+
+    // Shared name (same country) 40, shared external id 30, shared domain 15,
+    // shared website 15, locations within 1 km 10, or within 10 km 5. Maximum of 100.
+    // Proximity therefore only adjusts the score of a pair found from other evidence.
+
+    let sql = r#"update rec.candidate_duplicates
+        set score = least(100,
+            case when shared_names is not null then 40 else 0 end +
+            case when shared_ext_ids is not null then 30 else 0 end +
+            case when shared_domains is not null then 15 else 0 end +
+            case when shared_websites is not null then 15 else 0 end +
+            case when distance_km < 1 then 10
+                 when distance_km < 10 then 5
+                 else 0 end);"#;
+
+    execute_sql(sql, pool).await
+}
+
+
+async fn execute_sql(sql: &str, pool: &Pool<Postgres>) -> Result<PgQueryResult, AppError> {
+
+    sqlx::raw_sql(sql).execute(pool)
+        .await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
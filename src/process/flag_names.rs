@@ -0,0 +1,93 @@
+use sqlx::{Pool, Postgres};
+use log::info;
+use crate::AppError;
+
+const MAX_NAME_LENGTH: i32 = 150;
+
+pub async fn flag_name_issues (pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    // A rule based check of the (repaired) display names. Nothing is changed - names that
+    // look odd are flagged, in the qflag_ids and qflags columns of rec.names, so that
+    // new oddities can be found (and if necessary repaired) without reading through the logs.
+    // Flag names are used in the smm summaries and so must not contain commas.
+
+    info!("Flagging Possible Name Issues");
+
+    let wh = r#"name_type <> 10 and display_name ~ '\s'
+        and display_name = upper(display_name)
+        and display_name <> lower(display_name)"#;
+    flag_names(wh, 1, "all_caps", "multi-word name in capitals", pool).await?;
+
+    let wh = r#"display_name ~ '^[-.,;:!?/\\&*_+=#@~|]'
+        or display_name ~ '[-,;:/\\&*_+=#@~|]$'"#;
+    flag_names(wh, 2, "edge_punctuation", "leading or trailing punctuation", pool).await?;
+
+    let wh = r#"length(display_name) - length(replace(display_name, '(', '')) <> length(display_name) - length(replace(display_name, ')', ''))
+        or length(display_name) - length(replace(display_name, '[', '')) <> length(display_name) - length(replace(display_name, ']', ''))
+        or length(display_name) - length(replace(display_name, '{', '')) <> length(display_name) - length(replace(display_name, '}', ''))"#;
+    flag_names(wh, 3, "unbalanced_brackets", "unbalanced brackets", pool).await?;
+
+    let wh = r#"(length(display_name) - length(replace(display_name, '"', ''))) % 2 = 1
+        or length(display_name) - length(replace(display_name, '“', '')) <> length(display_name) - length(replace(display_name, '”', ''))
+        or length(display_name) - length(replace(display_name, '«', '')) <> length(display_name) - length(replace(display_name, '»', ''))"#;
+    flag_names(wh, 4, "unbalanced_quotes", "unbalanced double quotes", pool).await?;
+
+    let wh = r#"display_name ~* '(https?://|www\.|[[:alnum:]._%+-]+@[[:alnum:].-]+\.[[:alpha:]]{2,})'"#;
+    flag_names(wh, 5, "url_or_email", "embedded url or email address", pool).await?;
+
+    let wh = format!("length(display_name) > {MAX_NAME_LENGTH}");
+    flag_names(&wh, 6, "very_long", "very long name", pool).await?;
+
+    // The acronym is only considered to be repeated if it is in brackets, or separated by
+    // a dash, colon or comma at the start or end of the name - names like 'RMIT University'
+    // legitimately include the acronym.
+
+    let wh = r#"name_type <> 10
+        and exists (select 1 from rec.names a
+                    where a.id = n.id
+                    and a.name_type = 10
+                    and length(a.display_name) > 1
+                    and n.display_name ~ replace(
+                        '(\(ACR\)|^ACR\s*[-–:,]\s|\s[-–:,]\s*ACR$)', 'ACR',
+                        regexp_replace(a.display_name, '([.^$*+?()\[\]{}|\\-])', '\\\1', 'g')))"#;
+    flag_names(wh, 7, "repeats_acronym", "name repeats the organisation's acronym", pool).await?;
+
+    let wh = r#"display_name ~* '\mliterally\M'"#;
+    flag_names(wh, 8, "literally", "name includes 'literally'", pool).await?;
+
+    let wh = r#"display_name ~* '(\[\[|\]\]|\{\{|\}\}|\[\s*(edit|править|citation needed)|\|\s*(edit|править)|<ref|''''|&nbsp;)'"#;
+    flag_names(wh, 9, "wiki_markup", "wiki markup or editing text", pool).await?;
+
+    let sql = r#"select count(*) from rec.names where qflag_ids is not null"#;
+    let n: i64 = sqlx::query_scalar(sql).fetch_one(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    info!("{n} names flagged with one or more possible issues");
+    info!("");
+    Ok(())
+}
+
+
+async fn flag_names(wh: &str, flag_id: i32, flag: &str, description: &str, pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    let sql = format!(r#"update rec.names n
+        set qflag_ids = case when qflag_ids is null then '{flag_id}'
+            else qflag_ids||', {flag_id}'
+        end,
+        qflags = case when qflags is null then '{flag}'
+            else qflags||', {flag}'
+        end
+        where {wh}"#);
+
+    let n = sqlx::query(&sql).execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?.rows_affected();
+
+    if n > 0 {
+        if n == 1 {
+            info!("{description} ({})", "1 record");
+        }
+        else {
+            info!("{description} ({})", format!("{n} records").as_str());
+        };
+    }
+    Ok(())
+}
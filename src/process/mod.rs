@@ -3,6 +3,11 @@ mod check_langs;
 pub mod specific_langs;
 mod add_scripts;
 mod dedup_names;
+mod dedup_orgs;
+mod check_acronyms;
+mod check_dates;
+mod flag_names;
+pub mod crosswalk;
 
 use crate::setup::InitParams;
 use crate::sql::create_ppr_tables;
@@ -66,6 +71,7 @@ pub async fn process_data(params: &InitParams, pool : &Pool<Postgres>) -> Result
     }
     
     dedup_names::remove_dups(pool).await?;  // done here to prevent PK errors in core_data
+    flag_names::flag_name_issues(pool).await?;
     
     execute_sql(get_import_names_sql(), pool).await?;
     execute_sql(get_import_match_names_sql(), pool).await?;
@@ -183,6 +189,22 @@ pub async fn process_data(params: &InitParams, pool : &Pool<Postgres>) -> Result
         info!("");
     }
 
+    // Check acronyms against the organisation's other names.
+
+    check_acronyms::check_acronyms(pool).await?;
+
+    // Check the established years for obvious errors.
+
+    check_dates::flag_date_anomalies(pool).await?;
+
+    // Look for organisations that may be duplicated under different ROR ids.
+
+    dedup_orgs::find_candidate_dups(pool).await?;
+
+    // Build the crosswalk and reverse lookup tables for the external ids.
+
+    crosswalk::create_crosswalk_tables(pool).await?;
+
     Ok(())
 }
 
@@ -0,0 +1,59 @@
+mod reconcile;
+mod rest;
+
+use std::sync::Arc;
+use axum::Router;
+use log::info;
+use sqlx::{Pool, Postgres};
+use crate::{err::AppError, setup::InitParams};
+use crate::matching::load_match_data;
+
+// Optional HTTP server modes, over the processed ppr data: an OpenRefine reconciliation service
+// (--reconcile) and a read-only REST API (--serve), which share a single server if both are
+// requested. The server runs until the program is stopped (e.g. with Ctrl-C), and so should be
+// the last, or only, action requested.
+
+pub async fn run_server(params: &InitParams, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let base_url = format!("http://{}:{}", params.server_host, params.server_port);
+    let mut app = Router::new();
+
+    if params.flags.rest_server {
+        app = app.merge(rest::get_router(pool.clone()));
+        info!("REST API available at {}/organizations and {}/versions", base_url, base_url);
+    }
+
+    if params.flags.reconcile_server {
+
+        // The matching data is loaded once, before the server starts listening.
+
+        let data = load_match_data(pool).await?;
+        info!("Matching data loaded: {} organisations, {} names", data.orgs.len(), data.names.len());
+
+        let sql = "select 'ROR ' || version || ' (' || data_date || '), local reconciliation service' from ppr.version_details;";
+        let service_name: Option<String> = sqlx::query_scalar(sql).fetch_optional(pool).await
+            .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+        let state = Arc::new(reconcile::ReconcileState {
+            pool: pool.clone(),
+            data,
+            base_url: base_url.clone(),
+            service_name: service_name.unwrap_or("ROR local reconciliation service".to_string()),
+            default_limit: params.max_candidates as usize,
+        });
+        app = app.merge(reconcile::get_router(state));
+        info!("Reconciliation service available at {}/reconcile", base_url);
+    }
+
+    serve(app, &params.server_host, params.server_port).await
+}
+
+
+async fn serve(app: Router, host: &str, port: u16) -> Result<(), AppError> {
+
+    let addr = format!("{host}:{port}");
+    let listener = tokio::net::TcpListener::bind(&addr).await
+        .map_err(|e| AppError::ServerError(e, addr.clone()))?;
+    axum::serve(listener, app).await
+        .map_err(|e| AppError::ServerError(e, addr))
+}
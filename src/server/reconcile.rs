@@ -0,0 +1,378 @@
+use std::sync::Arc;
+use axum::{Router, Form, Json, routing::get, extract::{Query, State}};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response, Html};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use sqlx::{Pool, Postgres};
+use crate::lookup::org_record::{fetch_org_record, OrgRecord};
+use crate::matching::{find_candidates, get_match_status, MatchData};
+use crate::export::escape_markup;
+use crate::sql::create_lup_tables::ORG_TYPES;
+use super::rest::ApiError;
+
+// An implementation of the OpenRefine Reconciliation Service API (version 0.2), at /reconcile,
+// over the processed ppr data. A GET without parameters returns the service manifest. Queries
+// ('queries' parameter, GET or POST) are matched using the affiliation matching engine, with
+// optional 'country' and 'type' properties, and the query type (one of the ROR organisation
+// types) restricting the candidates. Entities can be previewed (/reconcile/preview) and extended
+// ('extend' parameter) with their external ids and locations, the properties available being
+// listed at /reconcile/properties. JSONP callbacks are supported for older clients.
+
+const EXTEND_PROPERTIES: [(&str, &str); 14] = [("ror_name", "ROR name"), ("status", "Status"), ("types", "Types"),
+    ("grid", "GRID"), ("isni", "ISNI"), ("fundref", "FundRef"), ("wikidata", "Wikidata"), ("city", "City"),
+    ("subdivision", "Subdivision"), ("country_code", "Country code"), ("country_name", "Country"),
+    ("geonames_id", "GeoNames id"), ("lat", "Latitude"), ("lng", "Longitude")];
+
+pub struct ReconcileState {
+    pub pool: Pool<Postgres>,
+    pub data: MatchData,
+    pub base_url: String,
+    pub service_name: String,
+    pub default_limit: usize,
+}
+
+#[derive(Deserialize)]
+pub struct ReconcileParams {
+    queries: Option<String>,
+    extend: Option<String>,
+    callback: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ReconQuery {
+    query: String,
+    #[serde(rename = "type")]
+    query_type: Option<Value>,
+    limit: Option<usize>,
+    properties: Option<Vec<QueryProperty>>,
+}
+
+#[derive(Deserialize)]
+struct QueryProperty {
+    pid: String,
+    v: Value,
+}
+
+#[derive(Deserialize)]
+struct ExtendRequest {
+    ids: Vec<String>,
+    properties: Vec<ExtendProperty>,
+}
+
+#[derive(Deserialize)]
+struct ExtendProperty {
+    id: String,
+}
+
+#[derive(Deserialize)]
+pub struct PreviewParams {
+    id: String,
+}
+
+#[derive(Deserialize)]
+pub struct PropertiesParams {
+    #[serde(rename = "type")]
+    prop_type: Option<String>,
+    limit: Option<usize>,
+    callback: Option<String>,
+}
+
+
+pub fn get_router(state: Arc<ReconcileState>) -> Router {
+    Router::new()
+        .route("/reconcile", get(reconcile_get).post(reconcile_post))
+        .route("/reconcile/preview", get(preview))
+        .route("/reconcile/properties", get(propose_properties))
+        .with_state(state)
+}
+
+
+async fn reconcile_get(State(state): State<Arc<ReconcileState>>, Query(params): Query<ReconcileParams>) -> Response {
+    reconcile(state, params).await
+}
+
+
+async fn reconcile_post(State(state): State<Arc<ReconcileState>>, Form(params): Form<ReconcileParams>) -> Response {
+    reconcile(state, params).await
+}
+
+
+async fn reconcile(state: Arc<ReconcileState>, params: ReconcileParams) -> Response {
+
+    let result = if let Some(q) = &params.queries {
+        match serde_json::from_str::<Map<String, Value>>(q) {
+            Ok(queries) => Ok(get_query_results(&state, queries)),
+            Err(e) => Err(ApiError(StatusCode::BAD_REQUEST, format!("invalid queries: {e}"))),
+        }
+    }
+    else if let Some(x) = &params.extend {
+        match serde_json::from_str::<ExtendRequest>(x) {
+            Ok(req) => get_extension(&state, req).await,
+            Err(e) => Err(ApiError(StatusCode::BAD_REQUEST, format!("invalid extend request: {e}"))),
+        }
+    }
+    else {
+        Ok(get_manifest(&state))
+    };
+
+    match result {
+        Ok(v) => json_response(v, params.callback.as_deref()),
+        Err(e) => e.into_response(),
+    }
+}
+
+
+fn get_manifest(state: &ReconcileState) -> Value {
+
+    let mut types = vec![json!({"id": "organization", "name": "Organization"})];
+    types.extend(ORG_TYPES.iter().map(|t| json!({"id": t, "name": get_type_name(t)})));
+    json!({
+        "versions": ["0.2"],
+        "name": state.service_name,
+        "identifierSpace": "https://ror.org/",
+        "schemaSpace": "https://ror.org/",
+        "defaultTypes": types,
+        "view": {"url": "https://ror.org/{{id}}"},
+        "preview": {"url": format!("{}/reconcile/preview?id={{{{id}}}}", state.base_url), "width": 400, "height": 140},
+        "extend": {
+            "propose_properties": {"service_url": state.base_url, "service_path": "/reconcile/properties"},
+            "property_settings": [],
+        },
+    })
+}
+
+
+fn get_query_results(state: &ReconcileState, queries: Map<String, Value>) -> Value {
+
+    // Each query is keyed (q0, q1...), and can be a simple string as well as an object.
+    // Invalid or empty queries return no results, rather than failing the whole batch.
+
+    let mut results = Map::new();
+    for (key, q) in queries {
+        let q: Option<ReconQuery> = match q {
+            Value::String(s) => Some(ReconQuery { query: s, query_type: None, limit: None, properties: None }),
+            _ => serde_json::from_value(q).ok(),
+        };
+        let candidates = match q {
+            Some(q) => get_candidates(state, &q),
+            None => Vec::new(),
+        };
+        results.insert(key, json!({"result": candidates}));
+    }
+    Value::Object(results)
+}
+
+
+fn get_candidates(state: &ReconcileState, q: &ReconQuery) -> Vec<Value> {
+
+    let mut org_type = q.query_type.as_ref().and_then(get_value_string);
+    let mut country_code = None;
+    for p in q.properties.iter().flatten() {
+        let v = match get_value_string(&p.v) {
+            Some(v) if !v.trim().is_empty() => v,
+            _ => continue,
+        };
+        match p.pid.as_str() {
+            "country" | "country_code" => country_code = state.data.get_country_code(&v),
+            "type" => org_type = Some(v.trim().to_lowercase()),
+            _ => (),
+        }
+    }
+    let org_type = org_type.filter(|t| ORG_TYPES.contains(&t.as_str()));
+
+    let limit = q.limit.unwrap_or(state.default_limit).max(1);
+    let cands = find_candidates(&q.query, country_code.as_deref(), org_type.as_deref(), &state.data, limit);
+    let is_match = get_match_status(&cands) == "matched";
+
+    cands.iter().enumerate().map(|(i, c)| {
+        let types: Vec<Value> = if c.types.is_empty() {
+            vec![json!({"id": "organization", "name": "Organization"})]
+        } else {
+            c.types.iter().map(|t| json!({"id": t, "name": get_type_name(t)})).collect()
+        };
+        json!({
+            "id": c.id,
+            "name": c.ror_name,
+            "type": types,
+            "score": (c.score * 1000.0).round() / 10.0,
+            "match": is_match && i == 0,
+            "description": format!("{} - matched on '{}'", c.country_code.as_deref().unwrap_or("no country"), c.matched_name),
+        })
+    }).collect()
+}
+
+
+async fn get_extension(state: &ReconcileState, req: ExtendRequest) -> Result<Value, ApiError> {
+
+    let props: Vec<&str> = req.properties.iter().map(|p| p.id.as_str())
+                              .filter(|p| EXTEND_PROPERTIES.iter().any(|(id, _)| id == p)).collect();
+    let meta: Vec<Value> = props.iter()
+        .map(|p| json!({"id": p, "name": EXTEND_PROPERTIES.iter().find(|(id, _)| id == p).map(|(_, n)| *n)}))
+        .collect();
+
+    let mut rows = Map::new();
+    for id in &req.ids {
+        let record = fetch_org_record(id, &state.pool).await?;
+        let mut row = Map::new();
+        for p in &props {
+            let values = match &record {
+                Some(r) => get_property_values(r, p),
+                None => Vec::new(),
+            };
+            row.insert(p.to_string(), Value::Array(values));
+        }
+        rows.insert(id.clone(), Value::Object(row));
+    }
+    Ok(json!({"meta": meta, "rows": rows}))
+}
+
+
+fn get_property_values(r: &OrgRecord, prop: &str) -> Vec<Value> {
+
+    // External ids have any preferred value first. Location properties have one value per
+    // location, in the same order, so that multiple locations line up across columns.
+
+    let strs = |vs: Vec<&String>| -> Vec<Value> { vs.into_iter().map(|v| json!({"str": v})).collect() };
+    match prop {
+        "ror_name" => vec![json!({"str": r.core.ror_name})],
+        "status" => vec![json!({"str": r.core.status})],
+        "types" => strs(r.types.iter().collect()),
+        "grid" | "isni" | "fundref" | "wikidata" => strs(r.external_ids.iter()
+                                                    .filter(|e| e.id_type == prop).map(|e| &e.id_value).collect()),
+        "city" => r.locations.iter().filter_map(|l| l.location.as_ref()).map(|v| json!({"str": v})).collect(),
+        "subdivision" => r.locations.iter().filter_map(|l| l.csubdiv_name.as_ref()).map(|v| json!({"str": v})).collect(),
+        "country_code" => r.locations.iter().filter_map(|l| l.country_code.as_ref()).map(|v| json!({"str": v})).collect(),
+        "country_name" => r.locations.iter().filter_map(|l| l.country_name.as_ref()).map(|v| json!({"str": v})).collect(),
+        "geonames_id" => r.locations.iter().filter_map(|l| l.geonames_id).map(|v| json!({"int": v})).collect(),
+        "lat" => r.locations.iter().filter_map(|l| l.lat).map(|v| json!({"float": v})).collect(),
+        "lng" => r.locations.iter().filter_map(|l| l.lng).map(|v| json!({"float": v})).collect(),
+        _ => Vec::new(),
+    }
+}
+
+
+async fn propose_properties(Query(params): Query<PropertiesParams>) -> Response {
+
+    let limit = params.limit.unwrap_or(EXTEND_PROPERTIES.len());
+    let props: Vec<Value> = EXTEND_PROPERTIES.iter().take(limit).map(|(id, name)| json!({"id": id, "name": name})).collect();
+    json_response(json!({"type": params.prop_type.unwrap_or("organization".to_string()), "properties": props}),
+                  params.callback.as_deref())
+}
+
+
+async fn preview(State(state): State<Arc<ReconcileState>>, Query(params): Query<PreviewParams>) -> Response {
+
+    let r = match fetch_org_record(params.id.trim(), &state.pool).await {
+        Ok(Some(r)) => r,
+        Ok(None) => return ApiError(StatusCode::NOT_FOUND, format!("{} not found", params.id)).into_response(),
+        Err(e) => return ApiError::from(e).into_response(),
+    };
+
+    let c = &r.core;
+    let location = [c.location.as_deref(), c.country_code.as_deref()].into_iter().flatten()
+                      .collect::<Vec<&str>>().join(", ");
+    let ids = r.external_ids.iter().filter(|e| e.is_preferred || e.id_type == "grid")
+                 .map(|e| format!("{}: {}", e.id_type, e.id_value)).collect::<Vec<String>>().join(", ");
+    let names = r.names.iter().filter(|n| n.in_ppr && !n.is_ror_name).map(|n| n.value.as_str())
+                   .collect::<Vec<&str>>().join("; ");
+    Html(format!(r#"<html><head><meta charset="utf-8"/></head>
+<body style="margin: 0; padding: 4px; font-family: Arial, sans-serif; font-size: 10pt">
+<a href="https://ror.org/{}" target="_blank" style="font-weight: bold">{}</a> ({})<br/>
+{} - {}{}<br/>
+{}<br/>
+<span style="color: #666">{}</span>
+</body></html>"#,
+        escape_markup(&c.id), escape_markup(&c.ror_name), escape_markup(&c.status),
+        escape_markup(&r.types.join(", ")), escape_markup(&location),
+        c.established.map(|y| format!(", established {y}")).unwrap_or_default(),
+        escape_markup(&ids), escape_markup(&names))).into_response()
+}
+
+
+fn get_value_string(v: &Value) -> Option<String> {
+
+    // Property values may be strings, numbers, objects with an id or name, or arrays of these.
+
+    match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Object(m) => m.get("id").or(m.get("name")).and_then(get_value_string),
+        Value::Array(a) => a.first().and_then(get_value_string),
+        _ => None,
+    }
+}
+
+
+fn get_type_name(t: &str) -> String {
+    let mut cs = t.chars();
+    cs.next().map(|f| f.to_uppercase().chain(cs).collect()).unwrap_or_default()
+}
+
+
+fn json_response(v: Value, callback: Option<&str>) -> Response {
+
+    // With a (valid) JSONP callback the json is wrapped in a call to it.
+
+    match callback {
+        Some(cb) if !cb.is_empty() && cb.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '.' || ch == '$') => {
+            ([(header::CONTENT_TYPE, "application/javascript")], format!("{cb}({v})")).into_response()
+        },
+        _ => Json(v).into_response(),
+    }
+}
+
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get_body(r: Response) -> String {
+        let bytes = axum::body::to_bytes(r.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    fn get_content_type(r: &Response) -> String {
+        r.headers().get(header::CONTENT_TYPE).and_then(|h| h.to_str().ok()).unwrap_or_default().to_string()
+    }
+
+    #[test]
+    fn check_values_from_strings_and_numbers() {
+        assert_eq!(get_value_string(&json!("AU")), Some("AU".to_string()));
+        assert_eq!(get_value_string(&json!(42)), Some("42".to_string()));
+        assert_eq!(get_value_string(&json!(true)), None);
+        assert_eq!(get_value_string(&json!(null)), None);
+    }
+
+    #[test]
+    fn check_values_from_objects_and_arrays() {
+        assert_eq!(get_value_string(&json!({"id": "education", "name": "Education"})), Some("education".to_string()));
+        assert_eq!(get_value_string(&json!({"name": "Australia"})), Some("Australia".to_string()));
+        assert_eq!(get_value_string(&json!({"label": "x"})), None);
+        assert_eq!(get_value_string(&json!(["NZ", "AU"])), Some("NZ".to_string()));
+        assert_eq!(get_value_string(&json!([{"id": "funder"}])), Some("funder".to_string()));
+        assert_eq!(get_value_string(&json!([])), None);
+    }
+
+    #[tokio::test]
+    async fn check_jsonp_callbacks() {
+        let r = json_response(json!({"a": 1}), Some("jQuery_123.cb$"));
+        assert_eq!(get_content_type(&r), "application/javascript");
+        assert_eq!(get_body(r).await, r#"jQuery_123.cb$({"a":1})"#);
+
+        let r = json_response(json!({"a": 1}), None);
+        assert_eq!(get_content_type(&r), "application/json");
+        assert_eq!(get_body(r).await, r#"{"a":1}"#);
+    }
+
+    #[tokio::test]
+    async fn check_bad_jsonp_callbacks_are_ignored() {
+        for cb in ["", "alert(1);cb", "cb<script>", "a b"] {
+            let r = json_response(json!({"a": 1}), Some(cb));
+            assert_eq!(get_content_type(&r), "application/json");
+            assert_eq!(get_body(r).await, r#"{"a":1}"#);
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use axum::{Router, Json, routing::get, extract::{Path, Query, State}};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use log::error;
+use sqlx::{Pool, Postgres};
+use crate::err::AppError;
+use crate::lookup::org_record::{fetch_org_record, fetch_withdrawn_org};
+use crate::lookup::name_search::{search_names, SearchFilters};
+use crate::lookup::org_list::list_orgs;
+use crate::sql::create_lup_tables::ORG_TYPES;
+
+// A read-only REST API over the processed (ppr) and summary (smm) data, returning json:
+//      /organizations/{id}              the processed record of an organisation
+//      /organizations                   organisations, optionally filtered by query (a name
+//                                       search), country, type and status
+//      /versions                        the version summaries
+//      /versions/{vcode}/summary        the smm data for a version (inc_wd=true for the
+//                                       version including withdrawn organisations)
+// Lists are paged, using 'page' (from 1) and 'per_page' (default 20, maximum 100), and returned
+// with the page details and the total number of items. Errors are returned as {"error": "..."},
+// with the details of any database or other internal error logged rather than returned.
+
+const DEFAULT_PER_PAGE: i64 = 20;
+const MAX_PER_PAGE: i64 = 100;
+
+#[derive(Deserialize)]
+pub struct OrgListParams {
+    query: Option<String>,
+    country: Option<String>,
+    #[serde(rename = "type")]
+    org_type: Option<String>,
+    status: Option<String>,
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct PageParams {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct SummaryParams {
+    inc_wd: Option<bool>,
+}
+
+// Errors are returned as json. Internal errors (e.g. database failures) are logged, but
+// only reported to the client as a generic 500, so that no SQL or other detail is exposed.
+// Also used by the reconciliation service.
+
+pub struct ApiError(pub StatusCode, pub String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({"error": self.1}))).into_response()
+    }
+}
+
+impl From<AppError> for ApiError {
+    fn from(e: AppError) -> Self {
+        match &e {
+            AppError::SqlxError(se, sql) => error!("Server database error: {se} (sql: {sql})"),
+            _ => error!("Server error: {e}"),
+        }
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, "internal server error".to_string())
+    }
+}
+
+type ApiResult = Result<Json<Value>, ApiError>;
+
+
+pub fn get_router(pool: Pool<Postgres>) -> Router {
+    Router::new()
+        .route("/organizations", get(get_organizations))
+        .route("/organizations/{id}", get(get_organization))
+        .route("/versions", get(get_versions))
+        .route("/versions/{vcode}/summary", get(get_version_summary))
+        .with_state(pool)
+}
+
+
+async fn get_organization(State(pool): State<Pool<Postgres>>, Path(id): Path<String>) -> ApiResult {
+
+    // Withdrawn organisations (only in rec.withdrawn if removed during processing) are
+    // reported as not found, with their successors.
+
+    let id = id.trim().to_string();
+    if let Some(r) = fetch_org_record(&id, &pool).await? {
+        return Ok(Json(serde_json::to_value(r).map_err(AppError::from)?));
+    }
+    let wds = fetch_withdrawn_org(&id, &pool).await?;
+    if wds.is_empty() {
+        return Err(ApiError(StatusCode::NOT_FOUND, format!("{id} not found")));
+    }
+    let successors: Vec<String> = wds.into_iter()
+        .filter_map(|w| w.successor_id.map(|s| format!("{} ({})", s, w.succ_name.unwrap_or_default())))
+        .collect();
+    let msg = if successors.is_empty() {
+        format!("{id} has been withdrawn, with no successor")
+    } else {
+        format!("{id} has been withdrawn - successor {}", successors.join(", "))
+    };
+    Err(ApiError(StatusCode::NOT_FOUND, msg))
+}
+
+
+async fn get_organizations(State(pool): State<Pool<Postgres>>, Query(params): Query<OrgListParams>) -> ApiResult {
+
+    let (page, per_page, offset) = get_page(params.page, params.per_page)?;
+    let country = params.country.unwrap_or_default().trim().to_uppercase();
+    if !country.is_empty() && (country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic())) {
+        return Err(ApiError(StatusCode::BAD_REQUEST, format!("'{country}' is not a two letter country code")));
+    }
+    let org_type = params.org_type.unwrap_or_default().trim().to_lowercase();
+    if !org_type.is_empty() && !ORG_TYPES.contains(&org_type.as_str()) {
+        return Err(ApiError(StatusCode::BAD_REQUEST, format!("'{org_type}' is not an organisation type")));
+    }
+    let status = params.status.unwrap_or_default().trim().to_lowercase();
+    if !status.is_empty() && !["active", "inactive", "withdrawn"].contains(&status.as_str()) {
+        return Err(ApiError(StatusCode::BAD_REQUEST, format!("'{status}' is not an organisation status")));
+    }
+    let filters = SearchFilters { country, org_type, status };
+    let query = params.query.unwrap_or_default().trim().to_string();
+
+    // The total is taken from the rows returned, and so needs the first page if the one
+    // requested is beyond the end of the list.
+
+    let (items, total) = if query.is_empty() {
+        let rows = list_orgs(&filters, per_page, offset, &pool).await?;
+        let total = match rows.first() {
+            Some(r) => r.total,
+            None if offset > 0 => list_orgs(&filters, 1, 0, &pool).await?.first().map(|r| r.total).unwrap_or(0),
+            None => 0,
+        };
+        (serde_json::to_value(rows).map_err(AppError::from)?, total)
+    } else {
+        let rows = search_names(&query, &filters, per_page, offset, &pool).await?;
+        let total = match rows.first() {
+            Some(r) => r.total,
+            None if offset > 0 => search_names(&query, &filters, 1, 0, &pool).await?.first().map(|r| r.total).unwrap_or(0),
+            None => 0,
+        };
+        (serde_json::to_value(rows).map_err(AppError::from)?, total)
+    };
+    Ok(Json(get_page_json(page, per_page, total, items)))
+}
+
+
+async fn get_versions(State(pool): State<Pool<Postgres>>, Query(params): Query<PageParams>) -> ApiResult {
+
+    let (page, per_page, offset) = get_page(params.page, params.per_page)?;
+
+    let sql = "select count(*) from smm.version_summaries;";
+    let total: i64 = sqlx::query_scalar(sql).fetch_one(&pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let sql = r#"select coalesce(json_agg(t), '[]')::text from
+                (select * from smm.version_summaries
+                 order by vdate desc, inc_wd
+                 limit $1 offset $2) t;"#;
+    let items: String = sqlx::query_scalar(sql).bind(per_page).bind(offset).fetch_one(&pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    let items: Value = serde_json::from_str(&items).map_err(AppError::from)?;
+    Ok(Json(get_page_json(page, per_page, total, items)))
+}
+
+
+async fn get_version_summary(State(pool): State<Pool<Postgres>>, Path(vcode): Path<String>,
+                             Query(params): Query<SummaryParams>) -> ApiResult {
+
+    // Each smm table with a version code contributes its rows for the version, as an array
+    // keyed by the table name. Tables also with inc_wd are filtered on that as well.
+
+    let inc_wd = params.inc_wd.unwrap_or(false);
+    let sql = "select count(*) from smm.version_summaries where vcode = $1 and inc_wd = $2;";
+    let n: i64 = sqlx::query_scalar(sql).bind(&vcode).bind(inc_wd).fetch_one(&pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    if n == 0 {
+        return Err(ApiError(StatusCode::NOT_FOUND, format!("no summary data for version {vcode} (inc_wd {inc_wd})")));
+    }
+
+    let sql = r#"select c.table_name::text,
+                bool_or(c.column_name = 'inc_wd') as has_inc_wd
+                from information_schema.columns c
+                where c.table_schema = 'smm'
+                group by c.table_name
+                having bool_or(c.column_name = 'vcode')
+                order by c.table_name;"#;
+    let tables: Vec<(String, bool)> = sqlx::query_as(sql).fetch_all(&pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let mut summary = Map::new();
+    for (table, has_inc_wd) in tables {
+        let wd_clause = if has_inc_wd {" and inc_wd = $2"} else {""};
+        let sql = format!(r#"select coalesce(json_agg(t), '[]')::text from
+                    (select * from smm."{table}" where vcode = $1{wd_clause}) t;"#);
+        let query = sqlx::query_scalar(&sql).bind(&vcode);
+        let query = if has_inc_wd {query.bind(inc_wd)} else {query};
+        let rows: String = query.fetch_one(&pool).await
+            .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+        summary.insert(table, serde_json::from_str(&rows).map_err(AppError::from)?);
+    }
+    Ok(Json(json!({"vcode": vcode, "inc_wd": inc_wd, "tables": summary})))
+}
+
+
+fn get_page(page: Option<i64>, per_page: Option<i64>) -> Result<(i64, i64, i64), ApiError> {
+
+    // Returns the page, the page size and the offset of the page's first item, checking that
+    // the offset can be calculated (a very large page number would otherwise overflow).
+
+    let page = page.unwrap_or(1);
+    let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE);
+    if page < 1 || !(1..=MAX_PER_PAGE).contains(&per_page) {
+        return Err(ApiError(StatusCode::BAD_REQUEST,
+                   format!("page must be 1 or more, and per_page between 1 and {MAX_PER_PAGE}")));
+    }
+    match (page - 1).checked_mul(per_page) {
+        Some(offset) => Ok((page, per_page, offset)),
+        None => Err(ApiError(StatusCode::BAD_REQUEST, format!("page {page} is out of range"))),
+    }
+}
+
+
+fn get_page_json(page: i64, per_page: i64, total: i64, items: Value) -> Value {
+    json!({
+        "page": page,
+        "per_page": per_page,
+        "total": total,
+        "pages": (total + per_page - 1) / per_page,
+        "items": items,
+    })
+}
+
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page_error(page: Option<i64>, per_page: Option<i64>) -> Option<(StatusCode, String)> {
+        get_page(page, per_page).err().map(|e| (e.0, e.1))
+    }
+
+    #[test]
+    fn check_page_defaults_and_offsets() {
+        assert_eq!(get_page(None, None).ok(), Some((1, DEFAULT_PER_PAGE, 0)));
+        assert_eq!(get_page(Some(3), Some(50)).ok(), Some((3, 50, 100)));
+        assert_eq!(get_page(Some(1), Some(MAX_PER_PAGE)).ok(), Some((1, MAX_PER_PAGE, 0)));
+    }
+
+    #[test]
+    fn check_page_bounds_are_enforced() {
+        for (page, per_page) in [(Some(0), None), (Some(-1), None), (None, Some(0)), (None, Some(MAX_PER_PAGE + 1))] {
+            let (status, msg) = page_error(page, per_page).expect("should be rejected");
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(msg.contains("per_page between 1 and"));
+        }
+    }
+
+    #[test]
+    fn check_page_overflow_is_rejected() {
+        let (status, msg) = page_error(Some(i64::MAX), Some(MAX_PER_PAGE)).expect("should be rejected");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(msg, format!("page {} is out of range", i64::MAX));
+    }
+
+    #[test]
+    fn check_internal_errors_are_not_exposed() {
+        let e = ApiError::from(AppError::SqlxError(sqlx::Error::RowNotFound, "select secret from t;".to_string()));
+        assert_eq!(e.0, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(e.1, "internal server error");
+    }
+
+    #[test]
+    fn check_page_json_counts_pages() {
+        let v = get_page_json(2, 20, 41, json!([]));
+        assert_eq!(v["pages"], 3);
+        assert_eq!((v["page"].clone(), v["per_page"].clone(), v["total"].clone()), (json!(2), json!(20), json!(41)));
+        assert_eq!(get_page_json(1, 20, 40, json!([]))["pages"], 2);
+        assert_eq!(get_page_json(1, 20, 0, json!([]))["pages"], 0);
+    }
+}
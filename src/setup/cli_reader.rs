@@ -1,8 +1,12 @@
 
 // Module uses clap crate to read command line arguments. 
 
-use clap::{command, Arg, ArgMatches};
+use clap::{command, Arg, ArgMatches, builder::PossibleValuesParser};
 use crate::err::AppError;
+use crate::import::data_vectors::parse_ror_id;
+use crate::lookup::enrich_ids::ENRICH_FIELDS;
+use crate::sql::create_lup_tables::ORG_TYPES;
+use crate::sql::create_ppr_tables::PPR_TABLES;
 use std::ffi::OsString;
 use std::path::PathBuf;
 
@@ -11,6 +15,35 @@ pub struct CliPars {
     pub source_file: String,
     pub data_version: String,
     pub data_date: String,
+    pub output_format: String,
+    pub report_format: String,
+    pub compare_versions: Vec<String>,
+    pub change_threshold: f64,
+    pub ror_json_mode: String,
+    pub rdf_format: String,
+    pub graph_format: String,
+    pub geojson_mode: String,
+    pub export_tables: Vec<String>,
+    pub delimiter: String,
+    pub ndjson_index: String,
+    pub id_field: String,
+    pub org_ids: Vec<String>,
+    pub org_format: String,
+    pub search_text: String,
+    pub filter_status: String,
+    pub search_limit: i64,
+    pub match_text: String,
+    pub match_file: PathBuf,
+    pub match_column: String,
+    pub max_candidates: i64,
+    pub enrich_file: PathBuf,
+    pub id_column: String,
+    pub enrich_fields: Vec<String>,
+    pub server_host: String,
+    pub server_port: u16,
+    pub filter_country: String,
+    pub filter_org_type: String,
+    pub filter_root: String,
     pub flags: Flags, 
     pub test_folder: PathBuf,
 }
@@ -22,6 +55,25 @@ pub struct Flags {
     pub simplify_comms: bool,
     pub export_csv: bool,
     pub export_all_csv: bool,
+    pub country_report: bool,
+    pub compare_versions: bool,
+    pub export_ror_json: bool,
+    pub export_sqlite: bool,
+    pub export_rdf: bool,
+    pub export_graph: bool,
+    pub export_geojson: bool,
+    pub export_tables: bool,
+    pub gzip_output: bool,
+    pub export_parquet: bool,
+    pub export_ndjson: bool,
+    pub export_crosswalk: bool,
+    pub lookup_orgs: bool,
+    pub search_names: bool,
+    pub match_affiliation: bool,
+    pub match_file: bool,
+    pub enrich_ids: bool,
+    pub reconcile_server: bool,
+    pub rest_server: bool,
     pub inc_withdrawn: bool,
     pub create_config: bool,
     pub create_lookups: bool,
@@ -33,11 +85,187 @@ pub fn fetch_valid_arguments(args: Vec<OsString>) -> Result<CliPars, AppError> {
 
     let parse_result = parse_args(args.to_vec())?;
 
-    // The string parameters below guaranteed to unwrap OK as all have a default value of "".
+    // The string parameters below guaranteed to unwrap OK as all have a default value ("", "csv" or "text").
 
     let source_file = parse_result.get_one::<String>("src_file").unwrap();
     let data_version = parse_result.get_one::<String>("data_version").unwrap();
     let data_date = parse_result.get_one::<String>("data_date").unwrap();
+    let output_format = parse_result.get_one::<String>("output_format").unwrap();
+    let report_format = parse_result.get_one::<String>("report_format").unwrap();
+    let change_threshold = *parse_result.get_one::<f64>("change_threshold").unwrap();
+
+    // The two versions to be compared, if given, otherwise an empty vector.
+
+    let compare_versions: Vec<String> = match parse_result.get_many::<String>("compare_versions") {
+        Some(vs) => vs.cloned().collect(),
+        None => Vec::new(),
+    };
+
+    // The mode of any ROR json export (clean or enriched), if requested, otherwise an empty string.
+
+    let ror_json_mode = match parse_result.get_one::<String>("ror_json") {
+        Some(m) => m.clone(),
+        None => "".to_string(),
+    };
+
+    // The format of any linked data export (ttl, nt or jsonld), if requested, otherwise an empty string.
+
+    let rdf_format = match parse_result.get_one::<String>("rdf") {
+        Some(f) => f.clone(),
+        None => "".to_string(),
+    };
+
+    // The format of any relationship graph export (graphml, dot or csv), and any filters
+    // on the organisations included (in a graph or GeoJSON export), otherwise empty strings. The country code must be two
+    // letters, and a root organisation can be given as a full ROR URL or as the id alone.
+
+    let graph_format = match parse_result.get_one::<String>("graph") {
+        Some(f) => f.clone(),
+        None => "".to_string(),
+    };
+
+    // Whether any GeoJSON export is to a single file or one per country, if requested.
+
+    let geojson_mode = match parse_result.get_one::<String>("geojson") {
+        Some(m) => m.clone(),
+        None => "".to_string(),
+    };
+
+    // The ppr tables to be exported as delimited files, if requested, otherwise an empty
+    // vector. 'all' (the default if no table is named) is expanded to the full list.
+
+    let export_tables: Vec<String> = match parse_result.get_many::<String>("tables") {
+        Some(ts) => {
+            let ts: Vec<String> = ts.cloned().collect();
+            if ts.iter().any(|t| t == "all") {
+                PPR_TABLES.iter().map(|t| t.to_string()).collect()
+            } else {
+                ts
+            }
+        },
+        None => Vec::new(),
+    };
+    let delimiter = parse_result.get_one::<String>("delimiter").unwrap();
+
+    // The index named in a search engine bulk export, if requested, and the field used as
+    // the document id. Index names must be lower case, and without spaces or most punctuation.
+
+    let ndjson_index = match parse_result.get_one::<String>("ndjson") {
+        Some(i) => {
+            let i = i.trim().to_string();
+            if i.is_empty() || i.starts_with(['-', '_', '+'])
+               || !i.chars().all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '-' || ch == '_' || ch == '.') {
+                return Err(AppError::InconsistentProgramParameter(format!("'{}' is not a valid index name", i)));
+            }
+            i
+        },
+        None => "".to_string(),
+    };
+    let id_field = parse_result.get_one::<String>("id_field").unwrap();
+
+    let filter_country = match parse_result.get_one::<String>("country") {
+        Some(c) => {
+            let c = c.trim().to_uppercase();
+            if c.len() != 2 || !c.chars().all(|ch| ch.is_ascii_alphabetic()) {
+                return Err(AppError::InconsistentProgramParameter(format!("'{}' is not a two letter country code", c)));
+            }
+            c
+        },
+        None => "".to_string(),
+    };
+
+    let filter_org_type = match parse_result.get_one::<String>("org_type") {
+        Some(t) => t.clone(),
+        None => "".to_string(),
+    };
+
+    let filter_root = match parse_result.get_one::<String>("root") {
+        Some(r) => get_valid_ror_id(r)?,
+        None => "".to_string(),
+    };
+
+    // The organisations to be looked up, if requested, as short ROR ids, and the output format.
+
+    let org_ids: Vec<String> = match parse_result.get_many::<String>("org") {
+        Some(ids) => ids.map(|id| get_valid_ror_id(id)).collect::<Result<Vec<String>, AppError>>()?,
+        None => Vec::new(),
+    };
+    let org_format = parse_result.get_one::<String>("org_format").unwrap();
+
+    // The text of any name search, the status used as a further filter, and the number of results.
+
+    let search_text = match parse_result.get_one::<String>("search") {
+        Some(s) => {
+            let s = s.trim().to_string();
+            if !s.chars().any(|ch| ch.is_alphanumeric()) {
+                return Err(AppError::InconsistentProgramParameter(format!("'{}' is not a valid search text", s)));
+            }
+            s
+        },
+        None => "".to_string(),
+    };
+    let filter_status = match parse_result.get_one::<String>("status") {
+        Some(s) => s.clone(),
+        None => "".to_string(),
+    };
+    let search_limit = *parse_result.get_one::<i64>("limit").unwrap();
+
+    // Any single affiliation string to be matched, and any csv file of affiliations to be matched
+    // in batch, with the column holding them, and the number of candidates reported for each.
+
+    let match_text = match parse_result.get_one::<String>("match") {
+        Some(s) => {
+            let s = s.trim().to_string();
+            if !s.chars().any(|ch| ch.is_alphanumeric()) {
+                return Err(AppError::InconsistentProgramParameter(format!("'{}' is not a valid affiliation", s)));
+            }
+            s
+        },
+        None => "".to_string(),
+    };
+    let match_file = match parse_result.get_one::<String>("match_file") {
+        Some(f) => {
+            let f = PathBuf::from(f.trim());
+            match f.try_exists() {
+                Ok(true) => f,
+                _ => return Err(AppError::InconsistentProgramParameter(format!("match file {:?} does not exist", f))),
+            }
+        },
+        None => PathBuf::new(),
+    };
+    let match_column = parse_result.get_one::<String>("match_column").unwrap();
+    let max_candidates = *parse_result.get_one::<i64>("max_candidates").unwrap();
+
+    // Any csv file of ROR ids to be enriched, with the column holding the ids, and the fields
+    // to be added. 'all' (the default) is expanded to the full list.
+
+    let enrich_file = match parse_result.get_one::<String>("enrich") {
+        Some(f) => {
+            let f = PathBuf::from(f.trim());
+            match f.try_exists() {
+                Ok(true) => f,
+                _ => return Err(AppError::InconsistentProgramParameter(format!("enrich file {:?} does not exist", f))),
+            }
+        },
+        None => PathBuf::new(),
+    };
+    let id_column = parse_result.get_one::<String>("id_column").unwrap();
+    let enrich_fields: Vec<String> = match parse_result.get_many::<String>("enrich_fields") {
+        Some(fs) => {
+            let fs: Vec<String> = fs.cloned().collect();
+            if fs.iter().any(|f| f == "all") {
+                ENRICH_FIELDS.iter().map(|(f, _)| f.to_string()).collect()
+            } else {
+                fs
+            }
+        },
+        None => Vec::new(),
+    };
+
+    // The address at which any HTTP server listens.
+
+    let server_host = parse_result.get_one::<String>("host").unwrap();
+    let server_port = *parse_result.get_one::<u16>("port").unwrap();
    
     let test_folder_as_string = parse_result.get_one::<String>("test_folder").unwrap();
     let test_folder = PathBuf::from(test_folder_as_string);
@@ -49,6 +277,25 @@ pub fn fetch_valid_arguments(args: Vec<OsString>) -> Result<CliPars, AppError> {
     let mut s_flag = parse_result.get_flag("s_flag");
     let mut x_flag = parse_result.get_flag("x_flag");
     let mut y_flag = parse_result.get_flag("y_flag");
+    let mut r_flag = parse_result.get_flag("r_flag");
+    let mut l_flag = parse_result.get_flag("l_flag");
+    let mut n_flag = !compare_versions.is_empty();
+    let mut j_flag = !ror_json_mode.is_empty();
+    let mut z_flag = !rdf_format.is_empty();
+    let mut q_flag = !graph_format.is_empty();
+    let mut b_flag = !geojson_mode.is_empty();
+    let mut tables_flag = !export_tables.is_empty();
+    let gzip_flag = parse_result.get_flag("gzip");
+    let mut parquet_flag = parse_result.get_flag("parquet");
+    let mut ndjson_flag = !ndjson_index.is_empty();
+    let mut crosswalk_flag = parse_result.get_flag("crosswalk");
+    let mut org_flag = !org_ids.is_empty();
+    let mut search_flag = !search_text.is_empty();
+    let mut match_flag = !match_text.is_empty();
+    let mut match_file_flag = !match_file.as_os_str().is_empty();
+    let mut enrich_flag = !enrich_file.as_os_str().is_empty();
+    let mut reconcile_flag = parse_result.get_flag("reconcile");
+    let mut serve_flag = parse_result.get_flag("serve");
     let w_flag = parse_result.get_flag("w_flag");
     let i_flag = parse_result.get_flag("i_flag");
     let mut c_flag = parse_result.get_flag("c_flag");
@@ -71,6 +318,24 @@ pub fn fetch_valid_arguments(args: Vec<OsString>) -> Result<CliPars, AppError> {
         s_flag = false;
         x_flag = false;
         y_flag = false;
+        r_flag = false;
+        n_flag = false;
+        j_flag = false;
+        l_flag = false;
+        z_flag = false;
+        q_flag = false;
+        b_flag = false;
+        tables_flag = false;
+        parquet_flag = false;
+        ndjson_flag = false;
+        crosswalk_flag = false;
+        org_flag = false;
+        search_flag = false;
+        match_flag = false;
+        match_file_flag = false;
+        enrich_flag = false;
+        reconcile_flag = false;
+        serve_flag = false;
         t_flag = false;        
     }
 
@@ -88,13 +353,38 @@ pub fn fetch_valid_arguments(args: Vec<OsString>) -> Result<CliPars, AppError> {
         s_flag = true;
         x_flag = false;
         y_flag = false;
+        r_flag = false;
+        n_flag = false;
+        j_flag = false;
+        l_flag = false;
+        z_flag = false;
+        q_flag = false;
+        b_flag = false;
+        tables_flag = false;
+        parquet_flag = false;
+        ndjson_flag = false;
+        crosswalk_flag = false;
+        org_flag = false;
+        search_flag = false;
+        match_flag = false;
+        match_file_flag = false;
+        enrich_flag = false;
+        reconcile_flag = false;
+        serve_flag = false;
     }
 
     // More usual situation is -a, -e, -x, or -y, possibly with -w.
     // -a and / or -e flags can be accompanied by -x or -y (will be done first)
     // If -x and -y flags both given, only -y is allowed.
     // If -e flag emsure -a is true. If -s flag ensure both -a and -e are true
-    // If none of a, e, s, x, y or t flags set a to true, as the default - this
+    // -r (country report), -n (version comparison), -j (ROR json export), -l (SQLite export)
+    // -z (linked data export), -q (graph export), -b (GeoJSON export), --tables (ppr table
+    // export), --parquet (Parquet export), --ndjson (search engine bulk export) and --crosswalk
+    // (external id crosswalk) can be used alone or with any of the above, as can --org (print
+    // organisation records), --search (name search), --match / --match_file (affiliation matching)
+    // and --enrich (ROR id enrichment). --reconcile (reconciliation server) and --serve (REST API)
+    // run until stopped, after any other actions.
+    // If none of a, e, s, x, y, r, n, j, l, z, q, b, tables, parquet, ndjson, crosswalk, org, search, match, match_file, enrich, reconcile, serve or t flags set a to true, as the default - this
     // will also need the source file designated, perhaps in config file.
     
     else 
@@ -103,7 +393,7 @@ pub fn fetch_valid_arguments(args: Vec<OsString>) -> Result<CliPars, AppError> {
             x_flag = false;
         }
        
-        if !a_flag && !e_flag && !s_flag && !x_flag && !y_flag {
+        if !a_flag && !e_flag && !s_flag && !x_flag && !y_flag && !r_flag && !n_flag && !j_flag && !l_flag && !z_flag && !q_flag && !b_flag && !tables_flag && !parquet_flag && !ndjson_flag && !crosswalk_flag && !org_flag && !search_flag && !match_flag && !match_file_flag && !enrich_flag && !reconcile_flag && !serve_flag {
             a_flag = true;   
         }
 
@@ -123,6 +413,25 @@ pub fn fetch_valid_arguments(args: Vec<OsString>) -> Result<CliPars, AppError> {
         simplify_comms: s_flag,
         export_csv: x_flag,
         export_all_csv: y_flag,
+        country_report: r_flag,
+        compare_versions: n_flag,
+        export_ror_json: j_flag,
+        export_sqlite: l_flag,
+        export_rdf: z_flag,
+        export_graph: q_flag,
+        export_geojson: b_flag,
+        export_tables: tables_flag,
+        gzip_output: gzip_flag,
+        export_parquet: parquet_flag,
+        export_ndjson: ndjson_flag,
+        export_crosswalk: crosswalk_flag,
+        lookup_orgs: org_flag,
+        search_names: search_flag,
+        match_affiliation: match_flag,
+        match_file: match_file_flag,
+        enrich_ids: enrich_flag,
+        reconcile_server: reconcile_flag,
+        rest_server: serve_flag,
         create_config: c_flag,
         create_lookups: k_flag,
         create_summary: m_flag,
@@ -134,11 +443,47 @@ pub fn fetch_valid_arguments(args: Vec<OsString>) -> Result<CliPars, AppError> {
         source_file: source_file.clone(),
         data_version: data_version.clone(),
         data_date: data_date.clone(),
+        output_format: output_format.clone(),
+        report_format: report_format.clone(),
+        compare_versions: compare_versions.clone(),
+        change_threshold,
+        ror_json_mode: ror_json_mode.clone(),
+        rdf_format: rdf_format.clone(),
+        graph_format: graph_format.clone(),
+        geojson_mode: geojson_mode.clone(),
+        export_tables: export_tables.clone(),
+        delimiter: delimiter.clone(),
+        ndjson_index: ndjson_index.clone(),
+        id_field: id_field.clone(),
+        org_ids: org_ids.clone(),
+        org_format: org_format.clone(),
+        search_text: search_text.clone(),
+        filter_status: filter_status.clone(),
+        search_limit,
+        match_text: match_text.clone(),
+        match_file: match_file.clone(),
+        match_column: match_column.clone(),
+        max_candidates,
+        enrich_file: enrich_file.clone(),
+        id_column: id_column.clone(),
+        enrich_fields: enrich_fields.clone(),
+        server_host: server_host.clone(),
+        server_port,
+        filter_country: filter_country.clone(),
+        filter_org_type: filter_org_type.clone(),
+        filter_root: filter_root.clone(),
         test_folder: test_folder.clone(),
         flags: flags,
     })
 }
 
+fn get_valid_ror_id(id: &str) -> Result<String, AppError> {
+
+    // Accepts either the 9 character ROR id or the full URL form, returning the former.
+
+    parse_ror_id(id).ok_or_else(|| AppError::InconsistentProgramParameter(format!("'{}' is not a valid ROR id", id.trim())))
+}
+
 fn parse_args(args: Vec<OsString>) -> Result<ArgMatches, clap::Error> {
 
     command!()
@@ -167,6 +512,24 @@ fn parse_args(args: Vec<OsString>) -> Result<ArgMatches, clap::Error> {
            .help("A string with a date in ISO format that gives the date of the data")
            .default_value("")
         )
+        .arg(
+            Arg::new("output_format")
+           .short('o')
+           .long("output_format")
+           .required(false)
+           .help("The format of the summary files produced by -x or -y, either csv (the default), xlsx or json")
+           .value_parser(["csv", "xlsx", "json"])
+           .default_value("csv")
+        )
+        .arg(
+            Arg::new("report_format")
+           .short('p')
+           .long("report_format")
+           .required(false)
+           .help("The format of the summary report produced after an import, either text (the default), html or md (markdown)")
+           .value_parser(["text", "html", "md"])
+           .default_value("text")
+        )
         .arg(
             Arg::new("a_flag")
            .short('a')
@@ -207,6 +570,268 @@ fn parse_args(args: Vec<OsString>) -> Result<ArgMatches, clap::Error> {
            .help("A flag signifying output a summary of the data for all versions into csv files")
            .action(clap::ArgAction::SetTrue)
        )
+       .arg(
+           Arg::new("r_flag")
+          .short('r')
+          .long("country_report")
+          .required(false)
+          .help("A flag signifying output a text report, country by country, of the current or specified version")
+          .action(clap::ArgAction::SetTrue)
+       )
+       .arg(
+           Arg::new("compare_versions")
+          .short('n')
+          .long("compare")
+          .required(false)
+          .num_args(2)
+          .value_names(["VERSION_1", "VERSION_2"])
+          .help("Two version codes, of versions already summarised, to be compared in a text and csv report")
+       )
+       .arg(
+           Arg::new("change_threshold")
+          .short('g')
+          .long("threshold")
+          .required(false)
+          .value_parser(clap::value_parser!(f64))
+          .help("The %age change above which differences are marked in a version comparison (default 10)")
+          .default_value("10")
+       )
+       .arg(
+           Arg::new("ror_json")
+          .short('j')
+          .long("ror_json")
+          .required(false)
+          .num_args(0..=1)
+          .value_parser(["clean", "enriched"])
+          .default_missing_value("clean")
+          .help("Output the processed data as ROR v2 json, either clean (the default) or enriched with derived language and script codes")
+       )
+       .arg(
+           Arg::new("rdf")
+          .short('z')
+          .long("rdf")
+          .required(false)
+          .num_args(0..=1)
+          .value_parser(["ttl", "nt", "jsonld"])
+          .default_missing_value("ttl")
+          .help("Output the processed data as schema.org linked data, as Turtle (ttl, the default), N-Triples (nt) or JSON-LD (jsonld)")
+       )
+       .arg(
+           Arg::new("graph")
+          .short('q')
+          .long("graph")
+          .required(false)
+          .num_args(0..=1)
+          .value_parser(["graphml", "dot", "csv"])
+          .default_missing_value("graphml")
+          .help("Output the relationships between organisations as a graph, in graphml (the default), dot or csv (node and edge list) format")
+       )
+       .arg(
+           Arg::new("geojson")
+          .short('b')
+          .long("geojson")
+          .required(false)
+          .num_args(0..=1)
+          .value_parser(["single", "by_country"])
+          .default_missing_value("single")
+          .help("Output the locations of organisations as GeoJSON, in a single file (the default) or one file per country (by_country)")
+       )
+       .arg(
+           Arg::new("tables")
+          .long("tables")
+          .required(false)
+          .num_args(0..)
+          .value_parser(PossibleValuesParser::new(std::iter::once("all").chain(PPR_TABLES)))
+          .default_missing_value("all")
+          .help("Output the named ppr tables (or all of them, the default) as delimited files")
+       )
+       .arg(
+           Arg::new("delimiter")
+          .long("delimiter")
+          .required(false)
+          .value_parser(["comma", "tab"])
+          .default_value("comma")
+          .help("The delimiter used in files produced by --tables, either comma (the default) or tab")
+       )
+       .arg(
+           Arg::new("gzip")
+          .long("gzip")
+          .required(false)
+          .help("A flag signifying that files produced by --tables should be gzipped")
+          .action(clap::ArgAction::SetTrue)
+       )
+       .arg(
+           Arg::new("parquet")
+          .long("parquet")
+          .required(false)
+          .help("A flag signifying output the ppr tables, and the smm tables for all versions, as Parquet files")
+          .action(clap::ArgAction::SetTrue)
+       )
+       .arg(
+           Arg::new("ndjson")
+          .long("ndjson")
+          .required(false)
+          .num_args(0..=1)
+          .default_missing_value("ror")
+          .help("Output the processed data as an Elasticsearch / OpenSearch bulk (NDJSON) file, for the named index ('ror' by default)")
+       )
+       .arg(
+           Arg::new("id_field")
+          .long("id_field")
+          .required(false)
+          .value_parser(["id", "ror_id", "none"])
+          .default_value("id")
+          .help("The document id used in files produced by --ndjson, either the short ROR id (id, the default), the full ROR id (ror_id) or none")
+       )
+       .arg(
+           Arg::new("crosswalk")
+          .long("crosswalk")
+          .required(false)
+          .help("A flag signifying rebuild the external id crosswalk tables, and output them as csv files")
+          .action(clap::ArgAction::SetTrue)
+       )
+       .arg(
+           Arg::new("org")
+          .long("org")
+          .required(false)
+          .num_args(1..)
+          .help("One or more ROR ids (short or full form), the processed records of which are printed")
+       )
+       .arg(
+           Arg::new("org_format")
+          .long("org_format")
+          .required(false)
+          .value_parser(["text", "json"])
+          .default_value("text")
+          .help("The format of the records printed by --org, either text (the default) or json")
+       )
+       .arg(
+           Arg::new("search")
+          .long("search")
+          .required(false)
+          .help("Text to be searched for (case and accent insensitively) in the names of organisations")
+       )
+       .arg(
+           Arg::new("status")
+          .long("status")
+          .required(false)
+          .value_parser(["active", "inactive", "withdrawn"])
+          .help("An organisation status, restricting a name search to organisations with that status")
+       )
+       .arg(
+           Arg::new("limit")
+          .long("limit")
+          .required(false)
+          .value_parser(clap::value_parser!(i64).range(1..))
+          .default_value("20")
+          .help("The maximum number of organisations returned by a name search (default 20)")
+       )
+       .arg(
+           Arg::new("match")
+          .long("match")
+          .required(false)
+          .help("An affiliation string, to be matched against the organisations, with the ranked candidates printed")
+       )
+       .arg(
+           Arg::new("match_file")
+          .long("match_file")
+          .required(false)
+          .help("The path of a csv file of affiliation strings, each to be matched, with the results written to a csv file")
+       )
+       .arg(
+           Arg::new("match_column")
+          .long("match_column")
+          .required(false)
+          .default_value("affiliation")
+          .help("The column of the --match_file csv file that holds the affiliation strings (default 'affiliation')")
+       )
+       .arg(
+           Arg::new("max_candidates")
+          .long("max_candidates")
+          .required(false)
+          .value_parser(clap::value_parser!(i64).range(1..))
+          .default_value("5")
+          .help("The maximum number of candidate organisations reported for each affiliation (default 5)")
+       )
+       .arg(
+           Arg::new("enrich")
+          .long("enrich")
+          .required(false)
+          .help("The path of a csv file with a column of ROR ids, to be written to a new csv file with data about each organisation added")
+       )
+       .arg(
+           Arg::new("id_column")
+          .long("id_column")
+          .required(false)
+          .default_value("ror_id")
+          .help("The column of the --enrich csv file that holds the ROR ids (default 'ror_id')")
+       )
+       .arg(
+           Arg::new("enrich_fields")
+          .long("enrich_fields")
+          .required(false)
+          .num_args(1..)
+          .value_delimiter(',')
+          .value_parser(PossibleValuesParser::new(std::iter::once("all").chain(ENRICH_FIELDS.iter().map(|(f, _)| *f))))
+          .default_value("all")
+          .help("The fields added to each row of the --enrich csv file (all of them, the default, or those named)")
+       )
+       .arg(
+           Arg::new("reconcile")
+          .long("reconcile")
+          .required(false)
+          .help("A flag signifying run an OpenRefine reconciliation service over the processed data, until stopped")
+          .action(clap::ArgAction::SetTrue)
+       )
+       .arg(
+           Arg::new("serve")
+          .long("serve")
+          .required(false)
+          .help("A flag signifying run a read-only REST API over the processed and summary data, until stopped")
+          .action(clap::ArgAction::SetTrue)
+       )
+       .arg(
+           Arg::new("host")
+          .long("host")
+          .required(false)
+          .default_value("127.0.0.1")
+          .help("The host name or IP address at which any HTTP server listens (default 127.0.0.1)")
+       )
+       .arg(
+           Arg::new("port")
+          .long("port")
+          .required(false)
+          .value_parser(clap::value_parser!(u16).range(1..))
+          .default_value("8000")
+          .help("The port at which any HTTP server listens (default 8000)")
+       )
+       .arg(
+           Arg::new("country")
+          .long("country")
+          .required(false)
+          .help("A two letter country code, restricting a graph or GeoJSON export, or a name search, to organisations in that country")
+       )
+       .arg(
+           Arg::new("org_type")
+          .long("org_type")
+          .required(false)
+          .value_parser(ORG_TYPES)
+          .help("An organisation type, restricting a graph or GeoJSON export, or a name search, to organisations of that type")
+       )
+       .arg(
+           Arg::new("root")
+          .long("root")
+          .required(false)
+          .help("A ROR id, restricting a graph or GeoJSON export to that organisation and its descendants")
+       )
+       .arg(
+           Arg::new("l_flag")
+          .short('l')
+          .long("sqlite")
+          .required(false)
+          .help("A flag signifying output the ppr and lup tables, and the smm data for the current version, to a SQLite file")
+          .action(clap::ArgAction::SetTrue)
+       )
        .arg(
            Arg::new("w_flag")
           .short('w')
@@ -436,6 +1061,474 @@ mod tests {
     }
 
 
+    #[test]
+    fn check_cli_with_n_option_and_threshold() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "-n", "v1.58", "v1.62", "-g", "5"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.export_csv, false);
+        assert_eq!(res.flags.compare_versions, true);
+        assert_eq!(res.compare_versions, vec!["v1.58", "v1.62"]);
+        assert_eq!(res.change_threshold, 5.0);
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn check_cli_with_n_option_and_one_version_panics() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "-n", "v1.58"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let _res = fetch_valid_arguments(test_args).unwrap();
+    }
+
+
+    #[test]
+    fn check_cli_with_j_option_default_and_enriched() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "-j"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.export_ror_json, true);
+        assert_eq!(res.ror_json_mode, "clean");
+
+        let args : Vec<&str> = vec![target, "-a", "-j", "enriched"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, true);
+        assert_eq!(res.flags.export_ror_json, true);
+        assert_eq!(res.ror_json_mode, "enriched");
+    }
+
+
+    #[test]
+    fn check_cli_with_z_option_default_and_jsonld() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "-z"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.export_rdf, true);
+        assert_eq!(res.rdf_format, "ttl");
+
+        let args : Vec<&str> = vec![target, "-z", "jsonld", "-l"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.export_rdf, true);
+        assert_eq!(res.flags.export_sqlite, true);
+        assert_eq!(res.rdf_format, "jsonld");
+    }
+
+
+    #[test]
+    fn check_cli_with_q_option_and_filters() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "-q"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.export_graph, true);
+        assert_eq!(res.graph_format, "graphml");
+        assert_eq!(res.filter_country, "");
+
+        let args : Vec<&str> = vec![target, "-q", "dot", "--country", "au", "--org_type", "education", "--root", "https://ror.org/04ttjf776"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.export_graph, true);
+        assert_eq!(res.graph_format, "dot");
+        assert_eq!(res.filter_country, "AU");
+        assert_eq!(res.filter_org_type, "education");
+        assert_eq!(res.filter_root, "04ttjf776");
+    }
+
+
+    #[test]
+    fn check_cli_with_b_option_by_country() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "-b"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.export_geojson, true);
+        assert_eq!(res.geojson_mode, "single");
+
+        let args : Vec<&str> = vec![target, "-b", "by_country", "--org_type", "funder"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.export_geojson, true);
+        assert_eq!(res.geojson_mode, "by_country");
+        assert_eq!(res.filter_org_type, "funder");
+    }
+
+
+    #[test]
+    fn check_cli_with_tables_option() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "--tables"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.export_tables, true);
+        assert_eq!(res.flags.gzip_output, false);
+        assert_eq!(res.export_tables.len(), 9);
+        assert_eq!(res.delimiter, "comma");
+
+        let args : Vec<&str> = vec![target, "--tables", "names", "locations", "--delimiter", "tab", "--gzip"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.export_tables, true);
+        assert_eq!(res.flags.gzip_output, true);
+        assert_eq!(res.export_tables, vec!["names".to_string(), "locations".to_string()]);
+        assert_eq!(res.delimiter, "tab");
+    }
+
+
+    #[test]
+    fn check_cli_with_parquet_flag() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "--parquet"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.export_tables, false);
+        assert_eq!(res.flags.export_parquet, true);
+    }
+
+
+    #[test]
+    fn check_cli_with_ndjson_option() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "--ndjson"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.export_ndjson, true);
+        assert_eq!(res.ndjson_index, "ror");
+        assert_eq!(res.id_field, "id");
+
+        let args : Vec<&str> = vec![target, "--ndjson", "ror-orgs", "--id_field", "ror_id"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.export_ndjson, true);
+        assert_eq!(res.ndjson_index, "ror-orgs");
+        assert_eq!(res.id_field, "ror_id");
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn check_cli_with_ndjson_option_and_bad_index_panics() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "--ndjson", "ROR Orgs"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+        let _res = fetch_valid_arguments(test_args).unwrap();
+    }
+
+
+    #[test]
+    fn check_cli_with_crosswalk_flag() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "--crosswalk"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.export_ndjson, false);
+        assert_eq!(res.flags.export_crosswalk, true);
+    }
+
+
+    #[test]
+    fn check_cli_with_org_option() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "--org", "04ttjf776", "https://ror.org/006jxzx88"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.lookup_orgs, true);
+        assert_eq!(res.org_ids, vec!["04ttjf776".to_string(), "006jxzx88".to_string()]);
+        assert_eq!(res.org_format, "text");
+
+        let args : Vec<&str> = vec![target, "--org", "04ttjf776", "--org_format", "json"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.lookup_orgs, true);
+        assert_eq!(res.org_format, "json");
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn check_cli_with_org_option_and_bad_id_panics() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "--org", "04ttjf776", "ror.org/0-x"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+        let _res = fetch_valid_arguments(test_args).unwrap();
+    }
+
+
+    #[test]
+    fn check_cli_with_search_option() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "--search", " Universidad de "];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.search_names, true);
+        assert_eq!(res.search_text, "Universidad de");
+        assert_eq!(res.filter_status, "");
+        assert_eq!(res.search_limit, 20);
+
+        let args : Vec<&str> = vec![target, "--search", "fraunhofer", "--country", "de", "--org_type", "facility",
+                                    "--status", "active", "--limit", "5"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.search_names, true);
+        assert_eq!(res.filter_country, "DE");
+        assert_eq!(res.filter_org_type, "facility");
+        assert_eq!(res.filter_status, "active");
+        assert_eq!(res.search_limit, 5);
+    }
+
+
+    #[test]
+    fn check_cli_with_match_options() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "--match", " Dept of Physics, RMIT University, Melbourne, Australia "];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.match_affiliation, true);
+        assert_eq!(res.flags.match_file, false);
+        assert_eq!(res.match_text, "Dept of Physics, RMIT University, Melbourne, Australia");
+        assert_eq!(res.max_candidates, 5);
+
+        let args : Vec<&str> = vec![target, "--match_file", "Cargo.toml", "--match_column", "affil", "--max_candidates", "3"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.match_affiliation, false);
+        assert_eq!(res.flags.match_file, true);
+        assert_eq!(res.match_file, PathBuf::from("Cargo.toml"));
+        assert_eq!(res.match_column, "affil");
+        assert_eq!(res.max_candidates, 3);
+    }
+
+
+    #[test]
+    fn check_cli_with_enrich_options() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "--enrich", "Cargo.toml"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.enrich_ids, true);
+        assert_eq!(res.enrich_file, PathBuf::from("Cargo.toml"));
+        assert_eq!(res.id_column, "ror_id");
+        assert_eq!(res.enrich_fields.len(), 9);
+
+        let args : Vec<&str> = vec![target, "--enrich", "Cargo.toml", "--id_column", "ROR", "--enrich_fields", "ror_name,country", "isni"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.enrich_ids, true);
+        assert_eq!(res.id_column, "ROR");
+        assert_eq!(res.enrich_fields, vec!["ror_name", "country", "isni"]);
+    }
+
+
+    #[test]
+    fn check_cli_with_reconcile_flag() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "--reconcile"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.reconcile_server, true);
+        assert_eq!(res.server_host, "127.0.0.1");
+        assert_eq!(res.server_port, 8000);
+
+        let args : Vec<&str> = vec![target, "--reconcile", "--host", "0.0.0.0", "--port", "8091", "--max_candidates", "3"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.reconcile_server, true);
+        assert_eq!(res.server_host, "0.0.0.0");
+        assert_eq!(res.server_port, 8091);
+        assert_eq!(res.max_candidates, 3);
+    }
+
+
+    #[test]
+    fn check_cli_with_serve_flag() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "--serve", "--port", "8092"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.rest_server, true);
+        assert_eq!(res.flags.reconcile_server, false);
+        assert_eq!(res.server_port, 8092);
+
+        let args : Vec<&str> = vec![target, "--serve", "--reconcile"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.rest_server, true);
+        assert_eq!(res.flags.reconcile_server, true);
+        assert_eq!(res.server_host, "127.0.0.1");
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn check_cli_with_match_file_option_and_missing_file_panics() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "--match_file", "no such folder/affiliations.csv"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+        let _res = fetch_valid_arguments(test_args).unwrap();
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn check_cli_with_q_option_and_bad_root_panics() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "-q", "--root", "04ttjf"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+        let _res = fetch_valid_arguments(test_args).unwrap();
+    }
+
+
+    #[test]
+    fn check_cli_with_l_flag() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "-l"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.export_csv, false);
+        assert_eq!(res.flags.export_sqlite, true);
+    }
+
+
+    #[test]
+    fn check_cli_with_r_flag() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "-r"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.source_file, "");
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.enhance_proc, false);
+        assert_eq!(res.flags.simplify_comms, false);
+        assert_eq!(res.flags.export_csv, false);
+        assert_eq!(res.flags.export_all_csv, false);
+        assert_eq!(res.flags.country_report, true);
+        assert_eq!(res.flags.create_config, false);
+        assert_eq!(res.flags.create_lookups, false);
+        assert_eq!(res.flags.create_summary, false);
+        assert_eq!(res.flags.test_run, false);
+        assert_eq!(res.data_date, "");
+        assert_eq!(res.data_version, "");
+    }
+
+
+    #[test]
+    fn check_cli_with_x_flag_and_xlsx_format() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "-x", "-o", "xlsx"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.export_csv, true);
+        assert_eq!(res.flags.export_all_csv, false);
+        assert_eq!(res.output_format, "xlsx");
+    }
+
+
+    #[test]
+    fn check_cli_with_y_flag_and_json_format() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "-y", "-o", "json"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, false);
+        assert_eq!(res.flags.export_csv, false);
+        assert_eq!(res.flags.export_all_csv, true);
+        assert_eq!(res.output_format, "json");
+    }
+
+
+    #[test]
+    fn check_cli_with_html_report_format() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "-p", "html"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, true);
+        assert_eq!(res.output_format, "csv");
+        assert_eq!(res.report_format, "html");
+    }
+
+
+    #[test]
+    fn check_cli_with_md_report_format() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "-a", "-p", "md"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert_eq!(res.flags.import_ror, true);
+        assert_eq!(res.report_format, "md");
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn check_cli_with_invalid_output_format_panics() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "-x", "-o", "pdf"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let _res = fetch_valid_arguments(test_args).unwrap();
+    }
+
+
     #[test]
     fn check_cli_with_x_and_y_flag() {
         let target = "dummy target";
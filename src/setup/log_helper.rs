@@ -112,11 +112,67 @@ pub fn log_startup_params (ip : &InitParams) {
     else {
         info!("Processing run");
         info!("import_ror: {}", f.import_ror);
+        if f.import_ror {
+            info!("report_format: {}", ip.report_format);
+        }
         info!("enhanced name processing: {}", f.enhance_proc);
         info!("comm. names simplified: {}", f.simplify_comms);
 
         info!("export_csv: {}", f.export_csv);
         info!("export_all_csv: {}", f.export_all_csv);
+        if f.export_csv || f.export_all_csv {
+            info!("output_format: {}", ip.output_format);
+        }
+        if f.compare_versions {
+            info!("compare versions: {} and {}, threshold {}%", ip.compare_versions[0], ip.compare_versions[1], ip.change_threshold);
+        }
+        if f.export_ror_json {
+            info!("export ror json: {}", ip.ror_json_mode);
+        }
+        info!("export sqlite: {}", f.export_sqlite);
+        if f.export_rdf {
+            info!("export linked data: {}", ip.rdf_format);
+        }
+        if f.export_graph {
+            info!("export graph: {}, country '{}', org type '{}', root '{}'", ip.graph_format, 
+                   ip.filter_country, ip.filter_org_type, ip.filter_root);
+        }
+        if f.export_tables {
+            info!("export ppr tables: {}, delimiter {}, gzip {}", ip.export_tables.join(", "), ip.delimiter, f.gzip_output);
+        }
+        info!("export parquet: {}", f.export_parquet);
+        if f.export_ndjson {
+            info!("export ndjson: index '{}', id field {}", ip.ndjson_index, ip.id_field);
+        }
+        info!("export crosswalk: {}", f.export_crosswalk);
+        if f.lookup_orgs {
+            info!("org lookup: {} ({})", ip.org_ids.join(", "), ip.org_format);
+        }
+        if f.search_names {
+            info!("name search: '{}', country '{}', org type '{}', status '{}', limit {}", ip.search_text,
+                   ip.filter_country, ip.filter_org_type, ip.filter_status, ip.search_limit);
+        }
+        if f.match_affiliation {
+            info!("affiliation match: '{}', max candidates {}", ip.match_text, ip.max_candidates);
+        }
+        if f.match_file {
+            info!("affiliation file match: {}, column '{}', max candidates {}", ip.match_file.display(),
+                   ip.match_column, ip.max_candidates);
+        }
+        if f.enrich_ids {
+            info!("ROR id enrichment: {}, column '{}', fields {}", ip.enrich_file.display(),
+                   ip.id_column, ip.enrich_fields.join(", "));
+        }
+        if f.reconcile_server {
+            info!("reconciliation server: {}:{}, default limit {}", ip.server_host, ip.server_port, ip.max_candidates);
+        }
+        if f.rest_server {
+            info!("REST API server: {}:{}", ip.server_host, ip.server_port);
+        }
+        if f.export_geojson {
+            info!("export geojson: {}, country '{}', org type '{}', root '{}'", ip.geojson_mode, 
+                   ip.filter_country, ip.filter_org_type, ip.filter_root);
+        }
         info!("retain withdrawn: {}", f.inc_withdrawn);
     }
     info!("");
@@ -33,6 +33,35 @@ pub struct InitParams {
     pub data_version: String,
     pub data_date: String,
     pub double_quotes: String,
+    pub output_format: String,
+    pub report_format: String,
+    pub compare_versions: Vec<String>,
+    pub change_threshold: f64,
+    pub ror_json_mode: String,
+    pub rdf_format: String,
+    pub graph_format: String,
+    pub geojson_mode: String,
+    pub export_tables: Vec<String>,
+    pub delimiter: String,
+    pub ndjson_index: String,
+    pub id_field: String,
+    pub org_ids: Vec<String>,
+    pub org_format: String,
+    pub search_text: String,
+    pub filter_status: String,
+    pub search_limit: i64,
+    pub match_text: String,
+    pub match_file: PathBuf,
+    pub match_column: String,
+    pub max_candidates: i64,
+    pub enrich_file: PathBuf,
+    pub id_column: String,
+    pub enrich_fields: Vec<String>,
+    pub server_host: String,
+    pub server_port: u16,
+    pub filter_country: String,
+    pub filter_org_type: String,
+    pub filter_root: String,
     pub flags: Flags,
 }
 
@@ -192,6 +221,35 @@ pub fn combine_args(cli_pars: CliPars, config: Config) -> Result<InitParams, App
         data_version,
         data_date,
         double_quotes,
+        output_format: cli_pars.output_format,
+        report_format: cli_pars.report_format,
+        compare_versions: cli_pars.compare_versions,
+        change_threshold: cli_pars.change_threshold,
+        ror_json_mode: cli_pars.ror_json_mode,
+        rdf_format: cli_pars.rdf_format,
+        graph_format: cli_pars.graph_format,
+        geojson_mode: cli_pars.geojson_mode,
+        export_tables: cli_pars.export_tables,
+        delimiter: cli_pars.delimiter,
+        ndjson_index: cli_pars.ndjson_index,
+        id_field: cli_pars.id_field,
+        org_ids: cli_pars.org_ids,
+        org_format: cli_pars.org_format,
+        search_text: cli_pars.search_text,
+        filter_status: cli_pars.filter_status,
+        search_limit: cli_pars.search_limit,
+        match_text: cli_pars.match_text,
+        match_file: cli_pars.match_file,
+        match_column: cli_pars.match_column,
+        max_candidates: cli_pars.max_candidates,
+        enrich_file: cli_pars.enrich_file,
+        id_column: cli_pars.id_column,
+        enrich_fields: cli_pars.enrich_fields,
+        server_host: cli_pars.server_host,
+        server_port: cli_pars.server_port,
+        filter_country: cli_pars.filter_country,
+        filter_org_type: cli_pars.filter_org_type,
+        filter_root: cli_pars.filter_root,
         flags: cli_pars.flags,
     })
 
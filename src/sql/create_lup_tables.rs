@@ -1,3 +1,9 @@
+// The ROR organisation types, in the order of their ids in lup.ror_org_types (100 to 900),
+// excluding 'none' (1000), which is only used internally.
+
+pub const ORG_TYPES: [&str; 9] = ["government", "education", "healthcare", "company", "nonprofit", "funder",
+    "facility", "archive", "other"];
+
 
 pub fn get_sql<'a>() -> &'a str {
 
@@ -1,3 +1,8 @@
+// The ppr tables holding the processed data for each organisation, as exported by --tables.
+
+pub const PPR_TABLES: [&str; 9] = ["core_data", "names", "locations", "external_ids", "links",
+                                  "type", "relationships", "domains", "admin_data"];
+
 
 pub fn get_sql<'a>() -> &'a str {
 
@@ -202,10 +207,60 @@ pub fn get_sql<'a>() -> &'a str {
     );
     create index dup_names_idx on rec.dup_names(id);
 
+    -- pairs of organisations that may represent the same institution,
+    -- with the evidence found for each pair and an overall score
+
+    drop table if exists rec.candidate_duplicates;
+    create table rec.candidate_duplicates
+    (
+        id                int         Generated always as identity (START WITH 10001 INCREMENT BY 1) Primary Key
+      , id1               varchar     not null
+      , ror_name1         varchar     not null
+      , country_code1     varchar     null
+      , id2               varchar     not null
+      , ror_name2         varchar     not null
+      , country_code2     varchar     null
+      , shared_names      varchar     null
+      , shared_ext_ids    varchar     null
+      , shared_domains    varchar     null
+      , shared_websites   varchar     null
+      , distance_km       real        null
+      , existing_rel      varchar     null
+      , score             int         not null default 0
+    );
+    create index candidate_duplicates_idx on rec.candidate_duplicates(id1, id2);
+
+    drop table if exists rec.acronym_checks;
+    create table rec.acronym_checks
+    (
+        ident             int         not null
+      , id                varchar     not null
+      , acronym           varchar     not null
+      , lang              varchar     null
+      , result_id         int         not null
+      , result            varchar     not null
+      , matched_name      varchar     null
+    );
+    create index acronym_checks_idx on rec.acronym_checks(id);
+
+    drop table if exists rec.date_anomalies;
+    create table rec.date_anomalies
+    (
+        id                varchar     not null
+      , ror_name          varchar     not null
+      , established       int         null
+      , created           date        null
+      , last_modified     date        null
+      , anomaly_id        int         not null
+      , anomaly           varchar     not null
+    );
+    create index date_anomalies_idx on rec.date_anomalies(id);
+
     -- rec.names has an orig_value and display_value columns, as some 
     -- original values are changed (to correct errors, make them more consistent) 
     -- before further processing. The change_type and change_type_id
-    -- columns are used to record the nature of any changes
+    -- columns are used to record the nature of any changes, the qflag_ids
+    -- and qflags columns any possible issues found by the name quality checks
     
     drop table if exists rec.names;
     create table rec.names
@@ -228,6 +283,8 @@ pub fn get_sql<'a>() -> &'a str {
       , changed           bool        not null  default false
       , change_type_id    varchar     null
       , change_type       varchar     null
+      , qflag_ids         varchar     null
+      , qflags            varchar     null
     );
     create index rec_names_idx on rec.names(id);
 
@@ -109,7 +109,136 @@ pub fn get_sql<'a>() -> &'a str {
       , names_wolc_pc     real        null
     );
     
+    -- Tables added in later versions, created (if they do not already exist) by
+    -- get_added_tables_sql, which is also run at the start of each summary.
+
+    drop table if exists smm.name_quality_flags;
+    drop table if exists smm.acronym_checks;
+    drop table if exists smm.established_years;
+    drop table if exists smm.last_modified_months;
+    drop table if exists smm.country_summaries;
+    
     SET client_min_messages TO NOTICE;"#
 }
 
 
+pub fn get_added_tables_sql<'a>() -> &'a str {
+
+    // The smm tables added after the original set. They are created, if not already present,
+    // whenever summary data is stored, so that an existing installation gains them without
+    // -m having to be used (which would remove the summary data of earlier versions).
+
+    r#"SET client_min_messages TO WARNING; 
+    create schema if not exists smm;
+    
+    create table if not exists smm.name_quality_flags
+    (    
+        vcode             varchar     not null
+      , inc_wd            bool        not null
+      , flag_id           int         not null
+      , flag              varchar     not null
+      , num_names         int         null
+      , pc_of_names       real        null
+      , num_orgs          int         null
+      , pc_of_orgs        real        null
+    );
+    
+    create table if not exists smm.acronym_checks
+    (    
+        vcode             varchar     not null
+      , inc_wd            bool        not null
+      , grouping          varchar     not null
+      , group_code        varchar     null
+      , group_name        varchar     null
+      , num_acronyms      int         null
+      , num_derivable     int         null
+      , num_partial       int         null
+      , num_unexplained   int         null
+      , num_no_comp_names int         null
+      , pc_unexplained    real        null
+    );
+    
+    create table if not exists smm.established_years
+    (    
+        vcode             varchar     not null
+      , inc_wd            bool        not null
+      , grouping          varchar     not null
+      , group_code        varchar     null
+      , group_name        varchar     null
+      , num_orgs          int         null
+      , num_with_year     int         null
+      , pc_with_year      real        null
+      , earliest          int         null
+      , quartile_1        int         null
+      , median            int         null
+      , quartile_3        int         null
+      , latest            int         null
+      , num_pre_1800      int         null
+      , num_1800_1899     int         null
+      , num_1900_1949     int         null
+      , num_1950_1999     int         null
+      , num_2000_on       int         null
+    );
+    
+    create table if not exists smm.last_modified_months
+    (    
+        vcode             varchar     not null
+      , inc_wd            bool        not null
+      , lm_month          varchar     not null
+      , lm_schema         varchar     not null
+      , num_orgs          int         null
+      , pc_of_orgs        real        null
+    );
+    
+    create table if not exists smm.country_summaries
+    (    
+        vcode             varchar     not null
+      , inc_wd            bool        not null
+      , country_code      varchar     not null
+      , country_name      varchar     null
+      , num_orgs          int         null
+      , pc_of_orgs        real        null
+      , num_active        int         null
+      , num_inactive      int         null
+      , num_withdrawn     int         null
+      , num_government    int         null
+      , num_education     int         null
+      , num_healthcare    int         null
+      , num_company       int         null
+      , num_nonprofit     int         null
+      , num_funder        int         null
+      , num_facility      int         null
+      , num_archive       int         null
+      , num_other         int         null
+      , num_names         int         null
+      , num_labels        int         null
+      , num_aliases       int         null
+      , num_acronyms      int         null
+      , num_names_wolc    int         null
+      , pc_names_wlc      real        null
+      , num_nacro         int         null
+      , num_nacro_wolc    int         null
+      , pc_nacro_wlc      real        null
+      , num_with_ext_ids  int         null
+      , pc_with_ext_ids   real        null
+      , num_with_isni     int         null
+      , num_with_grid     int         null
+      , num_with_fundref  int         null
+      , num_with_wikidata int         null
+      , num_with_links    int         null
+      , pc_with_links     real        null
+      , num_with_website  int         null
+      , num_with_wikipedia int        null
+      , num_parent_rels   int         null
+      , num_child_rels    int         null
+      , num_related_rels  int         null
+      , num_pred_rels     int         null
+      , num_succ_rels     int         null
+      , num_with_rels     int         null
+      , pc_with_rels      real        null
+      , num_with_domains  int         null
+      , pc_with_domains   real        null
+    );
+    
+    SET client_min_messages TO NOTICE;"#
+}
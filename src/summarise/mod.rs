@@ -11,6 +11,17 @@ use log::info;
 pub async fn create_smm_tables(pool: &Pool<Postgres>) -> Result<(), AppError>
 {
     let sql = create_smm_tables::get_sql();
+    sqlx::raw_sql(sql).execute(pool)
+        .await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    create_added_smm_tables(pool).await
+}
+
+pub async fn create_added_smm_tables(pool: &Pool<Postgres>) -> Result<(), AppError>
+{
+    // Creates any of the later smm tables not yet present, leaving existing data untouched.
+
+    let sql = create_smm_tables::get_added_tables_sql();
     sqlx::raw_sql(sql).execute(pool)
         .await
         .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
@@ -32,9 +43,11 @@ pub async fn store_summary_data (params: &InitParams, pool: &Pool<Postgres>) ->
     let inc_wd = fp.inc_wd;
     let sdv = format!("select '{}' as vcode, ",  vcode);   // common beginning of sql statements
     
-    // Delete existing data in smm. tables for this version and construct the 
+    // Ensure any smm tables added since the installation was set up exist, then
+    // delete existing data in smm. tables for this version and construct the 
     // initial version summary table by obtaining record counts of all ppr tables.                  
 
+    create_added_smm_tables(pool).await?;
     smm_helper::delete_any_existing_data(&vcode, inc_wd, pool).await?;
 
     // Get status breakdown.
@@ -88,6 +101,10 @@ pub async fn store_summary_data (params: &InitParams, pool: &Pool<Postgres>) ->
 
     smm_helper::create_type_linked_tables(&sdv, inc_wd, pool).await?;
     smm_helper::store_singletons(&vcode, inc_wd, num_denom, num_names, pool).await?;
+    smm_helper::store_name_quality_flags(&vcode, inc_wd, num_denom, num_names, pool).await?;
+    smm_helper::store_acronym_checks(&vcode, inc_wd, pool).await?;
+    smm_helper::create_date_summaries(&vcode, &sdv, inc_wd, num_denom, pool).await?;
+    smm_helper::store_country_summaries(&vcode, inc_wd, num_denom, pool).await?;
     info!("All summary data transferred to smm tables"); 
     
     Ok(())
@@ -12,8 +12,13 @@ pub async fn delete_any_existing_data(vcode: &String, inc_wd: bool, pool: &Pool<
                 DELETE from smm.ranked_distributions {}
                 DELETE from smm.singletons {}
                 DELETE from smm.org_type_and_lang_code {}
-                DELETE from smm.org_type_and_relationships {}"#
-                , wc, wc, wc, wc, wc, wc, wc);
+                DELETE from smm.org_type_and_relationships {}
+                DELETE from smm.name_quality_flags {}
+                DELETE from smm.acronym_checks {}
+                DELETE from smm.established_years {}
+                DELETE from smm.last_modified_months {}
+                DELETE from smm.country_summaries {}"#
+                , wc, wc, wc, wc, wc, wc, wc, wc, wc, wc, wc, wc);
 
    sqlx::raw_sql(&del_sql).execute(pool).await
          .map_err(|e| AppError::SqlxError(e, del_sql.to_string()))
@@ -669,12 +674,241 @@ pub async fn store_singletons(vcode: &String, inc_wd: bool, num_denom: i64, num_
     sings.add(vcode, inc_wd, 41, "poly_subdivs", "Orgs in more than one ‘state’, number & pc of orgs", num_poly_subdivs, Some(pc_poly_subdivs));
     sings.add(vcode, inc_wd, 42, "poly_countries", "Orgs in more than one country, number & pc of orgs", num_poly_countries, Some(pc_poly_countries));
 
+    // Established year anomalies
+
+    let num_est_future = get_count(r#"select count(id) from rec.date_anomalies where anomaly_id = 1"#, pool).await?;
+    let num_est_pre_1000 = get_count(r#"select count(id) from rec.date_anomalies where anomaly_id = 2"#, pool).await?;
+    let num_est_post_created = get_count(r#"select count(id) from rec.date_anomalies where anomaly_id = 3"#, pool).await?;
+    let pc_est_future = get_pc(num_est_future, num_denom);
+    let pc_est_pre_1000 = get_pc(num_est_pre_1000, num_denom);
+    let pc_est_post_created = get_pc(num_est_post_created, num_denom);
+
+    sings.add(vcode, inc_wd, 60, "est_future", "Orgs established after the data date, number & pc of orgs", num_est_future, Some(pc_est_future));
+    sings.add(vcode, inc_wd, 61, "est_pre_1000", "Orgs established before 1000, number & pc of orgs", num_est_pre_1000, Some(pc_est_pre_1000));
+    sings.add(vcode, inc_wd, 62, "est_post_created", "Orgs established after the ROR record was created, number & pc of orgs", num_est_post_created, Some(pc_est_post_created));
+
     sings.store(pool).await?;
     
     Ok(())
 }
 
 
+pub async fn store_name_quality_flags(vcode: &String, inc_wd: bool, num_denom: i64, num_names: i64, pool: &Pool<Postgres>) -> Result<PgQueryResult, AppError> {
+
+    // Flags are held in rec.names as matching comma separated lists of ids and names.
+    // Only names of organisations still in the ppr tables are counted.
+
+    let sql = format!(r#"INSERT into smm.name_quality_flags (vcode, inc_wd, flag_id, flag, 
+                num_names, pc_of_names, num_orgs, pc_of_orgs)
+                select '{vcode}', {inc_wd}, f.flag_id::int, f.flag, 
+                count(n.ident), ROUND(count(n.ident)*10000::float / {num_names})/100.0,
+                count(distinct n.id), ROUND(count(distinct n.id)*10000::float / {num_denom})/100.0
+                from rec.names n
+                cross join unnest(string_to_array(n.qflag_ids, ', '), string_to_array(n.qflags, ', ')) as f(flag_id, flag)
+                where exists (select 1 from ppr.core_data c where c.id = n.id)
+                group by f.flag_id::int, f.flag
+                order by f.flag_id::int;"#);
+
+    sqlx::raw_sql(&sql).execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
+
+
+pub async fn store_acronym_checks(vcode: &String, inc_wd: bool, pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    // The results of the acronym checks, by country and by organisation type. Organisations with 
+    // more than one country or type are included in the totals of each. 
+
+    let core_sql = format!(r#"INSERT into smm.acronym_checks (vcode, inc_wd, grouping, group_code, group_name, 
+                num_acronyms, num_derivable, num_partial, num_unexplained, num_no_comp_names, pc_unexplained)
+                select '{vcode}', {inc_wd}, "#);
+    let counts_sql = r#" count(a.ident), 
+                count(a.ident) filter (where a.result_id = 1), 
+                count(a.ident) filter (where a.result_id = 2),
+                count(a.ident) filter (where a.result_id = 3), 
+                count(a.ident) filter (where a.result_id = 4), 
+                ROUND(count(a.ident) filter (where a.result_id = 3)*10000::float / count(a.ident))/100.0
+                from rec.acronym_checks a "#;
+
+    let sql = format!(r#"{core_sql} 'country', l.country_code, l.country_name, {counts_sql}
+                inner join (select distinct id, country_code, country_name from ppr.locations) l
+                on a.id = l.id
+                group by l.country_code, l.country_name
+                order by l.country_code;"#);
+    sqlx::raw_sql(&sql).execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let sql = format!(r#"{core_sql} 'org type', t.org_type::varchar, o.name, {counts_sql}
+                inner join ppr.type t
+                on a.id = t.id
+                inner join lup.ror_org_types o
+                on t.org_type = o.id
+                group by t.org_type, o.name
+                order by t.org_type;"#);
+    sqlx::raw_sql(&sql).execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    Ok(())
+}
+
+
+pub async fn create_date_summaries(vcode: &String, sdv: &str, inc_wd: bool, num_denom: i64, pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    // Distributions of established years, for all organisations, by organisation type and by country.
+    // Organisations with more than one country or type are included in the numbers of each.
+
+    let core_sql = format!(r#"INSERT into smm.established_years (vcode, inc_wd, grouping, group_code, group_name, 
+                num_orgs, num_with_year, pc_with_year, earliest, quartile_1, median, quartile_3, latest,
+                num_pre_1800, num_1800_1899, num_1900_1949, num_1950_1999, num_2000_on)
+                select '{vcode}', {inc_wd}, "#);
+    let counts_sql = r#" count(c.id), count(c.established), 
+                ROUND(count(c.established)*10000::float / count(c.id))/100.0,
+                min(c.established),
+                percentile_disc(0.25) within group (order by c.established),
+                percentile_disc(0.5) within group (order by c.established),
+                percentile_disc(0.75) within group (order by c.established),
+                max(c.established),
+                count(c.id) filter (where c.established < 1800),
+                count(c.id) filter (where c.established between 1800 and 1899),
+                count(c.id) filter (where c.established between 1900 and 1949),
+                count(c.id) filter (where c.established between 1950 and 1999),
+                count(c.id) filter (where c.established >= 2000)
+                from ppr.core_data c "#;
+
+    let sql = format!(r#"{core_sql} 'all', null, 'all organisations', {counts_sql};"#);
+    sqlx::raw_sql(&sql).execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let sql = format!(r#"{core_sql} 'org type', t.org_type::varchar, o.name, {counts_sql}
+                inner join ppr.type t
+                on c.id = t.id
+                inner join lup.ror_org_types o
+                on t.org_type = o.id
+                group by t.org_type, o.name
+                order by t.org_type;"#);
+    sqlx::raw_sql(&sql).execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let sql = format!(r#"{core_sql} 'country', l.country_code, l.country_name, {counts_sql}
+                inner join (select distinct id, country_code, country_name from ppr.locations) l
+                on c.id = l.id
+                group by l.country_code, l.country_name
+                order by l.country_code;"#);
+    sqlx::raw_sql(&sql).execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    // Numbers of records last modified in each month, split by the schema version of that modification.
+
+    let sql = format!(r#"INSERT into smm.last_modified_months (vcode, inc_wd, lm_month, lm_schema, num_orgs, pc_of_orgs)
+                select '{vcode}', {inc_wd}, to_char(last_modified, 'YYYY-MM'), lm_schema, count(id),
+                ROUND(count(id)*10000::float / {num_denom})/100.0
+                from ppr.admin_data
+                group by to_char(last_modified, 'YYYY-MM'), lm_schema
+                order by to_char(last_modified, 'YYYY-MM'), lm_schema;"#);
+    sqlx::raw_sql(&sql).execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    // Schema versions used in the created and last modified admin data, as attributes of each organisation.
+
+    let sql = format!(r#"{sdv} (dense_rank() over (order by cr_schema))::int as cat_id, cr_schema as cat_name, 
+            count(id) as number_cat,
+            round(count(id)*10000::float/{num_denom})/100.0 as pc_of_atts,
+            count(id) as number_orgs,
+            round(count(id)*10000::float/{num_denom})/100.0 as pc_of_orgs
+            from ppr.admin_data
+            group by cr_schema
+            order by cr_schema;"#);
+    let rows: Vec<TypeRow> = sqlx::query_as(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    store_summary(rows, inc_wd, 15, "created schemas", pool).await?;
+
+    let sql = format!(r#"{sdv} (dense_rank() over (order by lm_schema))::int as cat_id, lm_schema as cat_name, 
+            count(id) as number_cat,
+            round(count(id)*10000::float/{num_denom})/100.0 as pc_of_atts,
+            count(id) as number_orgs,
+            round(count(id)*10000::float/{num_denom})/100.0 as pc_of_orgs
+            from ppr.admin_data
+            group by lm_schema
+            order by lm_schema;"#);
+    let rows: Vec<TypeRow> = sqlx::query_as(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    store_summary(rows, inc_wd, 16, "last modified schemas", pool).await?;
+
+    Ok(())
+}
+
+
+pub async fn store_country_summaries(vcode: &String, inc_wd: bool, num_denom: i64, pool: &Pool<Postgres>) -> Result<PgQueryResult, AppError> {
+
+    // For each country, the numbers of organisations, their types, names, external ids, links, 
+    // relationships and domains, mostly derived from the counts in ppr.admin_data. 
+    // Organisations with locations in more than one country are included in the numbers of each.
+
+    let sql = format!(r#"INSERT into smm.country_summaries (vcode, inc_wd, country_code, country_name, 
+                num_orgs, pc_of_orgs, num_active, num_inactive, num_withdrawn,
+                num_government, num_education, num_healthcare, num_company, num_nonprofit, 
+                num_funder, num_facility, num_archive, num_other,
+                num_names, num_labels, num_aliases, num_acronyms, num_names_wolc, pc_names_wlc, 
+                num_nacro, num_nacro_wolc, pc_nacro_wlc,
+                num_with_ext_ids, pc_with_ext_ids, num_with_isni, num_with_grid, num_with_fundref, num_with_wikidata,
+                num_with_links, pc_with_links, num_with_website, num_with_wikipedia,
+                num_parent_rels, num_child_rels, num_related_rels, num_pred_rels, num_succ_rels, 
+                num_with_rels, pc_with_rels, num_with_domains, pc_with_domains)
+            select '{vcode}', {inc_wd}, l.country_code, l.country_name,
+                count(a.id), round(count(a.id)*10000::float/{num_denom})/100.0,
+                count(a.id) filter (where c.status = 1),
+                count(a.id) filter (where c.status = 2),
+                count(a.id) filter (where c.status = 3),
+                count(a.id) filter (where t.is_gov),
+                count(a.id) filter (where t.is_edu),
+                count(a.id) filter (where t.is_health),
+                count(a.id) filter (where t.is_comp),
+                count(a.id) filter (where t.is_nonprofit),
+                count(a.id) filter (where t.is_funder),
+                count(a.id) filter (where t.is_facility),
+                count(a.id) filter (where t.is_archive),
+                count(a.id) filter (where t.is_other),
+                sum(a.n_names), sum(a.n_labels), sum(a.n_aliases), sum(a.n_acronyms), sum(a.n_names_wolc),
+                round((sum(a.n_names) - sum(a.n_names_wolc))*10000::float/greatest(sum(a.n_names), 1))/100.0,
+                sum(a.n_nacro), sum(a.n_nacro_wolc),
+                round((sum(a.n_nacro) - sum(a.n_nacro_wolc))*10000::float/greatest(sum(a.n_nacro), 1))/100.0,
+                count(a.id) filter (where a.n_ext_ids > 0),
+                round((count(a.id) filter (where a.n_ext_ids > 0))*10000::float/count(a.id))/100.0,
+                count(a.id) filter (where a.n_isni > 0),
+                count(a.id) filter (where a.n_grid > 0),
+                count(a.id) filter (where a.n_fundref > 0),
+                count(a.id) filter (where a.n_wikidata > 0),
+                count(a.id) filter (where a.n_links > 0),
+                round((count(a.id) filter (where a.n_links > 0))*10000::float/count(a.id))/100.0,
+                count(a.id) filter (where a.n_website > 0),
+                count(a.id) filter (where a.n_wikipedia > 0),
+                sum(a.n_parrels), sum(a.n_chrels), sum(a.n_relrels), sum(a.n_predrels), sum(a.n_sucrels),
+                count(a.id) filter (where a.n_parrels + a.n_chrels + a.n_relrels + a.n_predrels + a.n_sucrels > 0),
+                round((count(a.id) filter (where a.n_parrels + a.n_chrels + a.n_relrels + a.n_predrels + a.n_sucrels > 0))*10000::float/count(a.id))/100.0,
+                count(a.id) filter (where a.n_doms > 0),
+                round((count(a.id) filter (where a.n_doms > 0))*10000::float/count(a.id))/100.0
+            from ppr.admin_data a
+            inner join ppr.core_data c
+            on a.id = c.id
+            inner join (select distinct id, country_code, country_name from ppr.locations) l
+            on a.id = l.id
+            left join 
+                (select id, 
+                 bool_or(org_type = 100) as is_gov, bool_or(org_type = 200) as is_edu,
+                 bool_or(org_type = 300) as is_health, bool_or(org_type = 400) as is_comp,
+                 bool_or(org_type = 500) as is_nonprofit, bool_or(org_type = 600) as is_funder,
+                 bool_or(org_type = 700) as is_facility, bool_or(org_type = 800) as is_archive,
+                 bool_or(org_type = 900) as is_other
+                 from ppr.type
+                 group by id) t
+            on a.id = t.id
+            group by l.country_code, l.country_name
+            order by l.country_code;"#);
+
+    sqlx::raw_sql(&sql).execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
+
+
 pub async fn get_count (sql_string: &str, pool: &Pool<Postgres>) -> Result<i64, AppError> {
      sqlx::query_scalar(sql_string)
         .fetch_one(pool).await
@@ -0,0 +1,81 @@
+// Text normalisation shared by the processing, lookup and matching code: the folding of
+// accented characters, and the stop words ignored when names are compared.
+
+// The more common diacritics on (lower case) Latin characters, and the characters they are
+// folded to. Also used to generate the equivalent sql translate call, in lookup::name_search.
+
+pub const FOLDS: [(&str, char); 16] = [("àáâãäåāăą", 'a'), ("çćĉċč", 'c'), ("ďđ", 'd'), ("èéêëēėęě", 'e'),
+    ("ĝğġģ", 'g'), ("ìíîïīįı", 'i'), ("ķ", 'k'), ("ĺļľł", 'l'), ("ñńņň", 'n'), ("òóôõöøōő", 'o'),
+    ("ŕř", 'r'), ("śşšș", 's'), ("ţťț", 't'), ("ùúûüūůűų", 'u'), ("ýÿ", 'y'), ("źżž", 'z')];
+
+// The stop words removed from match names, with the change type recorded for each (see
+// add_scripts::prepare_match_names). Also removed from search text compared with match names.
+
+pub const MATCH_STOP_WORDS: [(&str, i32); 13] = [("and", 520), ("et", 521), ("und", 522), ("y", 523),
+    ("of", 524), ("the", 525), ("for", 526), ("de", 527), ("le", 528), ("la", 529), ("les", 530),
+    ("des", 531), ("del", 532)];
+
+
+pub fn fold_char(c: char) -> char {
+
+    // Removes the more common diacritics from (lower case) Latin characters.
+
+    if c.is_ascii() {
+        return c;
+    }
+    FOLDS.iter().find(|(cs, _)| cs.contains(c)).map(|(_, f)| *f).unwrap_or(c)
+}
+
+
+pub fn fold_lower(s: &str) -> String {
+    s.chars().flat_map(|c| c.to_lowercase()).map(fold_char).collect()
+}
+
+
+pub fn get_stop_words(lang: Option<&str>) -> Vec<&'static str> {
+
+    // Stop words are in lower case, with accents removed. If the language is
+    // not known (or not listed) the stop words of all the listed languages are used.
+
+    let en = vec!["of", "the", "and", "for", "at", "in", "on"];
+    let fr = vec!["de", "du", "des", "la", "le", "les", "l", "d", "et", "en", "pour", "a", "au", "aux", "sur"];
+    let de = vec!["fur", "und", "der", "die", "das", "des", "dem", "den", "zu", "zur", "zum", "von", "im", "am"];
+    let es = vec!["de", "del", "la", "las", "el", "los", "y", "e", "en", "para"];
+    let pt = vec!["de", "da", "do", "das", "dos", "e", "em", "para"];
+    let it = vec!["di", "del", "della", "dei", "degli", "delle", "e", "ed", "per", "la", "il", "lo", "l", "d", "in"];
+    let nl = vec!["van", "de", "het", "en", "voor", "der"];
+
+    match lang {
+        Some("en") => en,
+        Some("fr") => fr,
+        Some("de") => de,
+        Some("es") => es,
+        Some("pt") => pt,
+        Some("it") => it,
+        Some("nl") => nl,
+        _ => [en, fr, de, es, pt, it, nl].concat(),
+    }
+}
+
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_folding_of_accented_names() {
+        assert_eq!(fold_lower("École Polytechnique Fédérale"), "ecole polytechnique federale");
+        assert_eq!(fold_lower("Łódź"), "lodz");
+        assert_eq!(fold_lower("Universität Zürich"), "universitat zurich");
+        assert_eq!(fold_lower("東京大学"), "東京大学");
+    }
+
+    #[test]
+    fn check_stop_words_by_language() {
+        assert!(get_stop_words(Some("de")).contains(&"fur"));
+        assert!(!get_stop_words(Some("en")).contains(&"de"));
+        assert!(get_stop_words(Some("xx")).contains(&"de"));
+    }
+}